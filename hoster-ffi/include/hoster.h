@@ -0,0 +1,116 @@
+#ifndef HOSTER_H
+#define HOSTER_H
+
+/* Generated by cbindgen from hoster-ffi/src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Outcome of a call into the library.
+typedef enum HosterStatus {
+  HOSTER_STATUS_OK = 0,
+  // A required pointer argument was NULL.
+  HOSTER_STATUS_NULL_ARGUMENT,
+  // A string argument or the parsed text is not valid UTF-8.
+  HOSTER_STATUS_INVALID_UTF8,
+  // The text is not a valid hosts file.
+  HOSTER_STATUS_PARSE_ERROR,
+  HOSTER_STATUS_INVALID_ADDRESS,
+  HOSTER_STATUS_INVALID_HOSTNAME,
+  // A comment holds a line break.
+  HOSTER_STATUS_INVALID_COMMENT,
+  // The host is not in the file.
+  HOSTER_STATUS_NOT_FOUND,
+  // The result holds a NUL byte and cannot be returned as a C string.
+  HOSTER_STATUS_INTERIOR_NUL,
+  // The library panicked. The handle passed in should not be used again.
+  HOSTER_STATUS_PANIC,
+} HosterStatus;
+
+// A parsed hosts file.
+typedef struct HosterFile HosterFile;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses `len` bytes of `text` and stores a handle to the result in
+// `*out`. A leading byte order mark is skipped.
+//
+// # Safety
+//
+// `text` must point to `len` readable bytes, and `out` to writable memory
+// for one pointer. The handle must be released with [`hoster_free`].
+enum HosterStatus hoster_parse(const uint8_t *text, size_t len, struct HosterFile **out);
+
+// Releases a handle returned by [`hoster_parse`]. NULL is ignored.
+//
+// # Safety
+//
+// `file` must be NULL or a handle that has not been freed yet.
+void hoster_free(struct HosterFile *file);
+
+// Stores in `*out_ip` the address of the first entry for `host`. Hostnames
+// are compared by their canonical form.
+//
+// # Safety
+//
+// `file` must be a live handle, `host` a NUL-terminated string and
+// `out_ip` writable memory for one pointer. The address must be released
+// with [`hoster_string_free`].
+enum HosterStatus hoster_lookup(const struct HosterFile *file, const char *host, char **out_ip);
+
+// Appends an entry mapping `host` to `ip` at the end of the file. The host
+// is stored in its canonical form. When `comment` is not NULL, it is
+// written on a comment line above the entry, as the text after `#`.
+//
+// # Safety
+//
+// `file` must be a live handle; `ip`, `host` and `comment`, unless NULL,
+// NUL-terminated strings.
+enum HosterStatus hoster_add(struct HosterFile *file,
+                             const char *ip,
+                             const char *host,
+                             const char *comment);
+
+// Removes every occurrence of `host`, dropping the lines left without
+// hosts, and stores how many were removed in `*removed` unless it is NULL.
+// Fails with [`HosterStatus::NotFound`] when there was none.
+//
+// # Safety
+//
+// `file` must be a live handle, `host` a NUL-terminated string and
+// `removed` NULL or writable memory for one `size_t`.
+enum HosterStatus hoster_remove(struct HosterFile *file, const char *host, size_t *removed);
+
+// Stores the text of the file in `*out`.
+//
+// # Safety
+//
+// `file` must be a live handle and `out` writable memory for one pointer.
+// The text must be released with [`hoster_string_free`].
+enum HosterStatus hoster_serialize(const struct HosterFile *file, char **out);
+
+// Releases a string returned by the library. NULL is ignored.
+//
+// # Safety
+//
+// `string` must be NULL or a string returned by the library that has not
+// been freed yet.
+void hoster_string_free(char *string);
+
+// Message of the last call that failed on this thread, NULL if none did.
+// The string stays valid until the next failing call on the thread.
+const char *hoster_last_error_message(void);
+
+// Static description of `status`.
+const char *hoster_status_message(enum HosterStatus status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HOSTER_H */
@@ -0,0 +1,468 @@
+//! C bindings for hoster.
+//!
+//! A parsed hosts file lives behind an opaque [`HosterFile`] handle. Every
+//! function returns a [`HosterStatus`]; when it is not
+//! [`HosterStatus::Ok`], [`hoster_last_error_message`] describes what went
+//! wrong. Strings handed out by the library are owned by the caller and
+//! released with [`hoster_string_free`].
+//!
+//! The header in `include/hoster.h` is generated from this file with
+//! cbindgen.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::net::IpAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+use hoster::cst::Cst;
+use hoster::hostname;
+use hoster::parser::{self, Parser};
+use hoster::ErrorKind;
+
+/// Outcome of a call into the library.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HosterStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL.
+    NullArgument,
+    /// A string argument or the parsed text is not valid UTF-8.
+    InvalidUtf8,
+    /// The text is not a valid hosts file.
+    ParseError,
+    InvalidAddress,
+    InvalidHostname,
+    /// A comment holds a line break.
+    InvalidComment,
+    /// The host is not in the file.
+    NotFound,
+    /// The result holds a NUL byte and cannot be returned as a C string.
+    InteriorNul,
+    /// The library panicked. The handle passed in should not be used again.
+    Panic,
+}
+
+/// A parsed hosts file.
+pub struct HosterFile {
+    cst: Cst<1>,
+}
+
+struct Failure {
+    status: HosterStatus,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: HosterStatus, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn null(argument: &str) -> Self {
+        Self::new(
+            HosterStatus::NullArgument,
+            format!("`{}` must not be NULL", argument),
+        )
+    }
+}
+
+impl From<hoster::Error> for Failure {
+    fn from(error: hoster::Error) -> Self {
+        let status = match error.kind() {
+            ErrorKind::Parser(parser::Error::Utf8Error(_)) => HosterStatus::InvalidUtf8,
+            _ => HosterStatus::ParseError,
+        };
+
+        Self::new(status, error.to_string())
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Runs `call`, turning failures and panics into a status and recording
+/// their message for [`hoster_last_error_message`].
+fn run(call: impl FnOnce() -> Result<(), Failure>) -> HosterStatus {
+    let failure = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => return HosterStatus::Ok,
+        Ok(Err(failure)) => failure,
+        Err(_) => Failure::new(HosterStatus::Panic, "hoster panicked"),
+    };
+
+    let message =
+        CString::new(failure.message.replace('\0', "\\0")).expect("NUL bytes were escaped");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+
+    failure.status
+}
+
+unsafe fn str_arg<'a>(value: *const c_char, argument: &str) -> Result<&'a str, Failure> {
+    if value.is_null() {
+        return Err(Failure::null(argument));
+    }
+
+    CStr::from_ptr(value).to_str().map_err(|error| {
+        Failure::new(
+            HosterStatus::InvalidUtf8,
+            format!("`{}` is not valid UTF-8: {}", argument, error),
+        )
+    })
+}
+
+unsafe fn file_ref<'a>(file: *const HosterFile) -> Result<&'a HosterFile, Failure> {
+    file.as_ref().ok_or_else(|| Failure::null("file"))
+}
+
+unsafe fn file_arg<'a>(file: *mut HosterFile) -> Result<&'a mut HosterFile, Failure> {
+    file.as_mut().ok_or_else(|| Failure::null("file"))
+}
+
+fn c_string(value: String) -> Result<*mut c_char, Failure> {
+    CString::new(value).map(CString::into_raw).map_err(|error| {
+        Failure::new(
+            HosterStatus::InteriorNul,
+            format!("result holds a NUL byte at offset {}", error.nul_position()),
+        )
+    })
+}
+
+/// Parses `len` bytes of `text` and stores a handle to the result in
+/// `*out`. A leading byte order mark is skipped.
+///
+/// # Safety
+///
+/// `text` must point to `len` readable bytes, and `out` to writable memory
+/// for one pointer. The handle must be released with [`hoster_free`].
+#[no_mangle]
+pub unsafe extern "C" fn hoster_parse(
+    text: *const u8,
+    len: usize,
+    out: *mut *mut HosterFile,
+) -> HosterStatus {
+    run(|| {
+        if out.is_null() {
+            return Err(Failure::null("out"));
+        }
+
+        let bytes = match text.is_null() {
+            true if len == 0 => &[][..],
+            true => return Err(Failure::null("text")),
+            false => slice::from_raw_parts(text, len),
+        };
+        let cst = Parser::<()>::builder().build().parse_bytes(bytes)?;
+
+        *out = Box::into_raw(Box::new(HosterFile { cst }));
+        Ok(())
+    })
+}
+
+/// Releases a handle returned by [`hoster_parse`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `file` must be NULL or a handle that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hoster_free(file: *mut HosterFile) {
+    if !file.is_null() {
+        drop(Box::from_raw(file));
+    }
+}
+
+/// Stores in `*out_ip` the address of the first entry for `host`. Hostnames
+/// are compared by their canonical form.
+///
+/// # Safety
+///
+/// `file` must be a live handle, `host` a NUL-terminated string and
+/// `out_ip` writable memory for one pointer. The address must be released
+/// with [`hoster_string_free`].
+#[no_mangle]
+pub unsafe extern "C" fn hoster_lookup(
+    file: *const HosterFile,
+    host: *const c_char,
+    out_ip: *mut *mut c_char,
+) -> HosterStatus {
+    run(|| {
+        let file = file_ref(file)?;
+        let host = str_arg(host, "host")?;
+
+        if out_ip.is_null() {
+            return Err(Failure::null("out_ip"));
+        }
+
+        let ip = file
+            .cst
+            .entries()
+            .find(|(_, name)| hostname::eq(name, host))
+            .map(|(ip, _)| ip)
+            .ok_or_else(|| Failure::new(HosterStatus::NotFound, format!("`{}` not found", host)))?;
+
+        *out_ip = c_string(ip.to_string())?;
+        Ok(())
+    })
+}
+
+/// Appends an entry mapping `host` to `ip` at the end of the file. The host
+/// is stored in its canonical form. When `comment` is not NULL, it is
+/// written on a comment line above the entry, as the text after `#`.
+///
+/// # Safety
+///
+/// `file` must be a live handle; `ip`, `host` and `comment`, unless NULL,
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn hoster_add(
+    file: *mut HosterFile,
+    ip: *const c_char,
+    host: *const c_char,
+    comment: *const c_char,
+) -> HosterStatus {
+    run(|| {
+        let file = file_arg(file)?;
+        let ip = str_arg(ip, "ip")?;
+        let host = str_arg(host, "host")?;
+        let comment = match comment.is_null() {
+            true => None,
+            false => Some(str_arg(comment, "comment")?),
+        };
+
+        let ip: IpAddr = ip.parse().map_err(|_| {
+            Failure::new(
+                HosterStatus::InvalidAddress,
+                format!("invalid IP address `{}`", ip),
+            )
+        })?;
+        let host = hostname::normalize(host)
+            .map_err(|error| Failure::new(HosterStatus::InvalidHostname, error.to_string()))?;
+
+        if comment.is_some_and(|comment| comment.contains(['\r', '\n'])) {
+            return Err(Failure::new(
+                HosterStatus::InvalidComment,
+                "comment must fit on one line",
+            ));
+        }
+
+        file.cst.append_entry(ip, [host], comment);
+        Ok(())
+    })
+}
+
+/// Removes every occurrence of `host`, dropping the lines left without
+/// hosts, and stores how many were removed in `*removed` unless it is NULL.
+/// Fails with [`HosterStatus::NotFound`] when there was none.
+///
+/// # Safety
+///
+/// `file` must be a live handle, `host` a NUL-terminated string and
+/// `removed` NULL or writable memory for one `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn hoster_remove(
+    file: *mut HosterFile,
+    host: *const c_char,
+    removed: *mut usize,
+) -> HosterStatus {
+    run(|| {
+        let file = file_arg(file)?;
+        let host = str_arg(host, "host")?;
+        let count = file.cst.remove_host(host);
+
+        if let Some(removed) = removed.as_mut() {
+            *removed = count;
+        }
+
+        match count {
+            0 => Err(Failure::new(
+                HosterStatus::NotFound,
+                format!("`{}` not found", host),
+            )),
+            _ => Ok(()),
+        }
+    })
+}
+
+/// Stores the text of the file in `*out`.
+///
+/// # Safety
+///
+/// `file` must be a live handle and `out` writable memory for one pointer.
+/// The text must be released with [`hoster_string_free`].
+#[no_mangle]
+pub unsafe extern "C" fn hoster_serialize(
+    file: *const HosterFile,
+    out: *mut *mut c_char,
+) -> HosterStatus {
+    run(|| {
+        let file = file_ref(file)?;
+
+        if out.is_null() {
+            return Err(Failure::null("out"));
+        }
+
+        *out = c_string(file.cst.to_string())?;
+        Ok(())
+    })
+}
+
+/// Releases a string returned by the library. NULL is ignored.
+///
+/// # Safety
+///
+/// `string` must be NULL or a string returned by the library that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hoster_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Message of the last call that failed on this thread, NULL if none did.
+/// The string stays valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn hoster_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Static description of `status`.
+#[no_mangle]
+pub extern "C" fn hoster_status_message(status: HosterStatus) -> *const c_char {
+    let message = match status {
+        HosterStatus::Ok => c"ok",
+        HosterStatus::NullArgument => c"null argument",
+        HosterStatus::InvalidUtf8 => c"invalid UTF-8",
+        HosterStatus::ParseError => c"parse error",
+        HosterStatus::InvalidAddress => c"invalid IP address",
+        HosterStatus::InvalidHostname => c"invalid hostname",
+        HosterStatus::InvalidComment => c"invalid comment",
+        HosterStatus::NotFound => c"not found",
+        HosterStatus::InteriorNul => c"interior NUL byte",
+        HosterStatus::Panic => c"panic",
+    };
+
+    message.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    unsafe fn parse(text: &str) -> *mut HosterFile {
+        let mut file = ptr::null_mut();
+        let status = hoster_parse(text.as_ptr(), text.len(), &mut file);
+
+        assert_eq!(HosterStatus::Ok, status);
+        file
+    }
+
+    unsafe fn serialize(file: *mut HosterFile) -> String {
+        let mut out = ptr::null_mut();
+
+        assert_eq!(HosterStatus::Ok, hoster_serialize(file, &mut out));
+
+        let text = CStr::from_ptr(out).to_str().unwrap().to_string();
+        hoster_string_free(out);
+        text
+    }
+
+    unsafe fn last_error() -> &'static str {
+        CStr::from_ptr(hoster_last_error_message())
+            .to_str()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_remove() {
+        unsafe {
+            let file = parse("# dev\n127.0.0.1 a.test b.test\n10.0.0.1\tb.test\n::1 B.test.\n");
+            let mut removed = 0;
+
+            assert_eq!(
+                HosterStatus::Ok,
+                hoster_remove(file, c"b.test".as_ptr(), &mut removed)
+            );
+            assert_eq!(3, removed);
+            assert_eq!("# dev\n127.0.0.1 a.test\n", serialize(file));
+
+            assert_eq!(
+                HosterStatus::NotFound,
+                hoster_remove(file, c"b.test".as_ptr(), &mut removed)
+            );
+            assert_eq!(0, removed);
+            assert_eq!("`b.test` not found", last_error());
+
+            hoster_free(file);
+        }
+    }
+
+    #[test]
+    fn test_add_without_trailing_newline() {
+        unsafe {
+            let file = parse("127.0.0.1 localhost");
+
+            assert_eq!(
+                HosterStatus::Ok,
+                hoster_add(
+                    file,
+                    c"::1".as_ptr(),
+                    c"Bücher.de".as_ptr(),
+                    c" books".as_ptr()
+                )
+            );
+            assert_eq!(
+                "127.0.0.1 localhost\n# books\n::1 xn--bcher-kva.de\n",
+                serialize(file)
+            );
+
+            assert_eq!(
+                HosterStatus::InvalidComment,
+                hoster_add(
+                    file,
+                    c"::1".as_ptr(),
+                    c"a.test".as_ptr(),
+                    c"two\nlines".as_ptr()
+                )
+            );
+
+            hoster_free(file);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            let text = b"127.0.0.1 \xff\n";
+            let mut file = ptr::null_mut();
+
+            assert_eq!(
+                HosterStatus::InvalidUtf8,
+                hoster_parse(text.as_ptr(), text.len(), &mut file)
+            );
+            assert!(file.is_null());
+
+            assert_eq!(
+                HosterStatus::NullArgument,
+                hoster_parse(ptr::null(), 1, &mut file)
+            );
+            assert_eq!("`text` must not be NULL", last_error());
+
+            let file = parse("");
+
+            assert_eq!(
+                HosterStatus::InvalidAddress,
+                hoster_add(file, c"localhost".as_ptr(), c"a.test".as_ptr(), ptr::null())
+            );
+            assert_eq!("invalid IP address `localhost`", last_error());
+
+            hoster_free(file);
+        }
+    }
+}
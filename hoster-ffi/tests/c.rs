@@ -0,0 +1,38 @@
+#![cfg(unix)]
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Builds `tests/smoke.c` with the system C compiler against the header and
+/// the cdylib, then runs it.
+#[test]
+fn test_smoke() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let executable = env::current_exe().unwrap();
+    // Test binaries are built next to the libraries, in `target/<profile>/deps`.
+    let libraries = executable.parent().unwrap();
+    let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("smoke");
+
+    let status = Command::new(env::var_os("CC").unwrap_or_else(|| "cc".into()))
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(root.join("include"))
+        .arg(root.join("tests/smoke.c"))
+        .arg("-o")
+        .arg(&output)
+        .arg("-L")
+        .arg(libraries)
+        .arg(format!("-Wl,-rpath,{}", libraries.display()))
+        .arg("-lhoster_ffi")
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling smoke.c failed");
+
+    // Cargo points the loader at `target/<profile>`, which may hold a stale
+    // copy of the library, so only the rpath is used.
+    let status = Command::new(&output)
+        .env_remove("LD_LIBRARY_PATH")
+        .status()
+        .unwrap();
+    assert!(status.success(), "smoke test failed");
+}
@@ -0,0 +1,31 @@
+use std::fs;
+use std::path::Path;
+
+/// Fails when `include/hoster.h` is out of date. Run with `UPDATE_HEADER=1`
+/// to regenerate it.
+#[test]
+fn test_header_is_up_to_date() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(root.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(root.join("src/lib.rs"))
+        .generate()
+        .unwrap()
+        .write(&mut generated);
+
+    let path = root.join("include/hoster.h");
+
+    if std::env::var_os("UPDATE_HEADER").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+
+    let header = fs::read(&path).unwrap_or_default();
+
+    assert!(
+        header == generated,
+        "include/hoster.h is out of date, rerun with UPDATE_HEADER=1"
+    );
+}
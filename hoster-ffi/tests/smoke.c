@@ -0,0 +1,54 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "hoster.h"
+
+#define CHECK(condition)                                                      \
+  do {                                                                        \
+    if (!(condition)) {                                                       \
+      const char *message = hoster_last_error_message();                      \
+      fprintf(stderr, "%s:%d: check failed: %s (%s)\n", __FILE__, __LINE__,   \
+              #condition, message ? message : "no error");                    \
+      return 1;                                                               \
+    }                                                                         \
+  } while (0)
+
+int main(void) {
+  const char *text = "# local\n127.0.0.1 localhost api.test\n";
+  HosterFile *file = NULL;
+  char *ip = NULL;
+  char *out = NULL;
+  size_t removed = 0;
+
+  CHECK(hoster_parse((const uint8_t *)text, strlen(text), &file) ==
+        HOSTER_STATUS_OK);
+
+  CHECK(hoster_lookup(file, "API.test", &ip) == HOSTER_STATUS_OK);
+  CHECK(strcmp(ip, "127.0.0.1") == 0);
+  hoster_string_free(ip);
+
+  CHECK(hoster_add(file, "::1", "db.test", " database") == HOSTER_STATUS_OK);
+  CHECK(hoster_add(file, "not an ip", "db.test", NULL) ==
+        HOSTER_STATUS_INVALID_ADDRESS);
+  CHECK(strcmp(hoster_last_error_message(),
+               "invalid IP address `not an ip`") == 0);
+
+  CHECK(hoster_remove(file, "api.test", &removed) == HOSTER_STATUS_OK);
+  CHECK(removed == 1);
+  CHECK(hoster_remove(file, "api.test", NULL) == HOSTER_STATUS_NOT_FOUND);
+  CHECK(strcmp(hoster_status_message(HOSTER_STATUS_NOT_FOUND), "not found") ==
+        0);
+
+  const HosterFile *view = file;
+  CHECK(hoster_serialize(view, &out) == HOSTER_STATUS_OK);
+  CHECK(strcmp(out, "# local\n127.0.0.1 localhost\n# database\n::1 db.test\n") ==
+        0);
+  hoster_string_free(out);
+
+  CHECK(hoster_parse((const uint8_t *)"nope localhost\n", 15, &file) ==
+        HOSTER_STATUS_PARSE_ERROR);
+  CHECK(hoster_serialize(NULL, &out) == HOSTER_STATUS_NULL_ARGUMENT);
+
+  hoster_free(file);
+  return 0;
+}
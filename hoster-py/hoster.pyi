@@ -0,0 +1,31 @@
+from os import PathLike
+from typing import Optional, Union
+
+class ParseError(ValueError): ...
+
+class Entry:
+    @property
+    def ip(self) -> str: ...
+    @property
+    def hosts(self) -> list[str]: ...
+    @property
+    def comment(self) -> Optional[str]: ...
+    @property
+    def metadata(self) -> dict[str, str]: ...
+    @property
+    def line(self) -> int: ...
+
+class HostsFile:
+    def __init__(self, text: Union[str, bytes] = "", *, keep_invalid: bool = False) -> None: ...
+    @staticmethod
+    def read(path: Union[str, PathLike[str]], *, keep_invalid: bool = False) -> HostsFile: ...
+    def write(self, path: Union[str, PathLike[str]]) -> None: ...
+    @property
+    def entries(self) -> list[Entry]: ...
+    def lookup(self, host: str) -> Optional[str]: ...
+    def lookup_all(self, host: str) -> list[str]: ...
+    def add(self, ip: str, host: str, comment: Optional[str] = None) -> None: ...
+    def remove(self, host: str) -> int: ...
+    def __contains__(self, host: str) -> bool: ...
+
+def parse(text: Union[str, bytes], *, keep_invalid: bool = False) -> HostsFile: ...
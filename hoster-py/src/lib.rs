@@ -0,0 +1,244 @@
+//! Python bindings for hoster.
+//!
+//! The `hoster` module exposes [`HostsFile`], a parsed hosts file that keeps
+//! its formatting through edits, and [`Entry`], the model of one of its
+//! entry lines. Parse failures raise `hoster.ParseError`.
+
+use std::fs;
+use std::io::{BufWriter, ErrorKind};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use hoster::cst::{Cst, CstNode};
+use hoster::hostname;
+use hoster::metadata::Metadata;
+use hoster::parser::{InvalidLines, Parser};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+create_exception!(
+    hoster,
+    ParseError,
+    PyValueError,
+    "The text is not a valid hosts file."
+);
+
+fn parse_error(error: hoster::Error) -> PyErr {
+    ParseError::new_err(error.to_string())
+}
+
+/// Text of a hosts file, given as `str` or `bytes`.
+#[derive(FromPyObject)]
+enum Text {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl Text {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Text::Str(text) => text.as_bytes(),
+            Text::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// An entry line: an address, the hostnames mapped to it, its trailing
+/// comment and the metadata of its comments.
+#[pyclass(module = "hoster", frozen, eq, get_all, skip_from_py_object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    ip: String,
+    hosts: Vec<String>,
+    comment: Option<String>,
+    /// `key=value` pairs of the comments of the entry.
+    metadata: Metadata,
+    /// 1-based line number.
+    line: usize,
+}
+
+#[pymethods]
+impl Entry {
+    fn __repr__(&self) -> String {
+        format!(
+            "Entry(ip={:?}, hosts={:?}, comment={:?}, metadata={:?}, line={})",
+            self.ip, self.hosts, self.comment, self.metadata, self.line
+        )
+    }
+}
+
+/// A parsed hosts file. Edits keep the rest of the file, comments and
+/// whitespace included, as it was; `str()` gives back its text.
+#[pyclass(module = "hoster")]
+pub struct HostsFile {
+    cst: Cst<1>,
+}
+
+impl HostsFile {
+    fn parse(text: &[u8], keep_invalid: bool) -> PyResult<Self> {
+        let invalid_lines = match keep_invalid {
+            true => InvalidLines::Keep,
+            false => InvalidLines::default(),
+        };
+        let cst = Parser::<()>::builder()
+            .invalid_lines(invalid_lines)
+            .build()
+            .parse_bytes(text)
+            .map_err(parse_error)?;
+
+        Ok(Self { cst })
+    }
+
+    fn addresses<'a>(&'a self, host: &'a str) -> impl Iterator<Item = IpAddr> + 'a {
+        self.cst
+            .entries()
+            .filter(move |(_, name)| hostname::eq(name, host))
+            .map(|(ip, _)| ip)
+    }
+}
+
+#[pymethods]
+impl HostsFile {
+    /// Parses `text`. With `keep_invalid`, lines that do not parse are kept
+    /// verbatim instead of raising `ParseError`.
+    #[new]
+    #[pyo3(signature = (text = Text::Str(String::new()), *, keep_invalid = false))]
+    fn new(text: Text, keep_invalid: bool) -> PyResult<Self> {
+        Self::parse(text.as_bytes(), keep_invalid)
+    }
+
+    /// Reads and parses the file at `path`.
+    #[staticmethod]
+    #[pyo3(signature = (path, *, keep_invalid = false))]
+    fn read(path: std::path::PathBuf, keep_invalid: bool) -> PyResult<Self> {
+        Self::parse(&fs::read(path)?, keep_invalid)
+    }
+
+    /// Writes the file to `path`, through a temporary file in the same
+    /// directory renamed over it, so that a failed write leaves it as it was.
+    /// An existing file keeps its permissions.
+    fn write(&self, path: PathBuf) -> PyResult<()> {
+        let path = match fs::canonicalize(&path) {
+            Ok(path) => path,
+            Err(error) if error.kind() == ErrorKind::NotFound => path,
+            Err(error) => return Err(error.into()),
+        };
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut builder = tempfile::Builder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // As with `open`, the umask applies to new files.
+            builder.permissions(fs::Permissions::from_mode(0o666));
+        }
+
+        let mut writer = BufWriter::new(builder.tempfile_in(directory)?);
+        self.cst.write_to(&mut writer)?;
+        let file = writer.into_inner().map_err(|error| error.into_error())?;
+
+        if let Ok(metadata) = fs::metadata(&path) {
+            file.as_file().set_permissions(metadata.permissions())?;
+        }
+
+        file.as_file().sync_all()?;
+        file.persist(&path).map_err(|error| error.error)?;
+
+        Ok(())
+    }
+
+    /// Every entry, in file order.
+    #[getter]
+    fn entries(&self) -> Vec<Entry> {
+        let nodes = &self.cst.nodes;
+        // Line feeds are counted once, from one entry to the next.
+        let mut line = 1;
+        let mut counted = 0;
+
+        self.cst
+            .cursor()
+            .entries()
+            .map(|entry| {
+                line += nodes[counted..entry.range.start]
+                    .iter()
+                    .filter(|node| **node == CstNode::NewLine)
+                    .count();
+                counted = entry.range.start;
+
+                Entry {
+                    ip: entry.ip.to_string(),
+                    hosts: entry.hosts.iter().map(|host| host.to_string()).collect(),
+                    comment: entry.comment.map(str::to_string),
+                    line,
+                    metadata: entry.metadata(),
+                }
+            })
+            .collect()
+    }
+
+    /// The address of the first entry for `host`, `None` if there is none.
+    /// Hostnames are compared by their canonical form.
+    fn lookup(&self, host: &str) -> Option<String> {
+        self.addresses(host).next().map(|ip| ip.to_string())
+    }
+
+    /// The addresses of every entry for `host`, in file order.
+    fn lookup_all(&self, host: &str) -> Vec<String> {
+        self.addresses(host).map(|ip| ip.to_string()).collect()
+    }
+
+    /// Appends an entry mapping `host`, in its canonical form, to `ip`.
+    /// A `comment` goes on its own line above the entry, as the text after
+    /// `#`.
+    #[pyo3(signature = (ip, host, comment = None))]
+    fn add(&mut self, ip: &str, host: &str, comment: Option<&str>) -> PyResult<()> {
+        let ip: IpAddr = ip
+            .parse()
+            .map_err(|_| PyValueError::new_err(format!("invalid IP address `{}`", ip)))?;
+        let host =
+            hostname::normalize(host).map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+        if comment.is_some_and(|comment| comment.contains(['\r', '\n'])) {
+            return Err(PyValueError::new_err("comment must fit on one line"));
+        }
+
+        self.cst.append_entry(ip, [host], comment);
+        Ok(())
+    }
+
+    /// Removes every occurrence of `host`, dropping the lines left without
+    /// hosts. Returns how many were removed.
+    fn remove(&mut self, host: &str) -> usize {
+        self.cst.remove_host(host)
+    }
+
+    fn __contains__(&self, host: &str) -> bool {
+        self.addresses(host).next().is_some()
+    }
+
+    fn __str__(&self) -> String {
+        self.cst.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("HostsFile({:?})", self.cst.to_string())
+    }
+}
+
+/// Parses `text`, given as `str` or `bytes`.
+#[pyfunction]
+#[pyo3(signature = (text, *, keep_invalid = false))]
+fn parse(text: Text, keep_invalid: bool) -> PyResult<HostsFile> {
+    HostsFile::parse(text.as_bytes(), keep_invalid)
+}
+
+/// Parser for hosts files that preserves their formatting through edits.
+#[pymodule(name = "hoster")]
+mod module {
+    #[pymodule_export]
+    use super::{parse, Entry, HostsFile, ParseError};
+}
@@ -0,0 +1,34 @@
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `tests/test_hoster.py` against the extension module, with the
+/// interpreter PyO3 was built for.
+#[test]
+fn test_python() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // Test binaries are built next to the libraries, in `target/<profile>/deps`.
+    let library = env::current_exe().unwrap().with_file_name(format!(
+        "{}hoster_py{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    ));
+    let modules = Path::new(env!("CARGO_TARGET_TMPDIR")).join("python");
+
+    fs::create_dir_all(&modules).unwrap();
+    // Python finds extension modules by their name, with `.so` on every unix.
+    fs::copy(&library, modules.join("hoster.so")).unwrap();
+
+    let python = env::var_os("PYO3_PYTHON").unwrap_or_else(|| "python3".into());
+    let status = Command::new(python)
+        .args(["-m", "unittest", "discover", "-v", "-s"])
+        .arg(root.join("tests"))
+        .env("PYTHONPATH", &modules)
+        .status()
+        .unwrap();
+
+    assert!(status.success(), "Python tests failed");
+}
@@ -0,0 +1,107 @@
+import os
+import tempfile
+import unittest
+
+import hoster
+
+TEXT = """\
+# Docker
+192.168.0.17 host.docker.internal gateway.docker.internal # docker owner=ops
+
+127.0.0.1\tlocalhost
+::1 localhost
+"""
+
+
+class HostsFileTest(unittest.TestCase):
+    def test_entries(self):
+        entries = hoster.parse(TEXT).entries
+
+        self.assertEqual(3, len(entries))
+        self.assertEqual("192.168.0.17", entries[0].ip)
+        self.assertEqual(
+            ["host.docker.internal", "gateway.docker.internal"], entries[0].hosts
+        )
+        self.assertEqual(" docker owner=ops", entries[0].comment)
+        self.assertEqual({"owner": "ops"}, entries[0].metadata)
+        self.assertEqual(2, entries[0].line)
+        self.assertEqual(["localhost"], entries[2].hosts)
+        self.assertIsNone(entries[2].comment)
+        self.assertEqual({}, entries[2].metadata)
+        self.assertEqual(5, entries[2].line)
+        self.assertEqual(entries, hoster.parse(TEXT).entries)
+
+    def test_lookup(self):
+        hosts = hoster.parse(TEXT.encode())
+
+        self.assertEqual("127.0.0.1", hosts.lookup("LocalHost."))
+        self.assertEqual(["127.0.0.1", "::1"], hosts.lookup_all("localhost"))
+        self.assertIsNone(hosts.lookup("missing.test"))
+        self.assertIn("gateway.docker.internal", hosts)
+        self.assertNotIn("missing.test", hosts)
+
+    def test_edits_preserve_formatting(self):
+        hosts = hoster.HostsFile(TEXT)
+
+        self.assertEqual(1, hosts.remove("gateway.docker.internal"))
+        self.assertEqual(2, hosts.remove("localhost"))
+        self.assertEqual(0, hosts.remove("localhost"))
+        hosts.add("10.0.0.1", "Bücher.de", comment=" books")
+
+        self.assertEqual(
+            "# Docker\n"
+            "192.168.0.17 host.docker.internal # docker owner=ops\n"
+            "\n"
+            "# books\n"
+            "10.0.0.1 xn--bcher-kva.de\n",
+            str(hosts),
+        )
+
+    def test_errors(self):
+        with self.assertRaises(hoster.ParseError) as context:
+            hoster.parse("127.0.0.1 localhost\nnope localhost\n")
+
+        self.assertIsInstance(context.exception, ValueError)
+        self.assertIn("line 2", str(context.exception))
+
+        hosts = hoster.parse("nope localhost\n", keep_invalid=True)
+        self.assertEqual([], hosts.entries)
+        self.assertEqual("nope localhost\n", str(hosts))
+
+        with self.assertRaises(ValueError):
+            hosts.add("localhost", "localhost")
+        with self.assertRaises(ValueError):
+            hosts.add("127.0.0.1", "localhost", comment="two\nlines")
+
+    def test_read_and_write(self):
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "hosts")
+
+            with open(path, "w") as file:
+                file.write(TEXT)
+
+            hosts = hoster.HostsFile.read(path)
+            hosts.add("127.0.0.1", "api.test")
+            hosts.write(path)
+
+            with open(path) as file:
+                self.assertEqual(TEXT + "127.0.0.1 api.test\n", file.read())
+
+            os.chmod(path, 0o640)
+            hosts.write(path)
+            self.assertEqual(0o640, os.stat(path).st_mode & 0o777)
+
+            copy = os.path.join(directory, "copy")
+            hosts.write(copy)
+            with open(copy) as file:
+                self.assertEqual(str(hosts), file.read())
+            self.assertEqual(["copy", "hosts"], sorted(os.listdir(directory)))
+
+            with self.assertRaises(FileNotFoundError):
+                hosts.write(os.path.join(directory, "missing", "hosts"))
+            with self.assertRaises(FileNotFoundError):
+                hoster.HostsFile.read(os.path.join(directory, "missing"))
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,61 @@
+use std::fmt::Write;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hoster::parser::Parser;
+use hoster::tokenizer::Tokenizer;
+
+const LINES: usize = 500_000;
+
+/// Builds a blocklist shaped like the StevenBlack hosts file: mostly
+/// `0.0.0.0 host` lines with a section comment every thousand entries.
+fn blocklist(lines: usize) -> String {
+    let mut data = String::with_capacity(lines * 32);
+
+    for i in 0..lines {
+        if i % 1000 == 0 {
+            writeln!(data, "# Section {}", i / 1000).unwrap();
+        }
+
+        writeln!(data, "0.0.0.0 ads-{}.tracker{}.example.com", i, i % 97).unwrap();
+    }
+
+    data
+}
+
+fn bench_parsing(c: &mut Criterion) {
+    let data = blocklist(LINES);
+    let parser = Parser::default();
+
+    let mut group = c.benchmark_group("blocklist");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.sample_size(10);
+
+    group.bench_function(BenchmarkId::new("tokenize", LINES), |b| {
+        b.iter(|| {
+            Tokenizer::new_with_reader(data.as_bytes())
+                .parse()
+                .unwrap()
+                .get_tokens()
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("tokenize_then_parse", LINES), |b| {
+        b.iter(|| {
+            let tokens = Tokenizer::new_with_reader(data.as_bytes())
+                .parse()
+                .unwrap()
+                .get_tokens();
+
+            parser.parse::<1>(tokens).unwrap()
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("parse_str", LINES), |b| {
+        b.iter(|| parser.parse_str::<1>(&data).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parsing);
+criterion_main!(benches);
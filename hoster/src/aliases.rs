@@ -0,0 +1,365 @@
+//! Per-user alias files, pointed at by glibc's `HOSTALIASES` environment
+//! variable.
+//!
+//! Every line maps an alias to the name it stands for, as in
+//! `db db.internal.example.com`. Resolvers only consult the file for names
+//! without dots, so aliases cannot hold any, and compare aliases ignoring
+//! case. Comments and whitespace are handled as in hosts files and kept
+//! through edits.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::str::{from_utf8, Utf8Error};
+
+use thiserror::Error as ThisError;
+
+use crate::hostname;
+use crate::tokenizer::{skip_bom_bytes, TokenIter};
+use crate::tokens::TokenRef;
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("invalid UTF-8 in alias file: {0}")]
+    Utf8Error(Utf8Error),
+    #[error("invalid alias `{0}`, aliases cannot hold dots")]
+    InvalidAlias(String),
+    #[error("invalid hostname `{0}`")]
+    InvalidName(String),
+    #[error("alias `{0}` has no hostname")]
+    MissingName(String),
+    #[error("unexpected `{0}` after the hostname")]
+    ExtraField(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AliasNode {
+    Alias(String),
+    Name(String),
+    Comment(String),
+    Space,
+    Tab,
+    CarriageReturn,
+    NewLine,
+}
+
+impl Display for AliasNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AliasNode::Alias(text) | AliasNode::Name(text) => f.write_str(text),
+            AliasNode::Comment(comment) => write!(f, "#{}", comment),
+            AliasNode::Space => f.write_str(" "),
+            AliasNode::Tab => f.write_str("\t"),
+            AliasNode::CarriageReturn => f.write_str("\r"),
+            AliasNode::NewLine => writeln!(f),
+        }
+    }
+}
+
+/// A parsed alias file. Writing it back with [`Display`] gives the text it
+/// was parsed from, with the edits made to it.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Aliases {
+    pub nodes: Vec<AliasNode>,
+}
+
+impl Aliases {
+    pub fn parse(input: &str) -> Result<Self, crate::Error> {
+        Self::parse_bytes(input.as_bytes())
+    }
+
+    /// Parses an alias file. Errors point at the offending word, as parse
+    /// errors of hosts files do.
+    pub fn parse_bytes(input: &[u8]) -> Result<Self, crate::Error> {
+        let body = skip_bom_bytes(input);
+        let bom = input.len() - body.len();
+
+        let text = from_utf8(body).map_err(|error| {
+            let offset = error.valid_up_to();
+            crate::Error::located(Error::Utf8Error(error), body, offset, offset)
+        });
+
+        text.and_then(Self::parse_body)
+            .map_err(|error| error.shift(bom, 0))
+    }
+
+    fn parse_body(input: &str) -> Result<Self, crate::Error> {
+        let locate = |error: Error, start: usize, end: usize| {
+            crate::Error::located(error, input.as_bytes(), start, end)
+        };
+        let mut tokens = TokenIter::new(input);
+        let mut nodes = Vec::new();
+        // Words seen on the current line, and where its alias is.
+        let mut words = 0;
+        let mut alias = 0..0;
+
+        loop {
+            let start = tokens.position();
+            let token = match tokens.next() {
+                Some(token) => token,
+                None => break,
+            };
+            let end = tokens.position();
+
+            let node = match token {
+                TokenRef::HostOrIp(word) => {
+                    words += 1;
+
+                    match words {
+                        1 if is_alias(word) => {
+                            alias = start..end;
+                            AliasNode::Alias(word.to_string())
+                        }
+                        1 => return Err(locate(Error::InvalidAlias(word.into()), start, end)),
+                        2 if hostname::is_valid(word) => AliasNode::Name(word.to_string()),
+                        2 => return Err(locate(Error::InvalidName(word.into()), start, end)),
+                        _ => return Err(locate(Error::ExtraField(word.into()), start, end)),
+                    }
+                }
+                TokenRef::NewLine => {
+                    if words == 1 {
+                        break;
+                    }
+
+                    words = 0;
+                    AliasNode::NewLine
+                }
+                TokenRef::Comment(comment) => AliasNode::Comment(comment.to_string()),
+                TokenRef::Space => AliasNode::Space,
+                TokenRef::Tab => AliasNode::Tab,
+                TokenRef::CarriageReturn => AliasNode::CarriageReturn,
+            };
+
+            nodes.push(node);
+        }
+
+        // The alias may be followed by whitespace or a comment.
+        if words == 1 {
+            let error = Error::MissingName(input[alias.clone()].to_string());
+            return Err(locate(error, alias.start, alias.end));
+        }
+
+        Ok(Self { nodes })
+    }
+
+    /// Every `(alias, hostname)` pair, in the order they appear.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        let mut alias = None;
+
+        self.nodes.iter().filter_map(move |node| match node {
+            AliasNode::Alias(text) => {
+                alias = Some(text.as_str());
+                None
+            }
+            AliasNode::Name(name) => alias.map(|alias| (alias, name.as_str())),
+            _ => None,
+        })
+    }
+
+    /// The hostname `alias` stands for. As with the resolver, the first
+    /// line for it wins.
+    pub fn get(&self, alias: &str) -> Option<&str> {
+        self.entries()
+            .find(|(name, _)| name.eq_ignore_ascii_case(alias))
+            .map(|(_, name)| name)
+    }
+
+    /// Points `alias` at `name`: rewrites the hostname of its first line,
+    /// or appends a line when there is none. Returns the hostname it had.
+    pub fn set(&mut self, alias: &str, name: &str) -> Result<Option<String>, Error> {
+        if !is_alias(alias) {
+            return Err(Error::InvalidAlias(alias.to_string()));
+        }
+
+        if !hostname::is_valid(name) {
+            return Err(Error::InvalidName(name.to_string()));
+        }
+
+        let mut found = false;
+
+        for node in &mut self.nodes {
+            match node {
+                AliasNode::Alias(text) => found = text.eq_ignore_ascii_case(alias),
+                AliasNode::Name(text) if found => {
+                    return Ok(Some(core::mem::replace(text, name.to_string())));
+                }
+                _ => {}
+            }
+        }
+
+        if self
+            .nodes
+            .last()
+            .is_some_and(|node| *node != AliasNode::NewLine)
+        {
+            self.nodes.push(AliasNode::NewLine);
+        }
+
+        self.nodes.extend([
+            AliasNode::Alias(alias.to_string()),
+            AliasNode::Space,
+            AliasNode::Name(name.to_string()),
+            AliasNode::NewLine,
+        ]);
+
+        Ok(None)
+    }
+
+    /// Removes every line for `alias`. Returns how many were removed.
+    pub fn remove(&mut self, alias: &str) -> usize {
+        let mut removed = 0;
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        let mut line = Vec::new();
+
+        for node in self.nodes.drain(..) {
+            let end = node == AliasNode::NewLine;
+            line.push(node);
+
+            if end {
+                removed += take_line(&mut nodes, &mut line, alias);
+            }
+        }
+
+        removed += take_line(&mut nodes, &mut line, alias);
+        self.nodes = nodes;
+
+        removed
+    }
+}
+
+impl Display for Aliases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            node.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_alias(word: &str) -> bool {
+    !word.is_empty() && !word.contains('.')
+}
+
+/// Moves `line` to `nodes`, unless it is a line for `alias`.
+fn take_line(nodes: &mut Vec<AliasNode>, line: &mut Vec<AliasNode>, alias: &str) -> usize {
+    let matches = line
+        .iter()
+        .any(|node| matches!(node, AliasNode::Alias(text) if text.eq_ignore_ascii_case(alias)));
+
+    if matches {
+        line.clear();
+        1
+    } else {
+        nodes.append(line);
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+
+    const DATA: &str = "\
+# Aliases for the dev cluster
+db\tdb.internal.example.com
+Cache  cache.internal.example.com # redis
+
+db other.example.com
+";
+
+    #[test]
+    fn test_parse() {
+        let aliases = Aliases::parse(DATA).unwrap();
+
+        assert_eq!(DATA, aliases.to_string());
+        assert_eq!(
+            vec![
+                ("db", "db.internal.example.com"),
+                ("Cache", "cache.internal.example.com"),
+                ("db", "other.example.com"),
+            ],
+            aliases.entries().collect::<Vec<_>>()
+        );
+        assert_eq!(Some("db.internal.example.com"), aliases.get("DB"));
+        assert_eq!(Some("cache.internal.example.com"), aliases.get("cache"));
+        assert_eq!(None, aliases.get("db.internal"));
+        assert!(Aliases::parse("").unwrap().nodes.is_empty());
+    }
+
+    /// The error of parsing `input`, with the range it points at.
+    fn parse_error(input: &[u8]) -> (Error, core::ops::Range<usize>) {
+        let error = Aliases::parse_bytes(input).unwrap_err();
+        let range = error.span().unwrap().range();
+
+        match error.into_kind() {
+            ErrorKind::Aliases(error) => (error, range),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse_error(b"db.dev db.example.com\n"),
+            (Error::InvalidAlias(alias), range) if alias == "db.dev" && range == (0..6)
+        ));
+        assert!(matches!(
+            parse_error(b"# x\ndb\n"),
+            (Error::MissingName(alias), range) if alias == "db" && range == (4..6)
+        ));
+        assert!(matches!(
+            parse_error(b"db # no name"),
+            (Error::MissingName(_), range) if range == (0..2)
+        ));
+        assert!(matches!(
+            parse_error(b"db a.example.com b.example.com\n"),
+            (Error::ExtraField(field), range) if field == "b.example.com" && range == (17..30)
+        ));
+        assert!(matches!(
+            parse_error(b"db -bad-\n"),
+            (Error::InvalidName(_), range) if range == (3..8)
+        ));
+        assert!(matches!(
+            parse_error(b"\xEF\xBB\xBFdb \xC3\x28\n"),
+            (Error::Utf8Error(_), range) if range == (6..6)
+        ));
+
+        let error = Aliases::parse("# x\ndb\n").unwrap_err();
+        assert_eq!("line 2: alias `db` has no hostname", error.to_string());
+        assert_eq!(Some("db"), error.line_text());
+        assert!(error.help().is_some());
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut aliases = Aliases::parse(DATA).unwrap();
+
+        assert_eq!(
+            Some("cache.internal.example.com".to_string()),
+            aliases.set("CACHE", "redis.example.com").unwrap()
+        );
+        assert_eq!(2, aliases.remove("DB"));
+        assert_eq!(0, aliases.remove("db"));
+        assert_eq!(None, aliases.set("web", "www.example.com").unwrap());
+        assert!(matches!(
+            aliases.set("web.dev", "www.example.com"),
+            Err(Error::InvalidAlias(_))
+        ));
+
+        assert_eq!(
+            "\
+# Aliases for the dev cluster
+Cache  redis.example.com # redis
+
+web www.example.com
+",
+            aliases.to_string()
+        );
+
+        let mut aliases = Aliases::parse("# no newline").unwrap();
+        aliases.set("db", "db.example.com").unwrap();
+        assert_eq!("# no newline\ndb db.example.com\n", aliases.to_string());
+    }
+}
@@ -0,0 +1,923 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::hash::BuildHasher;
+use core::mem::size_of;
+use core::net::IpAddr;
+
+use hashbrown::hash_table::Entry;
+use hashbrown::{DefaultHashBuilder, HashTable};
+use thiserror::Error as ThisError;
+
+use crate::cst::{Cst, CstNode};
+use crate::parser::Parser;
+
+// Lines of a blocklist rarely have more nodes than this, so parsing one
+// line at a time stays on the stack.
+const LINE_NODES: usize = 8;
+
+const TAG_BITS: u32 = 3;
+const PAYLOAD_BITS: u32 = u32::BITS - TAG_BITS;
+const MAX_PAYLOAD: u32 = (1 << PAYLOAD_BITS) - 1;
+pub(crate) const NO_PARENT: u32 = u32::MAX;
+
+// `ENTRY` stands for the common `ip host host...\n` line with single spaces:
+// it holds the IP, the hostnames follow as `HOST` nodes and the spaces and
+// line feed are implied. `REPEAT` is the same line with the IP of the
+// previous entry, holding its first hostname, so most lines of a blocklist
+// take a single node.
+pub(crate) const ENTRY: u32 = 0;
+pub(crate) const REPEAT: u32 = 1;
+pub(crate) const IP: u32 = 2;
+pub(crate) const HOST: u32 = 3;
+pub(crate) const COMMENT: u32 = 4;
+pub(crate) const INVALID: u32 = 5;
+// Runs of spaces or tabs; the lowest bit tells which, the rest is the length.
+pub(crate) const WHITESPACE: u32 = 6;
+pub(crate) const CONTROL: u32 = 7;
+
+const CARRIAGE_RETURN: u32 = 0;
+pub(crate) const NEW_LINE: u32 = 1;
+
+/// An arena ran out of 29 bit indices.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ThisError)]
+pub enum Error {
+    #[error("arena text exceeds {MAX_PAYLOAD} bytes")]
+    TooManyStrings,
+    #[error("arena holds more than {MAX_PAYLOAD} IP addresses")]
+    TooManyIps,
+}
+
+/// Index of the next item of a table already holding `len` of them.
+fn next_index(len: usize, error: Error) -> Result<u32, Error> {
+    u32::try_from(len)
+        .ok()
+        .filter(|&index| index <= MAX_PAYLOAD)
+        .ok_or(error)
+}
+
+/// A node packed into 32 bits: a 3 bit tag and a 29 bit index into the
+/// arena, or the length of a run of spaces or tabs.
+///
+/// Stored as little endian bytes so that a slice of nodes has the same
+/// layout in memory and in a [cache](crate::cache) file.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(transparent)]
+pub(crate) struct PackedNode([u8; 4]);
+
+impl PackedNode {
+    #[inline]
+    fn new(tag: u32, payload: u32) -> Self {
+        debug_assert!(payload <= MAX_PAYLOAD, "arena index out of range");
+        Self(((tag << PAYLOAD_BITS) | payload).to_le_bytes())
+    }
+
+    #[inline]
+    pub(crate) fn tag(self) -> u32 {
+        u32::from_le_bytes(self.0) >> PAYLOAD_BITS
+    }
+
+    #[inline]
+    pub(crate) fn payload(self) -> u32 {
+        u32::from_le_bytes(self.0) & MAX_PAYLOAD
+    }
+
+    pub(crate) fn as_bytes(nodes: &[PackedNode]) -> &[u8] {
+        // SAFETY: `PackedNode` is a transparent wrapper around `[u8; 4]`.
+        unsafe { core::slice::from_raw_parts(nodes.as_ptr().cast(), nodes.len() * 4) }
+    }
+
+    /// Reinterprets `bytes` as nodes, ignoring a trailing partial node.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> &[PackedNode] {
+        // SAFETY: `PackedNode` is a transparent wrapper around `[u8; 4]`,
+        // so it has no alignment requirement and every bit pattern is valid.
+        unsafe { core::slice::from_raw_parts(bytes.as_ptr().cast(), bytes.len() / 4) }
+    }
+}
+
+/// An IP address as a family byte (4 or 6) followed by its octets, padded
+/// with zeros to the length of an IPv6 address.
+pub(crate) type PackedIp = [u8; 17];
+
+fn pack_ip(ip: IpAddr) -> PackedIp {
+    let mut packed = [0; 17];
+
+    match ip {
+        IpAddr::V4(ip) => {
+            packed[0] = 4;
+            packed[1..5].copy_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            packed[0] = 6;
+            packed[1..].copy_from_slice(&ip.octets());
+        }
+    }
+
+    packed
+}
+
+#[inline]
+pub(crate) fn unpack_ip(packed: &PackedIp) -> IpAddr {
+    if packed[0] == 4 {
+        IpAddr::from([packed[1], packed[2], packed[3], packed[4]])
+    } else {
+        let mut octets = [0; 16];
+        octets.copy_from_slice(&packed[1..]);
+        IpAddr::from(octets)
+    }
+}
+
+/// The parts of an [`Arena`] that nodes point into, borrowed either from
+/// an arena or from a [cache](crate::cache) file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tables<'a> {
+    pub(crate) strings: &'a [u8],
+    pub(crate) ips: &'a [PackedIp],
+}
+
+/// Identifier of a hostname interned in an [`Arena`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct HostId(u32);
+
+/// Strings stored back to back in one buffer, each behind its length as a
+/// LEB128 varint. A string is identified by the offset of its length.
+#[derive(Debug, Default)]
+pub(crate) struct Strings {
+    data: Vec<u8>,
+    table: HashTable<u32>,
+}
+
+impl Strings {
+    #[inline]
+    pub(crate) fn get(data: &[u8], id: u32) -> &str {
+        let mut position = id as usize;
+        let mut len = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = data[position];
+            position += 1;
+            len |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let bytes = &data[position..position + len];
+
+        // SAFETY: `push` only ever copies whole `&str`s into the buffer and
+        // ids always point at the length in front of one of them.
+        unsafe { core::str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Reads the hostname record at `id`: the id of its parent followed by
+    /// its first label.
+    #[inline]
+    pub(crate) fn record(data: &[u8], id: u32) -> (u32, &str) {
+        let id = id as usize;
+        let parent = u32::from_le_bytes([data[id], data[id + 1], data[id + 2], data[id + 3]]);
+
+        (parent, Self::get(data, id as u32 + 4))
+    }
+
+    fn push_record(data: &mut Vec<u8>, label: &str, parent: u32) -> Result<u32, Error> {
+        let id = next_index(data.len(), Error::TooManyStrings)?;
+
+        data.extend_from_slice(&parent.to_le_bytes());
+        Self::write(data, label);
+
+        Ok(id)
+    }
+
+    /// Appends `value` without deduplicating it.
+    fn push(data: &mut Vec<u8>, value: &str) -> Result<u32, Error> {
+        let id = next_index(data.len(), Error::TooManyStrings)?;
+        Self::write(data, value);
+
+        Ok(id)
+    }
+
+    fn write(data: &mut Vec<u8>, value: &str) {
+        let mut len = value.len();
+
+        while len >= 0x80 {
+            data.push((len as u8) | 0x80);
+            len >>= 7;
+        }
+
+        data.push(len as u8);
+        data.extend_from_slice(value.as_bytes());
+    }
+
+    fn intern(&mut self, hasher: &DefaultHashBuilder, value: &str) -> Result<u32, Error> {
+        let Self { data, table } = self;
+
+        match table.entry(
+            hasher.hash_one(value),
+            |&id| Self::get(data, id) == value,
+            |&id| hasher.hash_one(Self::get(data, id)),
+        ) {
+            Entry::Occupied(entry) => Ok(*entry.get()),
+            Entry::Vacant(entry) => Ok(*entry.insert(Self::push(data, value)?).get()),
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        self.data.capacity() + self.table.allocation_size()
+    }
+}
+
+/// Shared storage for the text of many hosts files.
+///
+/// Hostnames are split into labels and stored as a chain of
+/// `(parent, label)` records, so names sharing a suffix such as
+/// `.doubleclick.net` share its records. IP addresses go into a table of
+/// their own. An [`ArenaDocument`] only holds 32 bit indices into an arena,
+/// and one arena can back any number of documents, deduplicating whatever
+/// they have in common.
+///
+/// Loading several blocklists that share most of their hostnames takes at
+/// least ten times less memory at its peak than parsing each into a
+/// [`Cst`]. A single list of unique names saves less, about a factor of
+/// three, as only the nodes and the shared suffixes shrink.
+#[derive(Debug, Default)]
+pub struct Arena {
+    hasher: DefaultHashBuilder,
+    strings: Strings,
+    host_table: HashTable<u32>,
+    ips: Vec<PackedIp>,
+    ip_table: HashTable<u32>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `input` with the default parser, storing its text in the arena.
+    pub fn parse(&mut self, input: &str) -> Result<ArenaDocument, crate::Error> {
+        self.parse_with(&Parser::default(), input)
+    }
+
+    /// Parses `input` with `parser`, storing its text in the arena.
+    ///
+    /// The input is parsed one line at a time, so no full [`Cst`] is ever
+    /// built. Fails on the first line that does not fit in the arena.
+    pub fn parse_with<V>(
+        &mut self,
+        parser: &Parser<V>,
+        input: &str,
+    ) -> Result<ArenaDocument, crate::Error> {
+        // Only the start of the input may hold a byte order mark, so lines
+        // are parsed as slices of it.
+        let body = input.strip_prefix('\u{feff}').unwrap_or(input);
+        let bom = input.len() - body.len();
+        let lines = memchr::memchr_iter(b'\n', body.as_bytes()).count();
+
+        let mut document = ArenaDocument {
+            nodes: Vec::with_capacity(lines),
+            entry_ip: None,
+        };
+        let mut offset = bom;
+
+        for (line, text) in body.split_inclusive('\n').enumerate() {
+            let cst = parser
+                .parse_body::<LINE_NODES>(text, 0, None)
+                .map_err(|error| error.shift(offset, line))?;
+
+            document.push_line(self, &cst.nodes).map_err(|error| {
+                crate::Error::located(error, text.as_bytes(), 0, text.len()).shift(offset, line)
+            })?;
+            offset += text.len();
+        }
+
+        document.nodes.shrink_to_fit();
+
+        Ok(document)
+    }
+
+    /// Copies the nodes of `cst` into the arena.
+    pub fn insert<const LENGTH: usize>(
+        &mut self,
+        cst: &Cst<LENGTH>,
+    ) -> Result<ArenaDocument, crate::Error> {
+        let mut document = ArenaDocument::default();
+
+        for line in cst.nodes.split_inclusive(|node| *node == CstNode::NewLine) {
+            document.push_line(self, line)?;
+        }
+
+        Ok(document)
+    }
+
+    pub fn intern_host(&mut self, host: &str) -> Result<HostId, crate::Error> {
+        Ok(HostId(self.intern_labels(host)?))
+    }
+
+    fn intern_labels(&mut self, host: &str) -> Result<u32, Error> {
+        let Self {
+            hasher,
+            strings,
+            host_table,
+            ..
+        } = self;
+        let mut parent = NO_PARENT;
+
+        for label in host.rsplit('.') {
+            parent = match host_table.entry(
+                hasher.hash_one((label, parent)),
+                |&id| Strings::record(&strings.data, id) == (parent, label),
+                |&id| {
+                    let (parent, label) = Strings::record(&strings.data, id);
+                    hasher.hash_one((label, parent))
+                },
+            ) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => *entry
+                    .insert(Strings::push_record(&mut strings.data, label, parent)?)
+                    .get(),
+            };
+        }
+
+        Ok(parent)
+    }
+
+    /// Looks up `host` without interning it. The name must match exactly.
+    pub fn find_host(&self, host: &str) -> Option<HostId> {
+        let mut parent = NO_PARENT;
+
+        for label in host.rsplit('.') {
+            parent = *self
+                .host_table
+                .find(self.hasher.hash_one((label, parent)), |&id| {
+                    Strings::record(&self.strings.data, id) == (parent, label)
+                })?;
+        }
+
+        Some(HostId(parent))
+    }
+
+    pub fn host(&self, id: HostId) -> HostRef<'_> {
+        HostRef {
+            strings: &self.strings.data,
+            id,
+        }
+    }
+
+    /// Number of distinct hostnames and suffixes stored.
+    pub fn hosts_len(&self) -> usize {
+        self.host_table.len()
+    }
+
+    /// Bytes allocated on the heap by the arena.
+    pub fn heap_size(&self) -> usize {
+        self.strings.heap_size()
+            + self.host_table.allocation_size()
+            + self.ips.capacity() * size_of::<PackedIp>()
+            + self.ip_table.allocation_size()
+    }
+
+    fn intern_ip(&mut self, ip: IpAddr) -> Result<u32, Error> {
+        let Self {
+            hasher,
+            ips,
+            ip_table,
+            ..
+        } = self;
+        let ip = pack_ip(ip);
+
+        match ip_table.entry(
+            hasher.hash_one(ip),
+            |&id| ips[id as usize] == ip,
+            |&id| hasher.hash_one(ips[id as usize]),
+        ) {
+            Entry::Occupied(entry) => Ok(*entry.get()),
+            Entry::Vacant(entry) => {
+                let id = next_index(ips.len(), Error::TooManyIps)?;
+                ips.push(ip);
+                entry.insert(id);
+                Ok(id)
+            }
+        }
+    }
+
+    pub(crate) fn tables(&self) -> Tables<'_> {
+        Tables {
+            strings: &self.strings.data,
+            ips: &self.ips,
+        }
+    }
+}
+
+/// A hostname stored in an [`Arena`]. Displays as the full name.
+#[derive(Debug, Clone, Copy)]
+pub struct HostRef<'a> {
+    strings: &'a [u8],
+    id: HostId,
+}
+
+impl<'a> HostRef<'a> {
+    pub fn id(&self) -> HostId {
+        self.id
+    }
+
+    /// Labels of the name from left to right.
+    pub fn labels(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let strings = self.strings;
+        let mut id = self.id.0;
+
+        core::iter::from_fn(move || {
+            if id == NO_PARENT {
+                return None;
+            }
+
+            let (parent, label) = Strings::record(strings, id);
+            id = parent;
+
+            Some(label)
+        })
+    }
+}
+
+impl<'a> Display for HostRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, label) in self.labels().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+
+            f.write_str(label)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> PartialEq<str> for HostRef<'a> {
+    fn eq(&self, other: &str) -> bool {
+        let mut rest = other;
+
+        for (i, label) in self.labels().enumerate() {
+            if i > 0 {
+                match rest.strip_prefix('.') {
+                    Some(stripped) => rest = stripped,
+                    None => return false,
+                }
+            }
+
+            match rest.strip_prefix(label) {
+                Some(stripped) => rest = stripped,
+                None => return false,
+            }
+        }
+
+        rest.is_empty()
+    }
+}
+
+/// A node of an [`ArenaDocument`], borrowing its text from the arena.
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'a> {
+    Host(HostRef<'a>),
+    IP(IpAddr),
+    Comment(&'a str),
+    Space,
+    Tab,
+    CarriageReturn,
+    NewLine,
+    Invalid(&'a str),
+}
+
+impl<'a> From<NodeRef<'a>> for CstNode {
+    fn from(node: NodeRef<'a>) -> Self {
+        match node {
+            NodeRef::Host(host) => CstNode::Host(alloc::format!("{}", host)),
+            NodeRef::IP(ip) => CstNode::IP(ip),
+            NodeRef::Comment(comment) => CstNode::Comment(comment.into()),
+            NodeRef::Space => CstNode::Space,
+            NodeRef::Tab => CstNode::Tab,
+            NodeRef::CarriageReturn => CstNode::CarriageReturn,
+            NodeRef::NewLine => CstNode::NewLine,
+            NodeRef::Invalid(line) => CstNode::Invalid(line.into()),
+        }
+    }
+}
+
+impl<'a> Display for NodeRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeRef::Host(host) => host.fmt(f),
+            NodeRef::IP(ip) => ip.fmt(f),
+            NodeRef::Comment(comment) => write!(f, "#{}", comment),
+            NodeRef::Space => f.write_str(" "),
+            NodeRef::Tab => f.write_str("\t"),
+            NodeRef::CarriageReturn => f.write_str("\r"),
+            NodeRef::NewLine => f.write_str("\n"),
+            NodeRef::Invalid(line) => f.write_str(line),
+        }
+    }
+}
+
+/// A hosts file stored as packed indices into an [`Arena`].
+///
+/// Every method takes the arena the document was parsed into; passing a
+/// different one yields wrong nodes or panics.
+#[derive(Debug, Clone, Default)]
+pub struct ArenaDocument {
+    nodes: Vec<PackedNode>,
+    entry_ip: Option<u32>,
+}
+
+impl ArenaDocument {
+    pub fn iter<'a>(&'a self, arena: &'a Arena) -> Nodes<'a> {
+        Nodes::new(arena.tables(), &self.nodes)
+    }
+
+    /// Every `(ip, hostname)` pair of the document.
+    pub fn hosts<'a>(&'a self, arena: &'a Arena) -> impl Iterator<Item = (IpAddr, HostRef<'a>)> {
+        self.iter(arena).hosts()
+    }
+
+    pub fn to_cst<const LENGTH: usize>(&self, arena: &Arena) -> Cst<LENGTH> {
+        Cst {
+            nodes: self.iter(arena).map(CstNode::from).collect(),
+        }
+    }
+
+    pub fn display<'a>(&'a self, arena: &'a Arena) -> impl Display + 'a {
+        DisplayNodes(self.iter(arena))
+    }
+
+    pub(crate) fn nodes(&self) -> &[PackedNode] {
+        &self.nodes
+    }
+
+    /// Bytes allocated on the heap by the document itself, not counting
+    /// the arena.
+    pub fn heap_size(&self) -> usize {
+        self.nodes.capacity() * size_of::<PackedNode>()
+    }
+
+    fn push_line(&mut self, arena: &mut Arena, line: &[CstNode]) -> Result<(), Error> {
+        if let Some((ip, hosts)) = split_entry(line) {
+            let ip = arena.intern_ip(*ip)?;
+            let mut hosts = hosts.map(|host| arena.intern_labels(host));
+
+            if self.entry_ip == Some(ip) {
+                if let Some(first) = hosts.next().transpose()? {
+                    self.nodes.push(PackedNode::new(REPEAT, first));
+                } else {
+                    self.nodes.push(PackedNode::new(ENTRY, ip));
+                }
+            } else {
+                self.nodes.push(PackedNode::new(ENTRY, ip));
+                self.entry_ip = Some(ip);
+            }
+
+            for host in hosts {
+                self.nodes.push(PackedNode::new(HOST, host?));
+            }
+
+            return Ok(());
+        }
+
+        for node in line {
+            let packed = match node {
+                CstNode::Host(host) => PackedNode::new(HOST, arena.intern_labels(host)?),
+                CstNode::IP(ip) => PackedNode::new(IP, arena.intern_ip(*ip)?),
+                CstNode::Comment(comment) => {
+                    PackedNode::new(COMMENT, arena.strings.intern(&arena.hasher, comment)?)
+                }
+                CstNode::Invalid(text) => {
+                    PackedNode::new(INVALID, arena.strings.intern(&arena.hasher, text)?)
+                }
+                CstNode::Space | CstNode::Tab => {
+                    let kind = u32::from(*node == CstNode::Tab);
+
+                    match self.nodes.last_mut() {
+                        Some(last)
+                            if last.tag() == WHITESPACE
+                                && last.payload() & 1 == kind
+                                && last.payload() < MAX_PAYLOAD - 1 =>
+                        {
+                            *last = PackedNode::new(WHITESPACE, last.payload() + 2);
+                            continue;
+                        }
+                        _ => PackedNode::new(WHITESPACE, 2 | kind),
+                    }
+                }
+                CstNode::CarriageReturn => PackedNode::new(CONTROL, CARRIAGE_RETURN),
+                CstNode::NewLine => PackedNode::new(CONTROL, NEW_LINE),
+            };
+
+            self.nodes.push(packed);
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits an `ip (space host)* newline` line into its IP and hostnames.
+fn split_entry(line: &[CstNode]) -> Option<(&IpAddr, impl Iterator<Item = &str>)> {
+    let (CstNode::IP(ip), rest) = line.split_first()? else {
+        return None;
+    };
+    let (CstNode::NewLine, hosts) = rest.split_last()? else {
+        return None;
+    };
+
+    let simple = hosts.len() % 2 == 0
+        && hosts
+            .chunks_exact(2)
+            .all(|pair| matches!(pair, [CstNode::Space, CstNode::Host(_)]));
+
+    simple.then(|| {
+        let hosts = hosts.chunks_exact(2).filter_map(|pair| match &pair[1] {
+            CstNode::Host(host) => Some(host.as_str()),
+            _ => None,
+        });
+
+        (ip, hosts)
+    })
+}
+
+/// Iterator over the nodes of an [`ArenaDocument`].
+#[derive(Debug, Clone)]
+pub struct Nodes<'a> {
+    tables: Tables<'a>,
+    nodes: &'a [PackedNode],
+    index: usize,
+    run: Option<(NodeRef<'a>, u32)>,
+    entry_ip: Option<IpAddr>,
+    in_entry: bool,
+    repeat: bool,
+    space_written: bool,
+}
+
+impl<'a> Nodes<'a> {
+    pub(crate) fn new(tables: Tables<'a>, nodes: &'a [PackedNode]) -> Self {
+        Self {
+            tables,
+            nodes,
+            index: 0,
+            run: None,
+            entry_ip: None,
+            in_entry: false,
+            repeat: false,
+            space_written: false,
+        }
+    }
+
+    /// Every `(ip, hostname)` pair of the remaining nodes.
+    pub(crate) fn hosts(self) -> impl Iterator<Item = (IpAddr, HostRef<'a>)> {
+        let mut ip = None;
+
+        self.filter_map(move |node| match node {
+            NodeRef::IP(address) => {
+                ip = Some(address);
+                None
+            }
+            NodeRef::Host(host) => ip.map(|ip| (ip, host)),
+            NodeRef::NewLine => {
+                ip = None;
+                None
+            }
+            _ => None,
+        })
+    }
+
+    fn host(&self, id: u32) -> HostRef<'a> {
+        HostRef {
+            strings: self.tables.strings,
+            id: HostId(id),
+        }
+    }
+
+    /// Yields a space and a hostname for every hostname of the current
+    /// entry, then the line feed ending it.
+    fn entry_host(&mut self) -> NodeRef<'a> {
+        match self.nodes.get(self.index) {
+            Some(node) if node.tag() == HOST || (self.repeat && node.tag() == REPEAT) => {
+                if !self.space_written {
+                    self.space_written = true;
+                    return NodeRef::Space;
+                }
+
+                self.space_written = false;
+                self.repeat = false;
+                self.index += 1;
+                NodeRef::Host(self.host(node.payload()))
+            }
+            _ => {
+                self.in_entry = false;
+                NodeRef::NewLine
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = NodeRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((node, remaining)) = self.run {
+            self.run = (remaining > 1).then_some((node, remaining - 1));
+            return Some(node);
+        }
+
+        if self.in_entry {
+            return Some(self.entry_host());
+        }
+
+        let node = *self.nodes.get(self.index)?;
+        let tables = self.tables;
+        let payload = node.payload();
+
+        if node.tag() == REPEAT {
+            // The node also holds the first hostname, so it stays current
+            // for `entry_host` to read.
+            self.in_entry = true;
+            self.repeat = true;
+
+            return Some(NodeRef::IP(
+                self.entry_ip.expect("a repeated entry follows an entry"),
+            ));
+        }
+
+        self.index += 1;
+
+        Some(match node.tag() {
+            ENTRY => {
+                let ip = unpack_ip(&tables.ips[payload as usize]);
+
+                self.entry_ip = Some(ip);
+                self.in_entry = true;
+                NodeRef::IP(ip)
+            }
+            IP => NodeRef::IP(unpack_ip(&tables.ips[payload as usize])),
+            HOST => NodeRef::Host(self.host(payload)),
+            COMMENT => NodeRef::Comment(Strings::get(tables.strings, payload)),
+            INVALID => NodeRef::Invalid(Strings::get(tables.strings, payload)),
+            WHITESPACE => {
+                let whitespace = if payload & 1 == 0 {
+                    NodeRef::Space
+                } else {
+                    NodeRef::Tab
+                };
+                let len = payload >> 1;
+
+                self.run = (len > 1).then_some((whitespace, len - 1));
+                whitespace
+            }
+            _ if payload == CARRIAGE_RETURN => NodeRef::CarriageReturn,
+            _ => NodeRef::NewLine,
+        })
+    }
+}
+
+pub(crate) struct DisplayNodes<'a>(pub(crate) Nodes<'a>);
+
+impl<'a> Display for DisplayNodes<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in self.0.clone() {
+            node.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::ErrorKind;
+
+    const DATA: &str = "\
+# blocklist\r
+0.0.0.0 ads.doubleclick.net\r
+0.0.0.0 ads.doubleclick.net stats.doubleclick.net
+\t0.0.0.0\t\ttracker.example.com   # tracker
+
+::1 localhost
+127.0.0.1 localhost";
+
+    #[test]
+    fn test_round_trip() {
+        let mut arena = Arena::new();
+
+        let document = arena.parse(DATA).unwrap();
+        let expected = Parser::default().parse_str::<1>(DATA).unwrap();
+
+        assert_eq!(DATA, document.display(&arena).to_string());
+        assert_eq!(expected.nodes, document.to_cst::<1>(&arena).nodes);
+        assert_eq!(
+            expected.nodes,
+            arena.insert(&expected).unwrap().to_cst::<1>(&arena).nodes
+        );
+    }
+
+    #[test]
+    fn test_interning_shares_suffixes() {
+        let mut arena = Arena::new();
+
+        let ads = arena.intern_host("ads.doubleclick.net").unwrap();
+        let stats = arena.intern_host("stats.doubleclick.net").unwrap();
+
+        // net, doubleclick.net, ads.doubleclick.net, stats.doubleclick.net
+        assert_eq!(4, arena.hosts_len());
+        assert_eq!(ads, arena.intern_host("ads.doubleclick.net").unwrap());
+        assert_eq!(Some(stats), arena.find_host("stats.doubleclick.net"));
+        assert_eq!(None, arena.find_host("doubleclick.com"));
+        assert_eq!("stats.doubleclick.net", arena.host(stats).to_string());
+        assert!(arena.host(ads) == *"ads.doubleclick.net");
+        assert!(arena.host(ads) != *"ads.doubleclick");
+    }
+
+    #[test]
+    fn test_documents_share_arena() {
+        let mut arena = Arena::new();
+
+        let first = arena.parse("0.0.0.0 ads.example.com\n").unwrap();
+        let hosts = arena.hosts_len();
+        let second = arena
+            .parse("0.0.0.0 ads.example.com\n0.0.0.0 example.com\n")
+            .unwrap();
+
+        assert_eq!(hosts, arena.hosts_len());
+        assert_eq!(
+            vec![
+                ("0.0.0.0".parse().unwrap(), "ads.example.com".to_string()),
+                ("0.0.0.0".parse().unwrap(), "example.com".to_string()),
+            ],
+            second
+                .hosts(&arena)
+                .map(|(ip, host)| (ip, host.to_string()))
+                .collect::<Vec<(IpAddr, String)>>()
+        );
+        assert_eq!(1, first.hosts(&arena).count());
+        // One packed node for the entry and one for its hostname, then a
+        // single node for each line repeating the IP.
+        assert_eq!(2 * size_of::<u32>(), first.heap_size());
+        assert_eq!(3, second.nodes.len());
+    }
+
+    #[test]
+    fn test_parse_errors_are_located() {
+        let mut arena = Arena::new();
+
+        let error = arena
+            .parse("\u{feff}0.0.0.0 ads.example.com\nnot-an-ip host\n")
+            .unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(crate::parser::Error::InvalidIp(_))
+        ));
+        assert_eq!(Some(2), error.line());
+        assert_eq!(27..36, error.span().unwrap().range());
+    }
+
+    #[test]
+    fn test_full_arena_is_an_error() {
+        let mut arena = Arena::new();
+        // Zeroed allocations are only backed by memory once written to.
+        arena.strings.data = alloc::vec![0; MAX_PAYLOAD as usize + 1];
+
+        let error = arena.parse("# full\n127.0.0.1 localhost\n").unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Arena(Error::TooManyStrings)
+        ));
+        assert_eq!(Some(1), error.line());
+        assert!(arena.intern_host("localhost").is_err());
+        assert_eq!(
+            Err(Error::TooManyIps),
+            next_index(1 << 29, Error::TooManyIps)
+        );
+        assert_eq!(
+            Ok(MAX_PAYLOAD),
+            next_index((1 << 29) - 1, Error::TooManyIps)
+        );
+    }
+
+    #[test]
+    fn test_bom_only_at_start_of_input() {
+        let mut arena = Arena::new();
+        let data = "0.0.0.0 ads.example.com\n\u{feff}0.0.0.0 tracker.example.com\n";
+
+        assert!(Parser::default().parse_str::<1>(data).is_err());
+
+        let error = arena.parse(data).unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(crate::parser::Error::InvalidIp(_))
+        ));
+        assert_eq!(Some(2), error.line());
+        assert_eq!(24, error.span().unwrap().start);
+    }
+}
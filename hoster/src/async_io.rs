@@ -0,0 +1,242 @@
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+
+use crate::cst::{Cst, CstNode};
+use crate::error::Error;
+use crate::parser::Parser;
+
+/// Parses everything read from `reader` with the default [`Parser`].
+///
+/// See [`Parser::parse_async`].
+pub async fn parse<R, const LENGTH: usize>(reader: R) -> Result<Cst<LENGTH>, Error>
+where
+    R: AsyncRead + Unpin,
+{
+    Parser::default().parse_async(reader).await
+}
+
+/// Reads and parses the hosts file at `path` with the default [`Parser`].
+///
+/// See [`Parser::load_async`].
+pub async fn load<P, const LENGTH: usize>(path: P) -> Result<Cst<LENGTH>, Error>
+where
+    P: AsRef<Path>,
+{
+    Parser::default().load_async(path).await
+}
+
+impl<V> Parser<V> {
+    /// Reads everything from `reader` and parses it with the options of
+    /// this parser. Errors carry their location.
+    pub async fn parse_async<R, const LENGTH: usize>(
+        &self,
+        mut reader: R,
+    ) -> Result<Cst<LENGTH>, Error>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+
+        self.parse_bytes(&data)
+    }
+
+    /// Reads and parses the hosts file at `path` with the options of this
+    /// parser.
+    pub async fn load_async<P, const LENGTH: usize>(&self, path: P) -> Result<Cst<LENGTH>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let data = fs::read(path).await?;
+
+        self.parse_bytes(&data)
+    }
+}
+
+/// Writes `cst` to `path` without ever exposing a partially written file.
+///
+/// The document is written to a temporary file in the same directory, synced
+/// and then renamed over `path`. Permissions of an existing file are kept.
+pub async fn save<P, const LENGTH: usize>(path: P, cst: &Cst<LENGTH>) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let temp_path = crate::temp::path_for(path);
+
+    let result = write_and_rename(path, &temp_path, cst).await;
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path).await;
+    }
+
+    result
+}
+
+async fn write_and_rename<const LENGTH: usize>(
+    path: &Path,
+    temp_path: &Path,
+    cst: &Cst<LENGTH>,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(temp_path).await?);
+    cst.write_to_async(&mut writer).await?;
+    writer.flush().await?;
+
+    let file = writer.into_inner();
+    file.sync_all().await?;
+
+    if let Ok(metadata) = fs::metadata(path).await {
+        fs::set_permissions(temp_path, metadata.permissions()).await?;
+    }
+
+    drop(file);
+    fs::rename(temp_path, path).await?;
+
+    Ok(())
+}
+
+impl<const LENGTH: usize> Cst<LENGTH> {
+    /// Like [`Cst::write_to`], for async writers. Nodes are written one at a
+    /// time, so unbuffered writers are best wrapped in a [`BufWriter`].
+    pub async fn write_to_async<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        // Reused for every address, the only nodes that are formatted.
+        let mut ip = String::new();
+
+        for node in &self.nodes {
+            let bytes: &[u8] = match node {
+                CstNode::Host(host) => host.as_bytes(),
+                CstNode::IP(address) => {
+                    ip.clear();
+                    let _ = write!(ip, "{}", address);
+                    ip.as_bytes()
+                }
+                CstNode::Comment(comment) => {
+                    writer.write_all(b"#").await?;
+                    comment.as_bytes()
+                }
+                CstNode::Space => b" ",
+                CstNode::Tab => b"\t",
+                CstNode::CarriageReturn => b"\r",
+                CstNode::NewLine => b"\n",
+                CstNode::Invalid(line) => line.as_bytes(),
+            };
+
+            writer.write_all(bytes).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use crate::cst::CstNode;
+    use crate::{parser, ErrorKind};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_parse_from_async_reader() {
+        let data = "# comment\n127.0.0.1\tlocalhost\n";
+
+        let cst = parse::<_, 1>(data.as_bytes()).await.unwrap();
+
+        assert_eq!(
+            vec![
+                CstNode::Comment(" comment".to_string()),
+                CstNode::NewLine,
+                CstNode::IP("127.0.0.1".parse::<IpAddr>().unwrap()),
+                CstNode::Tab,
+                CstNode::Host("localhost".to_string()),
+                CstNode::NewLine,
+            ],
+            cst.nodes.into_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_invalid_ip() {
+        let result = parse::<_, 1>("localhost 127.0.0.1\n".as_bytes()).await;
+
+        assert!(matches!(
+            result.map_err(Error::into_kind),
+            Err(ErrorKind::Parser(parser::Error::InvalidIp(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_write_to_async() {
+        let data = "# hosts\r\n127.0.0.1\tlocalhost\n::1 localhost # v6\n";
+        let cst = crate::parser::Parser::default()
+            .parse_bytes::<1>(data.as_bytes())
+            .unwrap();
+        let mut output = Vec::new();
+
+        cst.write_to_async(&mut output).await.unwrap();
+
+        assert_eq!(data, String::from_utf8(output).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+
+        fs::write(&path, "127.0.0.1 localhost\n").await.unwrap();
+
+        let mut cst = load::<_, 1>(&path).await.unwrap();
+        cst.add_nodes::<_, 4>(vec![
+            CstNode::IP("10.0.0.1".parse().unwrap()),
+            CstNode::Space,
+            CstNode::Host("db.local".to_string()),
+            CstNode::NewLine,
+        ]);
+
+        save(&path, &cst).await.unwrap();
+
+        assert_eq!(
+            "127.0.0.1 localhost\n10.0.0.1 db.local\n",
+            fs::read_to_string(&path).await.unwrap()
+        );
+        assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[tokio::test]
+    async fn test_parse_with_options() {
+        let parser = Parser::<()>::builder()
+            .invalid_lines(parser::InvalidLines::Keep)
+            .dialect(crate::dialect::Dialect::Glibc)
+            .build();
+        let data = "nope\n127.0.0.1 localhost#loopback\n";
+
+        let cst = parser.parse_async::<_, 1>(data.as_bytes()).await.unwrap();
+
+        assert_eq!(CstNode::Invalid("nope".to_string()), cst.nodes[0]);
+        assert_eq!(CstNode::Host("localhost".to_string()), cst.nodes[4]);
+        assert_eq!(data, cst.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_load_reports_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+
+        fs::write(&path, "127.0.0.1 localhost\nlocalhost 127.0.0.1\n")
+            .await
+            .unwrap();
+
+        let error = load::<_, 1>(&path).await.unwrap_err();
+
+        assert_eq!(Some(2), error.line());
+        assert_eq!(Some("localhost 127.0.0.1"), error.line_text());
+    }
+}
@@ -0,0 +1,673 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::net::IpAddr;
+
+use thiserror::Error as ThisError;
+
+use crate::arena::{
+    Arena, ArenaDocument, DisplayNodes, HostRef, Nodes, PackedNode, Tables, COMMENT, CONTROL,
+    ENTRY, HOST, INVALID, IP, NEW_LINE, NO_PARENT, REPEAT, WHITESPACE,
+};
+use crate::cst::{Cst, CstNode};
+
+const MAGIC: [u8; 8] = *b"\0hoster\n";
+
+/// Version of the cache format, bumped on every incompatible change.
+pub const VERSION: u32 = 1;
+
+// Magic, version, a reserved word, the three fields of `Source` and the
+// lengths of the strings, IP and node tables, all little endian.
+const HEADER_LEN: usize = 64;
+const IP_LEN: usize = size_of::<crate::arena::PackedIp>();
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ThisError)]
+pub enum Error {
+    #[error("not a hoster cache file")]
+    BadMagic,
+    #[error("cache format version {0} is not supported, expected {VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("cache file is truncated")]
+    Truncated,
+    #[error("cache file is corrupt: {0}")]
+    Corrupt(&'static str),
+}
+
+/// Identity of the hosts file a cache was built from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Source {
+    pub len: u64,
+    /// 64 bit FNV-1a hash of the contents.
+    pub hash: u64,
+    /// Modification time in nanoseconds since the Unix epoch, 0 if unknown.
+    pub modified: u64,
+}
+
+impl Source {
+    pub fn new(contents: &[u8], modified: u64) -> Self {
+        Self {
+            len: contents.len() as u64,
+            hash: fnv1a(contents),
+            modified,
+        }
+    }
+}
+
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Serializes `document` and the parts of `arena` it uses into the cache
+/// format.
+///
+/// The whole arena is written, so a document parsed into an arena of its
+/// own makes for the smallest cache.
+pub fn encode(arena: &Arena, document: &ArenaDocument, source: &Source) -> Vec<u8> {
+    let Tables { strings, ips } = arena.tables();
+    let nodes = PackedNode::as_bytes(document.nodes());
+
+    let mut bytes =
+        Vec::with_capacity(HEADER_LEN + strings.len() + ips.len() * IP_LEN + nodes.len());
+
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+
+    for field in [
+        source.len,
+        source.hash,
+        source.modified,
+        strings.len() as u64,
+        ips.len() as u64,
+        document.nodes().len() as u64,
+    ] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+
+    bytes.extend_from_slice(strings);
+    bytes.extend_from_slice(ips.as_flattened());
+    bytes.extend_from_slice(nodes);
+
+    bytes
+}
+
+/// A parsed hosts file read straight out of a cache file's bytes.
+///
+/// Nothing is copied: the nodes, hostnames and IP addresses are read from
+/// the buffer as they are needed, so the buffer can just as well be a memory
+/// map of the file.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheView<'a> {
+    source: Source,
+    tables: Tables<'a>,
+    nodes: &'a [PackedNode],
+}
+
+impl<'a> CacheView<'a> {
+    /// Checks the header and every node of `bytes`, so that reading the view
+    /// afterwards cannot fail.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let view = Self::split(bytes)?;
+        view.validate()?;
+
+        Ok(view)
+    }
+
+    /// Splits `bytes` into its tables, only checking the header.
+    fn split(bytes: &'a [u8]) -> Result<Self, Error> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(Error::BadMagic);
+        }
+
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::Truncated);
+        }
+
+        let field = |offset: usize| {
+            let mut le = [0; 8];
+            le.copy_from_slice(&bytes[offset..offset + 8]);
+            u64::from_le_bytes(le)
+        };
+
+        let version = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let source = Source {
+            len: field(16),
+            hash: field(24),
+            modified: field(32),
+        };
+        let len = |count: u64, size: usize| {
+            usize::try_from(count)
+                .ok()
+                .and_then(|count| count.checked_mul(size))
+                .ok_or(Error::Corrupt("table length out of range"))
+        };
+        let strings_len = len(field(40), 1)?;
+        let ips_len = len(field(48), IP_LEN)?;
+        let nodes_len = len(field(56), 4)?;
+
+        let rest = &bytes[HEADER_LEN..];
+        let total = strings_len
+            .checked_add(ips_len)
+            .and_then(|total| total.checked_add(nodes_len))
+            .ok_or(Error::Corrupt("table length out of range"))?;
+
+        if rest.len() < total {
+            return Err(Error::Truncated);
+        }
+
+        if rest.len() > total {
+            return Err(Error::Corrupt("trailing bytes after the node table"));
+        }
+
+        let (strings, rest) = rest.split_at(strings_len);
+        let (ips, nodes) = rest.split_at(ips_len);
+
+        Ok(Self {
+            source,
+            tables: Tables {
+                strings,
+                ips: ips.as_chunks().0,
+            },
+            nodes: PackedNode::from_bytes(nodes),
+        })
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let Tables { strings, ips } = self.tables;
+
+        if ips.iter().any(|ip| !matches!(ip[0], 4 | 6)) {
+            return Err(Error::Corrupt("unknown IP address family"));
+        }
+
+        let check_ip = |id: u32| match (id as usize) < ips.len() {
+            true => Ok(()),
+            false => Err(Error::Corrupt("IP address index out of range")),
+        };
+
+        // Hostnames share their suffixes, so every record is checked once.
+        let mut checked = vec![0u64; strings.len().div_ceil(64)];
+        let mut has_entry = false;
+
+        for node in self.nodes {
+            let payload = node.payload();
+
+            match node.tag() {
+                ENTRY => {
+                    check_ip(payload)?;
+                    has_entry = true;
+                }
+                IP => check_ip(payload)?,
+                REPEAT if !has_entry => {
+                    return Err(Error::Corrupt("repeated entry before any entry"))
+                }
+                REPEAT | HOST => validate_host(strings, payload, &mut checked)?,
+                COMMENT | INVALID => validate_str(strings, payload)?,
+                WHITESPACE if payload >> 1 == 0 => {
+                    return Err(Error::Corrupt("empty run of whitespace"))
+                }
+                CONTROL if payload > NEW_LINE => {
+                    return Err(Error::Corrupt("unknown control character"))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Identity of the hosts file the cache was built from.
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    pub fn iter(&self) -> Nodes<'a> {
+        Nodes::new(self.tables, self.nodes)
+    }
+
+    /// Every `(ip, hostname)` pair of the cached document.
+    pub fn hosts(&self) -> impl Iterator<Item = (IpAddr, HostRef<'a>)> {
+        self.iter().hosts()
+    }
+
+    pub fn to_cst<const LENGTH: usize>(&self) -> Cst<LENGTH> {
+        Cst {
+            nodes: self.iter().map(CstNode::from).collect(),
+        }
+    }
+
+    pub fn display(&self) -> impl Display + 'a {
+        DisplayNodes(self.iter())
+    }
+}
+
+/// Checks that `id` points at a length prefixed UTF-8 string.
+fn validate_str(strings: &[u8], id: u32) -> Result<(), Error> {
+    let mut position = id as usize;
+    let mut len = 0usize;
+
+    for shift in (0..32).step_by(7) {
+        let byte = *strings
+            .get(position)
+            .ok_or(Error::Corrupt("string index out of range"))?;
+        position += 1;
+        len |= usize::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            let text = strings
+                .get(position..position.saturating_add(len))
+                .ok_or(Error::Corrupt("string index out of range"))?;
+
+            return core::str::from_utf8(text)
+                .map(|_| ())
+                .map_err(|_| Error::Corrupt("string is not valid UTF-8"));
+        }
+    }
+
+    Err(Error::Corrupt("string length out of range"))
+}
+
+/// Checks the chain of hostname records starting at `id`. Parents are
+/// always written before their children, so a chain pointing forward is
+/// corrupt and every valid chain ends.
+fn validate_host(strings: &[u8], mut id: u32, checked: &mut [u64]) -> Result<(), Error> {
+    while id != NO_PARENT {
+        let (word, bit) = (id as usize / 64, 1 << (id % 64));
+
+        if checked.get(word).is_some_and(|word| word & bit != 0) {
+            return Ok(());
+        }
+
+        let parent = strings
+            .get(id as usize..id as usize + 4)
+            .ok_or(Error::Corrupt("hostname index out of range"))?;
+        let parent = u32::from_le_bytes([parent[0], parent[1], parent[2], parent[3]]);
+
+        validate_str(strings, id + 4)?;
+
+        if parent != NO_PARENT && parent >= id {
+            return Err(Error::Corrupt("hostname record points forward"));
+        }
+
+        checked[word] |= bit;
+        id = parent;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+pub use self::file::{load_cache, read_source, save_cache, Cache};
+
+#[cfg(feature = "std")]
+mod file {
+    use std::fs::{self, File, Metadata};
+    use std::io::{self, Read, Write};
+    use std::ops::Deref;
+    use std::path::Path;
+    use std::time::UNIX_EPOCH;
+
+    use super::{encode, CacheView, Error, Source};
+    use crate::arena::{Arena, ArenaDocument};
+
+    /// A cache file loaded by [`load_cache`].
+    #[derive(Debug)]
+    pub struct Cache {
+        data: Data,
+    }
+
+    impl Cache {
+        pub fn view(&self) -> CacheView<'_> {
+            CacheView::split(&self.data).expect("the cache was validated when loaded")
+        }
+    }
+
+    #[derive(Debug)]
+    enum Data {
+        #[cfg(feature = "mmap")]
+        Mapped(memmap2::Mmap),
+        Owned(Vec<u8>),
+    }
+
+    impl Data {
+        fn read(mut file: File, len: u64) -> io::Result<Self> {
+            #[cfg(feature = "mmap")]
+            {
+                // SAFETY: `save_cache` replaces cache files instead of writing
+                // into them, so a mapped cache never changes under the view.
+                // Caches modified in place by other programs are undefined
+                // behaviour, as with any memory map. Sources, which editors
+                // do modify in place, are never mapped.
+                if len > 0 {
+                    if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+                        return Ok(Data::Mapped(map));
+                    }
+                }
+            }
+
+            let mut data = Vec::with_capacity(len as usize);
+            file.read_to_end(&mut data)?;
+
+            Ok(Data::Owned(data))
+        }
+    }
+
+    impl Deref for Data {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            match self {
+                #[cfg(feature = "mmap")]
+                Data::Mapped(map) => map,
+                Data::Owned(data) => data,
+            }
+        }
+    }
+
+    fn modified(metadata: &Metadata) -> u64 {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as u64)
+    }
+
+    /// Reads the hosts file at `path`, returning its contents and the
+    /// [`Source`] to pass to [`save_cache`] once they are parsed.
+    ///
+    /// The file is read into memory rather than mapped, as hosts files are
+    /// edited in place.
+    pub fn read_source<P>(path: P) -> Result<(Vec<u8>, Source), crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::open(path)?;
+        // Taken before reading, so a change while reading leaves the cache
+        // with an older modification time and it is rebuilt next time.
+        let metadata = file.metadata()?;
+        let mut data = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut data)?;
+
+        let source = Source::new(&data, modified(&metadata));
+
+        Ok((data, source))
+    }
+
+    /// Writes `document`, parsed into `arena` from the contents of the hosts
+    /// file `source` was read with [`read_source`], to a cache file at
+    /// `path`.
+    ///
+    /// The cache is written next to `path`, synced and renamed over it, so
+    /// readers never see a partly written file.
+    pub fn save_cache<P>(
+        path: P,
+        source: &Source,
+        arena: &Arena,
+        document: &ArenaDocument,
+    ) -> Result<(), crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let bytes = encode(arena, document, source);
+
+        let temporary = crate::temp::path_for(path);
+        let result = File::create(&temporary)
+            .and_then(|mut file| {
+                file.write_all(&bytes)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temporary, path));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+
+        Ok(result?)
+    }
+
+    /// Loads the cache file at `path` if it is still up to date with the
+    /// hosts file at `source`.
+    ///
+    /// Returns `None` when there is no cache yet, when it was written by an
+    /// incompatible version of hoster, or when the length, modification time
+    /// or hash of the source changed since. A cache file that is not one or is
+    /// damaged is an error.
+    pub fn load_cache<P, S>(path: P, source: S) -> Result<Option<Cache>, crate::Error>
+    where
+        P: AsRef<Path>,
+        S: AsRef<Path>,
+    {
+        let metadata = fs::metadata(source.as_ref())?;
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let len = file.metadata()?.len();
+        let data = Data::read(file, len)?;
+
+        let view = match CacheView::split(&data) {
+            Ok(view) => view,
+            Err(Error::UnsupportedVersion(_)) => return Ok(None),
+            Err(error) => return Err(crate::Error::new(error)),
+        };
+        let cached = view.source();
+
+        if cached.len != metadata.len() || cached.modified != modified(&metadata) {
+            return Ok(None);
+        }
+
+        if read_source(source.as_ref())?.1.hash != cached.hash {
+            return Ok(None);
+        }
+
+        view.validate().map_err(crate::Error::new)?;
+
+        Ok(Some(Cache { data }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = "\
+# blocklist\r
+0.0.0.0 ads.doubleclick.net\r
+0.0.0.0 ads.doubleclick.net stats.doubleclick.net
+0.0.0.0 tracker.example.com
+\t::1\t\tlocalhost   # loopback
+";
+
+    fn encoded() -> Vec<u8> {
+        let mut arena = Arena::new();
+        let document = arena.parse(DATA).unwrap();
+
+        encode(&arena, &document, &Source::new(DATA.as_bytes(), 42))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = encoded();
+        let view = CacheView::from_bytes(&bytes).unwrap();
+
+        assert_eq!(Source::new(DATA.as_bytes(), 42), view.source());
+        assert_eq!(DATA, alloc::format!("{}", view.display()));
+        assert_eq!(
+            crate::parser::Parser::default()
+                .parse_str::<1>(DATA)
+                .unwrap()
+                .nodes,
+            view.to_cst::<1>().nodes
+        );
+        assert_eq!(
+            vec![
+                "ads.doubleclick.net",
+                "ads.doubleclick.net",
+                "stats.doubleclick.net",
+                "tracker.example.com",
+                "localhost"
+            ],
+            view.hosts()
+                .map(|(_, host)| alloc::format!("{}", host))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let bytes = encoded();
+
+        assert_eq!(
+            Err(Error::BadMagic),
+            CacheView::from_bytes(DATA.as_bytes()).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::Truncated),
+            CacheView::from_bytes(&bytes[..bytes.len() - 1]).map(|_| ())
+        );
+
+        let mut newer = bytes.clone();
+        newer[8] = 2;
+
+        assert_eq!(
+            Err(Error::UnsupportedVersion(2)),
+            CacheView::from_bytes(&newer).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_corrupt_tables() {
+        let bytes = encoded();
+        let strings = HEADER_LEN;
+
+        // The comment ` blocklist` comes first, then the record of `net`,
+        // the root of every other hostname.
+        let mut not_utf8 = bytes.clone();
+        not_utf8[strings + 1] = 0xff;
+
+        assert_eq!(
+            Err(Error::Corrupt("string is not valid UTF-8")),
+            CacheView::from_bytes(&not_utf8).map(|_| ())
+        );
+
+        let mut cycle = bytes.clone();
+        cycle[strings + 11..strings + 15].copy_from_slice(&64u32.to_le_bytes());
+
+        assert_eq!(
+            Err(Error::Corrupt("hostname record points forward")),
+            CacheView::from_bytes(&cycle).map(|_| ())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_and_load() {
+        use std::fs::{self, File};
+        use std::time::{Duration, SystemTime};
+
+        let directory = tempfile::tempdir().unwrap();
+        let source = directory.path().join("hosts");
+        let path = directory.path().join("hosts.cache");
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let write_source = |contents: &str| {
+            fs::write(&source, contents).unwrap();
+            File::options()
+                .write(true)
+                .open(&source)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        write_source(DATA);
+        assert!(load_cache(&path, &source).unwrap().is_none());
+
+        let (data, read) = read_source(&source).unwrap();
+        let mut arena = Arena::new();
+        let document = arena.parse(core::str::from_utf8(&data).unwrap()).unwrap();
+        save_cache(&path, &read, &arena, &document).unwrap();
+
+        let cache = load_cache(&path, &source).unwrap().unwrap();
+        assert_eq!(DATA, cache.view().display().to_string());
+
+        // Same length and modification time, different contents.
+        write_source(&DATA.replace("tracker", "trackor"));
+        assert!(load_cache(&path, &source).unwrap().is_none());
+
+        write_source(DATA);
+        assert!(load_cache(&path, &source).unwrap().is_some());
+
+        File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        assert!(load_cache(&path, &source).unwrap().is_none());
+
+        fs::write(&path, b"garbage").unwrap();
+        assert!(matches!(
+            load_cache(&path, &source).map_err(crate::Error::into_kind),
+            Err(crate::ErrorKind::Cache(Error::BadMagic))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_source_edited_after_parsing() {
+        use std::fs;
+
+        let directory = tempfile::tempdir().unwrap();
+        let source = directory.path().join("hosts");
+        let path = directory.path().join("hosts.cache");
+        fs::write(&source, DATA).unwrap();
+
+        let (data, read) = read_source(&source).unwrap();
+        let mut arena = Arena::new();
+        let document = arena.parse(core::str::from_utf8(&data).unwrap()).unwrap();
+
+        // Edited between the parse and the save: the cache describes the old
+        // contents and must not pass for a cache of the new ones.
+        fs::write(&source, DATA.replace("tracker", "trackor")).unwrap();
+        save_cache(&path, &read, &arena, &document).unwrap();
+
+        assert!(load_cache(&path, &source).unwrap().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_concurrent_saves() {
+        use std::fs;
+
+        let directory = tempfile::tempdir().unwrap();
+        let source = directory.path().join("hosts");
+        let path = directory.path().join("hosts.cache");
+        fs::write(&source, DATA).unwrap();
+
+        let (_, read) = read_source(&source).unwrap();
+        let mut arena = Arena::new();
+        let document = arena.parse(DATA).unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..20 {
+                        save_cache(&path, &read, &arena, &document).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert!(load_cache(&path, &source).unwrap().is_some());
+        assert_eq!(2, fs::read_dir(directory.path()).unwrap().count());
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::{self, Display};
+use std::io::{self, Chain, Cursor, ErrorKind, Read};
+
+use crate::tokenizer::{Error, Tokenizer};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// Length of the longest magic number.
+const MAGIC_LENGTH: usize = 6;
+
+/// Compression format of a stream, told by its first bytes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// The format whose magic number `header` starts with.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if header.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Whether hoster was built with the feature decoding this format.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "gzip"),
+            Compression::Xz => cfg!(feature = "xz"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Compression::None => "uncompressed",
+            Compression::Gzip => "gzip",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        };
+
+        f.write_str(name)
+    }
+}
+
+// The bytes read to detect the format, put back in front of the rest.
+type Sniffed<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// A reader that decompresses whatever it wraps, in the format detected
+/// from its first bytes. Uncompressed input is passed through.
+///
+/// Concatenated gzip members, xz streams and zstd frames are all read.
+pub enum Decoder<R: Read> {
+    Plain(Sniffed<R>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::read::MultiGzDecoder<Sniffed<R>>),
+    #[cfg(feature = "xz")]
+    Xz(liblzma::read::XzDecoder<Sniffed<R>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, io::BufReader<Sniffed<R>>>),
+}
+
+impl<R: Read> Decoder<R> {
+    /// Reads the first bytes of `reader` to detect its format.
+    ///
+    /// Fails with [`ErrorKind::Unsupported`] for a format whose feature is
+    /// not enabled.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = vec![0; MAGIC_LENGTH];
+        let mut filled = 0;
+
+        while filled < MAGIC_LENGTH {
+            match reader.read(&mut header[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        header.truncate(filled);
+
+        let compression = Compression::detect(&header);
+        let reader = Cursor::new(header).chain(reader);
+
+        match compression {
+            Compression::None => Ok(Decoder::Plain(reader)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Decoder::Gzip(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Ok(Decoder::Xz(liblzma::read::XzDecoder::new_multi_decoder(
+                reader,
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Decoder::Zstd(zstd::stream::read::Decoder::new(reader)?)),
+            #[allow(unreachable_patterns)]
+            compression => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "{0} input needs hoster to be built with the `{0}` feature",
+                    compression
+                ),
+            )),
+        }
+    }
+
+    pub fn compression(&self) -> Compression {
+        match self {
+            Decoder::Plain(_) => Compression::None,
+            #[cfg(feature = "gzip")]
+            Decoder::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "xz")]
+            Decoder::Xz(_) => Compression::Xz,
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(_) => Compression::Zstd,
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoder::Plain(reader) => reader.read(buf),
+            #[cfg(feature = "gzip")]
+            Decoder::Gzip(reader) => reader.read(buf),
+            #[cfg(feature = "xz")]
+            Decoder::Xz(reader) => reader.read(buf),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: Read> fmt::Debug for Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Decoder").field(&self.compression()).finish()
+    }
+}
+
+impl<R: Read> Tokenizer<Decoder<R>> {
+    /// Like [`Tokenizer::new_with_reader`], decompressing `reader` first
+    /// when it is compressed.
+    pub fn new_with_compressed_reader(reader: R) -> Result<Self, Error> {
+        Ok(Tokenizer::new_with_reader(Decoder::new(reader)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    const DATA: &str = "127.0.0.1 localhost\n::1 localhost\n";
+
+    fn parse(data: &[u8]) -> Result<String, Error> {
+        let tokens = Tokenizer::new_with_compressed_reader(data)?
+            .parse()?
+            .get_tokens();
+
+        Ok(Parser::default().parse::<1>(tokens).unwrap().to_string())
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::Gzip, Compression::detect(&[0x1f, 0x8b, 8]));
+        assert_eq!(
+            Compression::Xz,
+            Compression::detect(b"\xfd7zXZ\x00\x00\x04")
+        );
+        assert_eq!(
+            Compression::Zstd,
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0])
+        );
+        assert_eq!(Compression::None, Compression::detect(b"\xfd7zX"));
+        assert_eq!(Compression::None, Compression::detect(b""));
+    }
+
+    #[test]
+    fn test_plain_input_is_passed_through() {
+        assert_eq!(DATA, parse(DATA.as_bytes()).unwrap());
+        assert_eq!("", parse(b"").unwrap());
+        assert_eq!("# a\n", parse(b"# a\n").unwrap());
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_unsupported() {
+        let error = Decoder::new(&[0x1f, 0x8b, 8, 0][..]).unwrap_err();
+
+        assert_eq!(ErrorKind::Unsupported, error.kind());
+        assert_eq!(
+            "gzip input needs hoster to be built with the `gzip` feature",
+            error.to_string()
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        use std::io::Write;
+
+        // Two members, as `cat a.gz b.gz` gives.
+        let mut data = Vec::new();
+        for part in DATA.split_inclusive('\n') {
+            let mut encoder =
+                flate2::write::GzEncoder::new(&mut data, flate2::Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+        }
+
+        assert_eq!(
+            Compression::Gzip,
+            Decoder::new(&data[..]).unwrap().compression()
+        );
+        assert_eq!(DATA, parse(&data).unwrap());
+
+        let error = parse(&data[..data.len() - 4]).unwrap_err();
+        assert!(matches!(error, Error::Io(_)));
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_xz() {
+        let mut data = Vec::new();
+        liblzma::read::XzEncoder::new(DATA.as_bytes(), 6)
+            .read_to_end(&mut data)
+            .unwrap();
+
+        assert_eq!(
+            Compression::Xz,
+            Decoder::new(&data[..]).unwrap().compression()
+        );
+        assert_eq!(DATA, parse(&data).unwrap());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        let data = zstd::encode_all(DATA.as_bytes(), 0).unwrap();
+
+        assert_eq!(
+            Compression::Zstd,
+            Decoder::new(&data[..]).unwrap().compression()
+        );
+        assert_eq!(DATA, parse(&data).unwrap());
+    }
+}
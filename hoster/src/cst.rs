@@ -1,18 +1,22 @@
-use std::{fmt::Display, ops::RangeBounds};
+use alloc::string::String;
+use core::{fmt::Display, net::IpAddr, ops::RangeBounds};
 
 use smallvec::SmallVec;
 
 use crate::tokens::Tokens;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum CstNode {
     Host(String),
-    IP(std::net::IpAddr),
+    IP(IpAddr),
     Comment(String),
     Space,
     Tab,
     CarriageReturn,
     NewLine,
+    /// Raw text of a line the parser was told to keep instead of rejecting,
+    /// up to but not including its line feed.
+    Invalid(String),
 }
 
 impl From<Tokens> for CstNode {
@@ -29,7 +33,7 @@ impl From<Tokens> for CstNode {
 }
 
 impl Display for CstNode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             CstNode::Host(host) => write!(f, "{}", host),
             CstNode::IP(ip) => write!(f, "{}", ip),
@@ -38,6 +42,7 @@ impl Display for CstNode {
             CstNode::Tab => write!(f, "\t"),
             CstNode::CarriageReturn => write!(f, "\r"),
             CstNode::NewLine => writeln!(f),
+            CstNode::Invalid(line) => write!(f, "{}", line),
         }
     }
 }
@@ -59,7 +64,7 @@ impl<const LENGTH: usize> Cst<LENGTH> {
     where
         T: Into<SmallVec<[CstNode; OTHER]>>,
     {
-        self.nodes.extend(nodes.into().into_iter());
+        self.nodes.extend(nodes.into());
     }
 
     pub fn add_node<T>(&mut self, node: T)
@@ -68,22 +73,63 @@ impl<const LENGTH: usize> Cst<LENGTH> {
     {
         self.nodes.push(node.into());
     }
+
+    /// Every `(ip, hostname)` pair, in the order they appear.
+    pub fn entries(&self) -> impl Iterator<Item = (IpAddr, &str)> {
+        let mut ip = None;
+
+        self.nodes.iter().filter_map(move |node| match node {
+            CstNode::IP(address) => {
+                ip = Some(*address);
+                None
+            }
+            CstNode::Host(host) => ip.map(|ip| (ip, host.as_str())),
+            CstNode::NewLine => {
+                ip = None;
+                None
+            }
+            _ => None,
+        })
+    }
+}
+
+impl<const LENGTH: usize> Display for Cst<LENGTH> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for node in &self.nodes {
+            node.fmt(f)?;
+        }
+
+        Ok(())
+    }
 }
 
-impl<const LENGTH: usize> ToString for Cst<LENGTH> {
-    fn to_string(&self) -> String {
-        self.nodes
-            .iter()
-            .map(|node| node.to_string())
-            .collect::<Vec<String>>()
-            .join("")
+#[cfg(feature = "std")]
+impl<const LENGTH: usize> Cst<LENGTH> {
+    /// Streams the document to `writer` node by node, without building the
+    /// whole file in memory first.
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for node in &self.nodes {
+            match node {
+                CstNode::Host(host) => writer.write_all(host.as_bytes())?,
+                CstNode::IP(ip) => write!(writer, "{}", ip)?,
+                CstNode::Comment(comment) => {
+                    writer.write_all(b"#")?;
+                    writer.write_all(comment.as_bytes())?;
+                }
+                CstNode::Space => writer.write_all(b" ")?,
+                CstNode::Tab => writer.write_all(b"\t")?,
+                CstNode::CarriageReturn => writer.write_all(b"\r")?,
+                CstNode::NewLine => writer.write_all(b"\n")?,
+                CstNode::Invalid(line) => writer.write_all(line.as_bytes())?,
+            }
+        }
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::net::IpAddr;
-
     use smallvec::smallvec_inline;
 
     use super::*;
@@ -135,4 +181,31 @@ mod tests {
 
         assert_eq!(expected, Cst { nodes: cst }.to_string());
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_to() {
+        let cst = Cst {
+            nodes: smallvec_inline![
+                CstNode::Comment(" loopback".to_string()),
+                CstNode::CarriageReturn,
+                CstNode::NewLine,
+                CstNode::IP("::1".parse::<IpAddr>().unwrap()),
+                CstNode::Tab,
+                CstNode::Host("localhost".to_string()),
+                CstNode::Space,
+                CstNode::Host("ip6-localhost".to_string()),
+                CstNode::NewLine,
+            ],
+        };
+
+        let mut output = Vec::new();
+        cst.write_to(&mut output).unwrap();
+
+        assert_eq!(
+            "# loopback\r\n::1\tlocalhost ip6-localhost\n",
+            String::from_utf8(output).unwrap()
+        );
+        assert_eq!(cst.to_string(), format!("{}", cst));
+    }
 }
@@ -0,0 +1,950 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::net::IpAddr;
+use core::ops::Range;
+
+use crate::cst::{Cst, CstNode};
+use crate::hostname;
+use crate::metadata::{self, Metadata};
+
+/// An entry line seen through a cursor.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Entry<'a> {
+    /// Nodes of the whole line, including its line feed.
+    pub range: Range<usize>,
+    pub ip: IpAddr,
+    pub hosts: Vec<&'a str>,
+    pub comment: Option<&'a str>,
+    /// Nodes of the whole CST, for reading the comments above the entry.
+    nodes: &'a [CstNode],
+}
+
+impl<'a> Entry<'a> {
+    /// `key=value` pairs of the comment lines right above the entry and of
+    /// its own comment, which wins over them.
+    ///
+    /// The comments are parsed on every call.
+    pub fn metadata(&self) -> Metadata {
+        let mut metadata = Metadata::new();
+
+        for text in comments_before(self.nodes, self.range.start)
+            .into_iter()
+            .chain(self.comment)
+        {
+            metadata.extend(metadata::parse(text));
+        }
+
+        metadata
+    }
+}
+
+impl core::fmt::Debug for Entry<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Entry")
+            .field("range", &self.range)
+            .field("ip", &self.ip)
+            .field("hosts", &self.hosts)
+            .field("comment", &self.comment)
+            .finish()
+    }
+}
+
+/// Movement over the nodes of a CST, shared by [`Cursor`] and
+/// [`CursorMut`].
+///
+/// A cursor sits on a node, or just past the last one. Lines are the nodes
+/// up to and including a line feed. Moves return whether the cursor moved;
+/// a move that fails leaves it where it was.
+pub trait Navigate {
+    fn nodes(&self) -> &[CstNode];
+
+    fn index(&self) -> usize;
+
+    fn set_index(&mut self, index: usize);
+
+    /// The node under the cursor, `None` past the end.
+    fn node(&self) -> Option<&CstNode> {
+        self.nodes().get(self.index())
+    }
+
+    fn next_node(&mut self) -> bool {
+        let moved = self.index() < self.nodes().len();
+
+        if moved {
+            self.set_index(self.index() + 1);
+        }
+
+        moved
+    }
+
+    fn prev_node(&mut self) -> bool {
+        let moved = self.index() > 0;
+
+        if moved {
+            self.set_index(self.index() - 1);
+        }
+
+        moved
+    }
+
+    /// Nodes of the line under the cursor.
+    fn line_range(&self) -> Range<usize> {
+        line_range(self.nodes(), self.index())
+    }
+
+    fn line(&self) -> &[CstNode] {
+        &self.nodes()[self.line_range()]
+    }
+
+    /// 1-based number of the line under the cursor.
+    fn line_number(&self) -> usize {
+        let start = self.line_range().start;
+
+        self.nodes()[..start]
+            .iter()
+            .filter(|node| **node == CstNode::NewLine)
+            .count()
+            + 1
+    }
+
+    /// Moves to the start of the next line.
+    fn next_line(&mut self) -> bool {
+        let end = self.line_range().end;
+        let moved = end < self.nodes().len();
+
+        if moved {
+            self.set_index(end);
+        }
+
+        moved
+    }
+
+    /// Moves to the start of the previous line.
+    fn prev_line(&mut self) -> bool {
+        let start = self.line_range().start;
+
+        if start == 0 {
+            return false;
+        }
+
+        self.set_index(line_range(self.nodes(), start - 1).start);
+        true
+    }
+
+    fn next_non_blank_line(&mut self) -> bool {
+        let index = self.index();
+
+        while self.next_line() {
+            if !is_blank(self.line()) {
+                return true;
+            }
+        }
+
+        self.set_index(index);
+        false
+    }
+
+    fn prev_non_blank_line(&mut self) -> bool {
+        let index = self.index();
+
+        while self.prev_line() {
+            if !is_blank(self.line()) {
+                return true;
+            }
+        }
+
+        self.set_index(index);
+        false
+    }
+
+    /// Moves to the IP address of the next entry.
+    fn next_entry(&mut self) -> bool {
+        self.seek_forward(|node| matches!(node, CstNode::IP(_)))
+    }
+
+    /// Moves to the IP address of the closest entry before the cursor.
+    fn prev_entry(&mut self) -> bool {
+        self.seek_backward(|node| matches!(node, CstNode::IP(_)))
+    }
+
+    fn next_comment(&mut self) -> bool {
+        self.seek_forward(|node| matches!(node, CstNode::Comment(_)))
+    }
+
+    fn prev_comment(&mut self) -> bool {
+        self.seek_backward(|node| matches!(node, CstNode::Comment(_)))
+    }
+
+    /// The entry on the line under the cursor.
+    fn entry(&self) -> Option<Entry<'_>> {
+        entry(self.nodes(), self.line_range())
+    }
+
+    /// Comment lines right above the line under the cursor, in file order.
+    fn comments_before(&self) -> Vec<&str> {
+        comments_before(self.nodes(), self.line_range().start)
+    }
+
+    /// Nodes of the block under the cursor: the lines around it that are
+    /// not separated from it by a blank line.
+    fn block_range(&self) -> Range<usize> {
+        let nodes = self.nodes();
+        let line = self.line_range();
+
+        if is_blank(&nodes[line.clone()]) {
+            return line;
+        }
+
+        let mut block = line;
+
+        while block.start > 0 {
+            let previous = line_range(nodes, block.start - 1);
+
+            if is_blank(&nodes[previous.clone()]) {
+                break;
+            }
+
+            block.start = previous.start;
+        }
+
+        while block.end < nodes.len() {
+            let next = line_range(nodes, block.end);
+
+            if is_blank(&nodes[next.clone()]) {
+                break;
+            }
+
+            block.end = next.end;
+        }
+
+        block
+    }
+
+    /// The other entries of the block under the cursor.
+    fn siblings(&self) -> Vec<Entry<'_>> {
+        let nodes = self.nodes();
+        let block = self.block_range();
+        let line = self.line_range();
+        let mut siblings = Vec::new();
+        let mut start = block.start;
+
+        while start < block.end {
+            let range = line_range(nodes, start);
+
+            if range != line {
+                siblings.extend(entry(nodes, range.clone()));
+            }
+
+            start = range.end;
+        }
+
+        siblings
+    }
+
+    fn seek_forward(&mut self, found: impl Fn(&CstNode) -> bool) -> bool {
+        let start = (self.index() + 1).min(self.nodes().len());
+        let position = self.nodes()[start..].iter().position(found);
+
+        if let Some(position) = position {
+            self.set_index(start + position);
+        }
+
+        position.is_some()
+    }
+
+    fn seek_backward(&mut self, found: impl Fn(&CstNode) -> bool) -> bool {
+        let position = self.nodes()[..self.index()].iter().rposition(found);
+
+        if let Some(position) = position {
+            self.set_index(position);
+        }
+
+        position.is_some()
+    }
+}
+
+fn line_range(nodes: &[CstNode], index: usize) -> Range<usize> {
+    let start = nodes[..index.min(nodes.len())]
+        .iter()
+        .rposition(|node| *node == CstNode::NewLine)
+        .map_or(0, |newline| newline + 1);
+    let end = nodes[start..]
+        .iter()
+        .position(|node| *node == CstNode::NewLine)
+        .map_or(nodes.len(), |newline| start + newline + 1);
+
+    start..end
+}
+
+fn is_blank(line: &[CstNode]) -> bool {
+    line.iter().all(|node| {
+        matches!(
+            node,
+            CstNode::Space | CstNode::Tab | CstNode::CarriageReturn | CstNode::NewLine
+        )
+    })
+}
+
+/// The comment of a line holding nothing but whitespace and a comment.
+fn comment_line(line: &[CstNode]) -> Option<&str> {
+    let mut comment = None;
+
+    for node in line {
+        match node {
+            CstNode::Comment(text) => comment = Some(text.as_str()),
+            CstNode::Space | CstNode::Tab | CstNode::CarriageReturn | CstNode::NewLine => {}
+            _ => return None,
+        }
+    }
+
+    comment
+}
+
+fn comments_before(nodes: &[CstNode], mut start: usize) -> Vec<&str> {
+    let mut comments = Vec::new();
+
+    while start > 0 {
+        let line = line_range(nodes, start - 1);
+
+        match comment_line(&nodes[line.clone()]) {
+            Some(comment) => comments.push(comment),
+            None => break,
+        }
+
+        start = line.start;
+    }
+
+    comments.reverse();
+    comments
+}
+
+fn entry(nodes: &[CstNode], range: Range<usize>) -> Option<Entry<'_>> {
+    let mut ip = None;
+    let mut hosts = Vec::new();
+    let mut comment = None;
+
+    for node in &nodes[range.clone()] {
+        match node {
+            CstNode::IP(address) => ip = Some(*address),
+            CstNode::Host(host) => hosts.push(host.as_str()),
+            CstNode::Comment(text) => comment = Some(text.as_str()),
+            _ => {}
+        }
+    }
+
+    Some(Entry {
+        range,
+        ip: ip?,
+        hosts,
+        comment,
+        nodes,
+    })
+}
+
+/// A read-only cursor over a CST.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    nodes: &'a [CstNode],
+    index: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// A cursor on the first node of `cst`.
+    pub fn new<const LENGTH: usize>(cst: &'a Cst<LENGTH>) -> Self {
+        Self {
+            nodes: &cst.nodes,
+            index: 0,
+        }
+    }
+}
+
+impl<'a> Cursor<'a> {
+    /// Entries from the line under the cursor on, without moving it.
+    pub fn entries(&self) -> impl Iterator<Item = Entry<'a>> + 'a {
+        let nodes = self.nodes;
+        let mut start = line_range(nodes, self.index).start;
+
+        core::iter::from_fn(move || {
+            while start < nodes.len() {
+                let range = line_range(nodes, start);
+                start = range.end;
+
+                if let Some(entry) = entry(nodes, range) {
+                    return Some(entry);
+                }
+            }
+
+            None
+        })
+    }
+}
+
+impl<'a> Navigate for Cursor<'a> {
+    fn nodes(&self) -> &[CstNode] {
+        self.nodes
+    }
+
+    fn index(&self) -> usize {
+        self.index
+    }
+
+    fn set_index(&mut self, index: usize) {
+        self.index = index.min(self.nodes.len());
+    }
+}
+
+/// A range of nodes taken from a [`CursorMut`], which keeps track of it
+/// through later edits made with the same cursor.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct EditRange {
+    start: usize,
+    end: usize,
+    // Number of edits the cursor had made when the range was taken.
+    version: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edit {
+    start: usize,
+    removed: usize,
+    inserted: usize,
+}
+
+/// A cursor that edits the CST it moves over.
+///
+/// Every edit goes through [`CursorMut::splice`], which records it so
+/// that [`EditRange`]s taken earlier can be mapped to where their nodes are
+/// now.
+#[derive(Debug)]
+pub struct CursorMut<'a, const LENGTH: usize> {
+    cst: &'a mut Cst<LENGTH>,
+    index: usize,
+    edits: Vec<Edit>,
+}
+
+impl<'a, const LENGTH: usize> CursorMut<'a, LENGTH> {
+    /// A cursor on the first node of `cst`.
+    pub fn new(cst: &'a mut Cst<LENGTH>) -> Self {
+        Self {
+            cst,
+            index: 0,
+            edits: Vec::new(),
+        }
+    }
+
+    /// The node under the cursor, as an edit range.
+    pub fn node_edit_range(&self) -> EditRange {
+        self.edit_range(self.index..(self.index + 1).min(self.cst.nodes.len()))
+    }
+
+    /// The line under the cursor, as an edit range.
+    pub fn line_edit_range(&self) -> EditRange {
+        self.edit_range(self.line_range())
+    }
+
+    pub fn edit_range(&self, range: Range<usize>) -> EditRange {
+        EditRange {
+            start: range.start,
+            end: range.end,
+            version: self.edits.len(),
+        }
+    }
+
+    /// Where the nodes of `range` are now. `None` if an edit since it was
+    /// taken removed or replaced any of them. Nodes inserted inside the
+    /// range become part of it.
+    pub fn resolve(&self, range: EditRange) -> Option<Range<usize>> {
+        let (mut start, mut end) = (range.start, range.end);
+
+        for edit in self.edits.get(range.version..)? {
+            let removed_end = edit.start + edit.removed;
+
+            if end <= edit.start && (start < end || start < edit.start) {
+                continue;
+            }
+
+            if start >= removed_end {
+                start = start - edit.removed + edit.inserted;
+                end = end - edit.removed + edit.inserted;
+            } else if edit.removed == 0 {
+                end += edit.inserted;
+            } else {
+                return None;
+            }
+        }
+
+        Some(start..end)
+    }
+
+    /// Replaces the nodes of `range` with `nodes` and moves to the first
+    /// node inserted, or to where the removed ones were.
+    pub fn splice<I>(&mut self, range: Range<usize>, nodes: I)
+    where
+        I: IntoIterator<Item = CstNode>,
+    {
+        let removed = self.cst.nodes.drain(range.clone()).count();
+        let before = self.cst.nodes.len();
+
+        self.cst.nodes.insert_many(range.start, nodes);
+        self.edits.push(Edit {
+            start: range.start,
+            removed,
+            inserted: self.cst.nodes.len() - before,
+        });
+        self.index = range.start;
+    }
+
+    /// Replaces the nodes `range` stands for. Returns `false`, changing
+    /// nothing, if they were edited away.
+    pub fn replace<I>(&mut self, range: EditRange, nodes: I) -> bool
+    where
+        I: IntoIterator<Item = CstNode>,
+    {
+        match self.resolve(range) {
+            Some(range) => {
+                self.splice(range, nodes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts `nodes` before the node under the cursor.
+    pub fn insert<I>(&mut self, nodes: I)
+    where
+        I: IntoIterator<Item = CstNode>,
+    {
+        self.splice(self.index..self.index, nodes);
+    }
+
+    /// Inserts a line after the line under the cursor and moves to it. A
+    /// line feed is added after `nodes`, and before them if the current line
+    /// has none.
+    pub fn insert_line_after<I>(&mut self, nodes: I)
+    where
+        I: IntoIterator<Item = CstNode>,
+    {
+        let end = self.line_range().end;
+        let needs_newline = end > 0 && self.cst.nodes[end - 1] != CstNode::NewLine;
+        let line = needs_newline
+            .then_some(CstNode::NewLine)
+            .into_iter()
+            .chain(nodes)
+            .chain(Some(CstNode::NewLine));
+
+        self.splice(end..end, line);
+
+        if needs_newline {
+            self.index += 1;
+        }
+    }
+
+    /// Removes the line under the cursor and moves to the start of the
+    /// line that followed it.
+    pub fn remove_line(&mut self) {
+        self.splice(self.line_range(), None);
+    }
+
+    /// Removes the node under the cursor, if any.
+    pub fn remove_node(&mut self) {
+        if self.index < self.cst.nodes.len() {
+            self.splice(self.index..self.index + 1, None);
+        }
+    }
+
+    /// Sets `key` in the comment of the entry under the cursor, adding a
+    /// comment when it has none. Returns `false`, changing nothing, when
+    /// the line is not an entry.
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<bool, metadata::Error> {
+        if self.entry().is_none() {
+            return Ok(false);
+        }
+
+        let line = self.line_range();
+        let (range, text) = match self.trailing_comment(line.clone()) {
+            Some((index, text)) => (index..index + 1, text),
+            None => {
+                let end = self.cst.nodes[line.clone()]
+                    .iter()
+                    .rposition(|node| matches!(node, CstNode::IP(_) | CstNode::Host(_)))
+                    .map_or(line.start, |position| line.start + position + 1);
+
+                (end..end, "")
+            }
+        };
+
+        let mut comment = metadata::Comment::parse(text);
+        comment.set(key, value)?;
+
+        let comment = CstNode::Comment(comment.to_string());
+        let index = self.index;
+
+        match range.is_empty() {
+            true => self.splice(range, [CstNode::Space, comment]),
+            false => self.splice(range, Some(comment)),
+        }
+
+        self.index = index;
+        Ok(true)
+    }
+
+    /// Removes `key` from the comment of the entry under the cursor, along
+    /// with the comment if nothing else is left in it. Returns the value it
+    /// had.
+    pub fn remove_metadata(&mut self, key: &str) -> Option<String> {
+        self.entry()?;
+
+        let line = self.line_range();
+        let (index, text) = self.trailing_comment(line.clone())?;
+        let mut comment = metadata::Comment::parse(text);
+        let value = comment.remove(key)?;
+        let text = comment.to_string();
+        let cursor = self.index;
+
+        if text.trim().is_empty() {
+            let start = self.cst.nodes[line.start..index]
+                .iter()
+                .rposition(|node| !matches!(node, CstNode::Space | CstNode::Tab))
+                .map_or(line.start, |position| line.start + position + 1);
+
+            self.splice(start..index + 1, None);
+        } else {
+            self.splice(index..index + 1, Some(CstNode::Comment(text)));
+        }
+
+        self.index = cursor.min(self.cst.nodes.len());
+        Some(value)
+    }
+
+    fn trailing_comment(&self, line: Range<usize>) -> Option<(usize, &str)> {
+        self.cst.nodes[line.clone()]
+            .iter()
+            .zip(line)
+            .find_map(|(node, index)| match node {
+                CstNode::Comment(text) => Some((index, text.as_str())),
+                _ => None,
+            })
+    }
+}
+
+impl<'a, const LENGTH: usize> Navigate for CursorMut<'a, LENGTH> {
+    fn nodes(&self) -> &[CstNode] {
+        &self.cst.nodes
+    }
+
+    fn index(&self) -> usize {
+        self.index
+    }
+
+    fn set_index(&mut self, index: usize) {
+        self.index = index.min(self.cst.nodes.len());
+    }
+}
+
+impl<const LENGTH: usize> Cst<LENGTH> {
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor::new(self)
+    }
+
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, LENGTH> {
+        CursorMut::new(self)
+    }
+
+    /// Appends a line mapping `hosts` to `ip`, preceded by a line holding
+    /// `comment` when there is one. Hostnames are stored as given.
+    pub fn append_entry<I>(&mut self, ip: IpAddr, hosts: I, comment: Option<&str>)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut line = vec![CstNode::IP(ip)];
+
+        for host in hosts {
+            line.extend([CstNode::Space, CstNode::Host(host)]);
+        }
+
+        let mut cursor = self.cursor_mut();
+        cursor.set_index(cursor.nodes().len());
+
+        if let Some(comment) = comment {
+            cursor.insert_line_after([CstNode::Comment(comment.to_string())]);
+        }
+
+        cursor.insert_line_after(line);
+    }
+
+    /// Removes every occurrence of `host`, compared by its canonical form,
+    /// along with the whitespace before it. Lines left without hosts are
+    /// removed whole. Returns how many were removed.
+    pub fn remove_host(&mut self, host: &str) -> usize {
+        let mut cursor = self.cursor_mut();
+        let mut count = 0;
+
+        loop {
+            let line = cursor.line_range();
+            let nodes = &cursor.nodes()[line.clone()];
+            let hosts = nodes
+                .iter()
+                .filter(|node| matches!(node, CstNode::Host(_)))
+                .count();
+            let matching = line
+                .clone()
+                .zip(nodes)
+                .filter(|(_, node)| matches!(node, CstNode::Host(name) if hostname::eq(name, host)))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            count += matching.len();
+
+            if !matching.is_empty() && matching.len() == hosts {
+                cursor.remove_line();
+
+                if cursor.index() == cursor.nodes().len() {
+                    break;
+                }
+
+                continue;
+            }
+
+            // Later hosts first, so that the indices of earlier ones hold.
+            for &index in matching.iter().rev() {
+                let start = cursor.nodes()[line.start..index]
+                    .iter()
+                    .rposition(|node| !matches!(node, CstNode::Space | CstNode::Tab))
+                    .map_or(line.start, |position| line.start + position + 1);
+
+                cursor.splice(start..index + 1, None);
+            }
+
+            if !cursor.next_line() {
+                break;
+            }
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    const DATA: &str = "\
+# Docker
+# Added by Docker Desktop
+192.168.0.17 host.docker.internal
+192.168.0.17 gateway.docker.internal # gateway
+
+127.0.0.1 localhost
+\t
+::1 localhost
+";
+
+    fn parse(data: &str) -> Cst<1> {
+        Parser::default().parse_str(data).unwrap()
+    }
+
+    #[test]
+    fn test_lines() {
+        let cst = parse(DATA);
+        let mut cursor = cst.cursor();
+
+        assert!(cursor.next_entry());
+        assert_eq!(3, cursor.line_number());
+        assert_eq!(
+            vec![" Docker", " Added by Docker Desktop"],
+            cursor.comments_before()
+        );
+
+        assert!(cursor.next_non_blank_line());
+        assert_eq!(4, cursor.line_number());
+        assert!(cursor.next_non_blank_line());
+        assert_eq!(6, cursor.line_number());
+        assert!(cursor.prev_non_blank_line());
+        assert_eq!(4, cursor.line_number());
+
+        assert!(cursor.next_comment());
+        assert_eq!(
+            Some(&CstNode::Comment(" gateway".to_string())),
+            cursor.node()
+        );
+        assert!(!cursor.next_comment());
+
+        while cursor.next_line() {}
+        assert_eq!(8, cursor.line_number());
+        assert!(!cursor.next_non_blank_line());
+        assert!(cursor.prev_entry());
+        assert_eq!(6, cursor.line_number());
+    }
+
+    #[test]
+    fn test_entry_and_siblings() {
+        let cst = parse(DATA);
+        let mut cursor = cst.cursor();
+
+        assert!(cursor.next_entry());
+        assert!(cursor.next_entry());
+
+        let entry = cursor.entry().unwrap();
+        assert_eq!(vec!["gateway.docker.internal"], entry.hosts);
+        assert_eq!(Some(" gateway"), entry.comment);
+
+        let siblings = cursor.siblings();
+        assert_eq!(1, siblings.len());
+        assert_eq!(vec!["host.docker.internal"], siblings[0].hosts);
+
+        assert!(cursor.next_entry());
+        assert!(cursor.siblings().is_empty());
+        assert!(cursor.comments_before().is_empty());
+    }
+
+    #[test]
+    fn test_edit_ranges() {
+        let mut cst = parse(DATA);
+        let mut cursor = cst.cursor_mut();
+
+        cursor.next_entry();
+        cursor.next_entry();
+        cursor.next_entry();
+        let localhost = cursor.line_edit_range();
+        cursor.prev_entry();
+        let gateway = cursor.line_edit_range();
+
+        // Edits before a range move it; edits of its nodes invalidate it.
+        cursor.set_index(0);
+        cursor.remove_line();
+        cursor.insert_line_after([
+            CstNode::IP("10.0.0.1".parse().unwrap()),
+            CstNode::Space,
+            CstNode::Host("db.dev".to_string()),
+        ]);
+        assert!(cursor.replace(gateway, None));
+        assert_eq!(None, cursor.resolve(gateway));
+
+        let range = cursor.resolve(localhost).unwrap();
+        cursor.set_index(range.start);
+        assert_eq!(vec!["localhost"], cursor.entry().unwrap().hosts);
+        drop(cursor);
+
+        assert_eq!(
+            "\
+# Added by Docker Desktop
+10.0.0.1 db.dev
+192.168.0.17 host.docker.internal
+
+127.0.0.1 localhost
+\t
+::1 localhost
+",
+            cst.to_string()
+        );
+    }
+
+    #[test]
+    fn test_append_and_remove_hosts() {
+        let mut cst = parse("# dev\n127.0.0.1 a.test b.test\n10.0.0.1\tb.test\n::1 B.test.");
+
+        assert_eq!(3, cst.remove_host("b.test"));
+        assert_eq!(0, cst.remove_host("b.test"));
+        assert_eq!("# dev\n127.0.0.1 a.test\n", cst.to_string());
+
+        let mut cst = parse("127.0.0.1 localhost");
+        cst.append_entry(
+            "::1".parse().unwrap(),
+            ["db.test".to_string(), "cache.test".to_string()],
+            Some(" services"),
+        );
+
+        assert_eq!(
+            "127.0.0.1 localhost\n# services\n::1 db.test cache.test\n",
+            cst.to_string()
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut cst = parse(
+            "\
+# owner=payments ticket=OPS-1
+# note=\"primary db\"
+10.1.2.3 db.dev  # owner=core expires=2026-12-01
+
+10.1.2.4 cache.dev
+",
+        );
+        let entries = cst.cursor().entries().collect::<Vec<_>>();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(
+            vec![
+                ("expires", "2026-12-01"),
+                ("note", "primary db"),
+                ("owner", "core"),
+                ("ticket", "OPS-1"),
+            ],
+            entries[0]
+                .metadata()
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert!(entries[1].metadata().is_empty());
+
+        let mut cursor = cst.cursor_mut();
+        assert_eq!(Ok(false), cursor.set_metadata("owner", "x"));
+        assert!(cursor.next_entry());
+        assert_eq!(Ok(true), cursor.set_metadata("owner", "db team"));
+        assert_eq!(
+            Some("2026-12-01".to_string()),
+            cursor.remove_metadata("expires")
+        );
+        assert_eq!(None, cursor.remove_metadata("expires"));
+        assert!(cursor.next_entry());
+        assert_eq!(Ok(true), cursor.set_metadata("owner", "core"));
+        assert_eq!(
+            Some(&CstNode::IP("10.1.2.4".parse().unwrap())),
+            cursor.node()
+        );
+        drop(cursor);
+
+        assert_eq!(
+            "\
+# owner=payments ticket=OPS-1
+# note=\"primary db\"
+10.1.2.3 db.dev  # owner=\"db team\"
+
+10.1.2.4 cache.dev # owner=core
+",
+            cst.to_string()
+        );
+
+        let mut cursor = cst.cursor_mut();
+        cursor.next_entry();
+        cursor.next_entry();
+        assert_eq!(Some("core".to_string()), cursor.remove_metadata("owner"));
+        drop(cursor);
+
+        assert!(cst.to_string().ends_with("\n10.1.2.4 cache.dev\n"));
+    }
+
+    #[test]
+    fn test_insert_inside_range() {
+        let mut cst = parse("127.0.0.1 localhost\n");
+        let mut cursor = cst.cursor_mut();
+        let line = cursor.line_edit_range();
+
+        cursor.next_entry();
+        cursor.set_index(3);
+        cursor.insert([CstNode::Space, CstNode::Host("local".to_string())]);
+
+        assert_eq!(Some(0..6), cursor.resolve(line));
+
+        cursor.set_index(6);
+        cursor.insert_line_after([CstNode::Comment(" end".to_string())]);
+        assert_eq!(Some(0..6), cursor.resolve(line));
+        drop(cursor);
+
+        assert_eq!("127.0.0.1 localhost local\n# end\n", cst.to_string());
+    }
+}
@@ -0,0 +1,432 @@
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+#[cfg(feature = "watch")]
+use std::time::Duration;
+
+use crate::cache::fnv1a;
+use crate::cst::Cst;
+use crate::error::Error;
+use crate::parser::Parser;
+use crate::resolver::HostsTable;
+use crate::trie::SuffixTrie;
+
+/// One parsed version of the hosts file behind a [`HostsDb`].
+///
+/// Snapshots never change; a reload builds a new one, so everything read
+/// from the same snapshot is consistent.
+#[derive(Debug)]
+pub struct Snapshot {
+    cst: Cst<1>,
+    index: SuffixTrie,
+    table: HostsTable,
+    hash: u64,
+    generation: u64,
+}
+
+impl Snapshot {
+    fn parse(data: &[u8], generation: u64) -> Result<Self, Error> {
+        let cst = Parser::default().parse_bytes(data)?;
+        let index = cst.entries().collect();
+        let table = HostsTable::from_cst(&cst);
+
+        Ok(Self {
+            cst,
+            index,
+            table,
+            hash: fnv1a(data),
+            generation,
+        })
+    }
+
+    pub fn cst(&self) -> &Cst<1> {
+        &self.cst
+    }
+
+    /// Index of the hostnames of the file, for matching parent domains.
+    /// It holds the first entry of each name.
+    pub fn index(&self) -> &SuffixTrie {
+        &self.index
+    }
+
+    /// Every address of every hostname of the file.
+    pub fn table(&self) -> &HostsTable {
+        &self.table
+    }
+
+    /// Number of times the file was reloaded with new contents before this
+    /// snapshot was taken, starting at 0.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Every address `host` is declared with, in file order, so that
+    /// `localhost` resolves to both `127.0.0.1` and `::1`. Empty if the
+    /// file does not declare it.
+    pub fn lookup(&self, host: &str) -> &[IpAddr] {
+        self.table.lookup(host)
+    }
+}
+
+/// A hosts file shared by many threads, reloaded when it changes.
+///
+/// Cloning is cheap and every clone sees the same file. Readers take the
+/// current [`Snapshot`] and keep it as long as they need consistent answers,
+/// while reloads swap in a new one. When the file stops parsing, for
+/// instance while an editor is halfway through writing it, the previous
+/// snapshot stays current and the error is kept in
+/// [`HostsDb::reload_error`]. A file truncated and rewritten in place can
+/// still be seen empty in between; replace it with a rename to avoid that.
+#[derive(Debug, Clone)]
+pub struct HostsDb {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    path: PathBuf,
+    current: RwLock<Arc<Snapshot>>,
+    error: Mutex<Option<Arc<Error>>>,
+    // Held while reading and parsing, so that reloads finish in order.
+    reloading: Mutex<()>,
+    #[cfg(feature = "watch")]
+    watcher: Mutex<Option<Box<dyn notify::Watcher + Send>>>,
+}
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared")
+            .field("path", &self.path)
+            .field("current", &self.current)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HostsDb {
+    /// Parses the hosts file at `path`. It is only read again by
+    /// [`HostsDb::reload`].
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let snapshot = Snapshot::parse(&fs::read(&path)?, 0)?;
+
+        Ok(Self {
+            shared: Arc::new(Shared {
+                path,
+                current: RwLock::new(Arc::new(snapshot)),
+                error: Mutex::new(None),
+                reloading: Mutex::new(()),
+                #[cfg(feature = "watch")]
+                watcher: Mutex::new(None),
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.shared.path
+    }
+
+    /// The current snapshot of the file.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.shared
+            .current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Looks `host` up in the current snapshot. See [`Snapshot::lookup`].
+    pub fn lookup(&self, host: &str) -> Vec<IpAddr> {
+        self.snapshot().lookup(host).to_vec()
+    }
+
+    /// Reads the file again and swaps in a new snapshot if its contents
+    /// changed. Returns whether they did.
+    ///
+    /// On error the current snapshot is kept and the error is also
+    /// remembered for [`HostsDb::reload_error`], hence the [`Arc`].
+    pub fn reload(&self) -> Result<bool, Arc<Error>> {
+        self.shared.reload()
+    }
+
+    /// Error of the last reload, if it failed.
+    pub fn reload_error(&self) -> Option<Arc<Error>> {
+        self.shared
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Shared {
+    fn reload(&self) -> Result<bool, Arc<Error>> {
+        let _reloading = self
+            .reloading
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let current = self
+            .current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+
+        let result = fs::read(&self.path).map_err(Error::from).and_then(|data| {
+            match fnv1a(&data) == current.hash {
+                true => Ok(None),
+                false => Snapshot::parse(&data, current.generation + 1).map(Some),
+            }
+        });
+        let mut error = self.error.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match result {
+            Ok(snapshot) => {
+                *error = None;
+
+                let Some(snapshot) = snapshot else {
+                    return Ok(false);
+                };
+
+                *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(snapshot);
+
+                Ok(true)
+            }
+            Err(failure) => {
+                let failure = Arc::new(failure);
+                *error = Some(failure.clone());
+
+                Err(failure)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "watch")]
+impl HostsDb {
+    /// Opens the hosts file at `path` and reloads it whenever it changes.
+    ///
+    /// Changes are watched with the native mechanism of the platform, such
+    /// as inotify on Linux, falling back to polling every two seconds where
+    /// that is not available. The watch stops with the last clone.
+    pub fn watch<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let db = Self::open(path)?;
+        let handler = db.event_handler();
+
+        // Editors often replace the file instead of writing to it, which
+        // native watches on the file itself would miss, so they watch its
+        // directory.
+        let directory = match db.path().parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        let native = notify::recommended_watcher(handler.clone())
+            .and_then(|watcher| db.start(Box::new(watcher), &directory));
+
+        if native.is_err() {
+            db.watch_polling_inner(handler, Duration::from_secs(2))?;
+        }
+
+        Ok(db)
+    }
+
+    /// Opens the hosts file at `path` and polls it for changes every
+    /// `interval`.
+    pub fn watch_polling<P>(path: P, interval: Duration) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let db = Self::open(path)?;
+        db.watch_polling_inner(db.event_handler(), interval)?;
+
+        Ok(db)
+    }
+
+    fn watch_polling_inner(
+        &self,
+        handler: impl notify::EventHandler + Clone,
+        interval: Duration,
+    ) -> Result<(), Error> {
+        // Modification times are only compared to the second, so the
+        // contents are compared too. Polling goes by path, so the file
+        // itself is watched and replacing it is still noticed.
+        let config = notify::Config::default()
+            .with_poll_interval(interval)
+            .with_compare_contents(true);
+
+        notify::PollWatcher::new(handler, config)
+            .and_then(|watcher| self.start(Box::new(watcher), self.path()))
+            .map_err(|error| Error::new(std::io::Error::other(error)))
+    }
+
+    fn start(
+        &self,
+        mut watcher: Box<dyn notify::Watcher + Send>,
+        target: &Path,
+    ) -> notify::Result<()> {
+        watcher.watch(target, notify::RecursiveMode::NonRecursive)?;
+        *self
+            .shared
+            .watcher
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(watcher);
+
+        Ok(())
+    }
+
+    fn event_handler(&self) -> impl notify::EventHandler + Clone {
+        use notify::event::{AccessKind, EventKind, MetadataKind, ModifyKind};
+
+        // Weak, so that the watcher owned by `Shared` does not keep it alive.
+        let shared = Arc::downgrade(&self.shared);
+        let name = self.path().file_name().map(ToOwned::to_owned);
+
+        move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            let relevant = !matches!(
+                event.kind,
+                EventKind::Access(AccessKind::Any | AccessKind::Read | AccessKind::Open(_))
+                    | EventKind::Access(AccessKind::Close(_))
+                    | EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime))
+            ) && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == name.as_deref());
+
+            if let (true, Some(shared)) = (relevant, shared.upgrade()) {
+                // Failures are kept for `reload_error`.
+                let _ = shared.reload();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_reload() {
+        assert_send_sync::<HostsDb>();
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.5 db.dev\n").unwrap();
+
+        let db = HostsDb::open(&path).unwrap();
+        let before = db.snapshot();
+
+        assert_eq!(vec![ip("10.0.0.5")], db.lookup("DB.dev"));
+        assert!(!db.reload().unwrap());
+
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.6 db.dev\n").unwrap();
+
+        assert!(db.clone().reload().unwrap());
+        assert_eq!(vec![ip("10.0.0.6")], db.lookup("db.dev"));
+        assert_eq!(1, db.snapshot().generation());
+        // Readers holding the old snapshot keep seeing the old file.
+        assert_eq!(&[ip("10.0.0.5")], before.lookup("db.dev"));
+    }
+
+    #[test]
+    fn test_lookup_dual_stack() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n::1 localhost\n").unwrap();
+
+        let db = HostsDb::open(&path).unwrap();
+
+        assert_eq!(vec![ip("127.0.0.1"), ip("::1")], db.lookup("localhost"));
+        assert_eq!(
+            HostsTable::parse("127.0.0.1 localhost\n::1 localhost\n")
+                .unwrap()
+                .lookup("localhost"),
+            db.snapshot().lookup("localhost")
+        );
+        assert!(db.lookup("missing.test").is_empty());
+    }
+
+    #[test]
+    fn test_reload_keeps_snapshot_on_error() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        fs::write(&path, "10.0.0.5 db.dev\n").unwrap();
+
+        let db = HostsDb::open(&path).unwrap();
+        fs::write(&path, "db.dev 10.0.0.5\n").unwrap();
+
+        let error = db.reload().unwrap_err();
+
+        assert_eq!(Some(1), error.line());
+        assert_eq!(Some(1), db.reload_error().unwrap().line());
+        assert_eq!(vec![ip("10.0.0.5")], db.lookup("db.dev"));
+
+        fs::remove_file(&path).unwrap();
+        assert!(db.reload().is_err());
+        assert_eq!(0, db.snapshot().generation());
+
+        fs::write(&path, "10.0.0.7 db.dev\n").unwrap();
+        assert!(db.reload().unwrap());
+        assert!(db.reload_error().is_none());
+    }
+
+    /// Waits until `db` resolves `host` to `ip`.
+    #[cfg(feature = "watch")]
+    fn wait_for(db: &HostsDb, host: &str, ip: &str) {
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+
+        while db.lookup(host) != [self::ip(ip)] {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "the file was not reloaded"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch_polling() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        fs::write(&path, "10.0.0.5 db.dev\n").unwrap();
+
+        let db = HostsDb::watch_polling(&path, Duration::from_millis(20)).unwrap();
+
+        // Replaced through a rename, as editors do.
+        let replacement = directory.path().join("hosts.new");
+        fs::write(&replacement, "10.0.0.6 db.dev other.dev\n").unwrap();
+        fs::rename(&replacement, &path).unwrap();
+
+        wait_for(&db, "other.dev", "10.0.0.6");
+        assert_eq!(1, db.snapshot().generation());
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        fs::write(&path, "10.0.0.5 db.dev\n").unwrap();
+
+        let db = HostsDb::watch(&path).unwrap();
+
+        fs::write(&path, "10.0.0.5 db.dev\nnot an entry\n").unwrap();
+        fs::write(&path, "10.0.0.6 db.dev\n").unwrap();
+
+        wait_for(&db, "db.dev", "10.0.0.6");
+    }
+}
@@ -0,0 +1,146 @@
+use alloc::string::{String, ToString};
+use core::fmt::Display;
+use core::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+/// Hosts file rules of a particular resolver.
+///
+/// The same file is read differently by different platforms. A dialect
+/// describes those differences so a file can be checked against a target
+/// platform from any host.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Dialect {
+    /// GNU libc `nss_files`: no line or alias limits, `#` starts a comment
+    /// anywhere on a line.
+    Glibc,
+    /// musl libc: lines are read into a 512 byte buffer, so anything past
+    /// 511 bytes (including the line feed) ends up on a bogus next line.
+    Musl,
+    /// macOS: as on the BSDs, a `#` anywhere on a line ends it, and the
+    /// system relies on the stock `localhost` and `broadcasthost` entries.
+    MacOs,
+    /// Windows: aliases past the ninth hostname on a line are ignored and
+    /// `0.0.0.0` fails immediately instead of reaching this machine.
+    Windows,
+}
+
+const MACOS_DEFAULTS: &[(&str, &str)] = &[
+    ("127.0.0.1", "localhost"),
+    ("255.255.255.255", "broadcasthost"),
+    ("::1", "localhost"),
+];
+
+impl Dialect {
+    pub const ALL: [Dialect; 4] = [
+        Dialect::Glibc,
+        Dialect::Musl,
+        Dialect::MacOs,
+        Dialect::Windows,
+    ];
+
+    /// Dialect of the platform hoster was compiled for.
+    pub const fn native() -> Self {
+        if cfg!(target_os = "windows") {
+            Dialect::Windows
+        } else if cfg!(target_os = "macos") {
+            Dialect::MacOs
+        } else if cfg!(target_env = "musl") {
+            Dialect::Musl
+        } else {
+            Dialect::Glibc
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Dialect::Glibc => "glibc",
+            Dialect::Musl => "musl",
+            Dialect::MacOs => "macos",
+            Dialect::Windows => "windows",
+        }
+    }
+
+    /// Maximum number of hostnames the resolver reads from one line.
+    pub const fn max_hosts_per_line(self) -> Option<usize> {
+        match self {
+            Dialect::Windows => Some(9),
+            _ => None,
+        }
+    }
+
+    /// Maximum length of a line in bytes, counting its line feed.
+    pub const fn max_line_length(self) -> Option<usize> {
+        match self {
+            Dialect::Musl => Some(511),
+            _ => None,
+        }
+    }
+
+    /// Whether a `#` inside a word starts a comment, so that
+    /// `host#note` declares `host`. The Unix resolvers all cut the line at
+    /// its first `#`, the BSD and macOS `gethostent` with `strpbrk`.
+    pub const fn comment_inside_word(self) -> bool {
+        matches!(self, Dialect::Glibc | Dialect::Musl | Dialect::MacOs)
+    }
+
+    /// Whether `0.0.0.0` and `::` are unreachable rather than an alias of
+    /// this machine.
+    pub const fn unspecified_is_unreachable(self) -> bool {
+        matches!(self, Dialect::Windows)
+    }
+
+    /// Entries the platform expects every hosts file to keep, as
+    /// `(ip, hostname)` pairs.
+    pub const fn defaults(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Dialect::MacOs => MACOS_DEFAULTS,
+            _ => &[],
+        }
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::native()
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, ThisError)]
+#[error("unknown dialect `{0}`, expected one of glibc, musl, macos or windows")]
+pub struct UnknownDialect(pub String);
+
+impl FromStr for Dialect {
+    type Err = UnknownDialect;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dialect::ALL
+            .into_iter()
+            .find(|dialect| dialect.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownDialect(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        for dialect in Dialect::ALL {
+            assert_eq!(Ok(dialect), dialect.name().parse());
+        }
+
+        assert_eq!(Ok(Dialect::MacOs), "MacOS".parse());
+        assert_eq!(
+            Err(UnknownDialect("bsd".to_string())),
+            "bsd".parse::<Dialect>()
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use crate::cst::{Cst, CstNode};
+use crate::span::Span;
+
+/// A parsed hosts file together with the source location of every node.
+///
+/// `spans[i]` is the location of `cst.nodes[i]` in the input.
+#[derive(Debug)]
+pub struct Document<const LENGTH: usize> {
+    pub cst: Cst<LENGTH>,
+    pub spans: Vec<Span>,
+}
+
+impl<const LENGTH: usize> Document<LENGTH> {
+    pub fn iter(&self) -> impl Iterator<Item = (&CstNode, &Span)> {
+        self.cst.nodes.iter().zip(self.spans.iter())
+    }
+
+    pub fn span(&self, idx: usize) -> Option<&Span> {
+        self.spans.get(idx)
+    }
+
+    /// Number of lines in the document, counting an unterminated last line.
+    pub fn lines(&self) -> usize {
+        self.spans.last().map_or(0, |span| span.line)
+    }
+
+    pub fn into_cst(self) -> Cst<LENGTH> {
+        self.cst
+    }
+}
+
+impl<const LENGTH: usize> Display for Document<LENGTH> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.cst.fmt(f)
+    }
+}
@@ -0,0 +1,486 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt::{self, Display, Write};
+
+use thiserror::Error as ThisError;
+
+use crate::aliases;
+use crate::arena;
+use crate::cache;
+use crate::cst::CstNode;
+use crate::hostname;
+use crate::metadata;
+use crate::parser;
+use crate::span::Span;
+use crate::tokenizer;
+
+#[derive(Debug, ThisError)]
+pub enum ErrorKind {
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Tokenizer(#[from] tokenizer::Error),
+
+    #[error(transparent)]
+    Parser(#[from] parser::Error),
+
+    #[error(transparent)]
+    Hostname(#[from] hostname::Error),
+
+    #[error(transparent)]
+    Cache(#[from] cache::Error),
+
+    #[error(transparent)]
+    Metadata(#[from] metadata::Error),
+
+    #[error(transparent)]
+    Aliases(#[from] aliases::Error),
+
+    #[error(transparent)]
+    Arena(#[from] arena::Error),
+}
+
+/// Output format of [`Error::render`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Format {
+    /// Multi-line snippet with the offending text underlined, like rustc.
+    #[default]
+    Rich,
+    /// Single `file:line:column: error: message` line, like gcc.
+    Plain,
+    /// One JSON object, for editor tooling.
+    Json,
+}
+
+/// Error returned by every high level hoster API.
+///
+/// Besides the underlying [`ErrorKind`] it carries, when known, the span of
+/// the offending token, the text of the line it is on and a hint on how to
+/// fix it.
+#[derive(Debug)]
+pub struct Error {
+    inner: Box<Inner>,
+}
+
+// Boxed so that results carrying an `Error` stay one pointer wide.
+#[derive(Debug)]
+struct Inner {
+    kind: ErrorKind,
+    span: Option<Span>,
+    column: Option<usize>,
+    line_text: Option<String>,
+    help: Option<&'static str>,
+}
+
+impl Error {
+    pub fn new<K>(kind: K) -> Self
+    where
+        K: Into<ErrorKind>,
+    {
+        let kind = kind.into();
+        let help = help_for(&kind);
+
+        Self {
+            inner: Box::new(Inner {
+                kind,
+                span: None,
+                column: None,
+                line_text: None,
+                help,
+            }),
+        }
+    }
+
+    /// Creates an error pointing at `source[start..end]`.
+    pub fn located<K>(kind: K, source: &[u8], start: usize, end: usize) -> Self
+    where
+        K: Into<ErrorKind>,
+    {
+        let start = start.min(source.len());
+        let end = end.clamp(start, source.len());
+
+        let line_start = memchr::memrchr(b'\n', &source[..start]).map_or(0, |i| i + 1);
+        let line_end =
+            memchr::memchr2(b'\n', b'\r', &source[start..]).map_or(source.len(), |i| start + i);
+        let line = memchr::memchr_iter(b'\n', &source[..start]).count() + 1;
+
+        let mut error = Self::new(kind);
+        error.inner.span = Some(Span::new(start, end, line));
+        error.inner.column = Some(
+            String::from_utf8_lossy(&source[line_start..start])
+                .chars()
+                .count()
+                + 1,
+        );
+        error.inner.line_text =
+            Some(String::from_utf8_lossy(&source[line_start..line_end]).into_owned());
+
+        error
+    }
+
+    /// Moves the span by `offset` bytes and `lines` lines, for errors found
+    /// in a slice of a larger input.
+    pub fn shift(mut self, offset: usize, lines: usize) -> Self {
+        self.inner.span = self.inner.span.map(|span| span.shift(offset, lines));
+        self
+    }
+
+    pub fn with_help(mut self, help: &'static str) -> Self {
+        self.inner.help = Some(help);
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        self.inner.kind
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.inner.span
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.inner.span.map(|span| span.line)
+    }
+
+    /// 1-based column of the start of the span, counted in characters.
+    pub fn column(&self) -> Option<usize> {
+        self.inner.column
+    }
+
+    pub fn line_text(&self) -> Option<&str> {
+        self.inner.line_text.as_deref()
+    }
+
+    pub fn help(&self) -> Option<&'static str> {
+        self.inner.help
+    }
+
+    /// Renders the error as a diagnostic. `origin` is usually the path of
+    /// the file that was parsed.
+    pub fn render(&self, format: Format, origin: Option<&str>) -> String {
+        let mut output = String::new();
+
+        let result = match format {
+            Format::Rich => self.render_rich(&mut output, origin),
+            Format::Plain => self.render_plain(&mut output, origin),
+            Format::Json => self.render_json(&mut output, origin),
+        };
+
+        result.expect("writing to a String does not fail");
+        output
+    }
+
+    fn render_rich(&self, out: &mut String, origin: Option<&str>) -> fmt::Result {
+        writeln!(out, "error: {}", self.inner.kind)?;
+
+        if let (Some(span), Some(column), Some(text)) =
+            (self.inner.span, self.inner.column, &self.inner.line_text)
+        {
+            let line = span.line.to_string();
+            let gutter = " ".repeat(line.len());
+
+            writeln!(
+                out,
+                "{}--> {}:{}:{}",
+                gutter,
+                origin.unwrap_or("<input>"),
+                line,
+                column
+            )?;
+            writeln!(out, "{} |", gutter)?;
+            writeln!(out, "{} | {}", line, text)?;
+
+            // Keep tabs in the padding so the carets line up with the text.
+            let padding: String = text
+                .chars()
+                .take(column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let width = text
+                .chars()
+                .skip(column - 1)
+                .take(span.len().max(1))
+                .count()
+                .max(1);
+
+            writeln!(out, "{} | {}{}", gutter, padding, "^".repeat(width))?;
+
+            if let Some(help) = self.inner.help {
+                writeln!(out, "{} |", gutter)?;
+                writeln!(out, "{} = help: {}", gutter, help)?;
+            }
+        } else if let Some(help) = self.inner.help {
+            writeln!(out, "  = help: {}", help)?;
+        }
+
+        Ok(())
+    }
+
+    fn render_plain(&self, out: &mut String, origin: Option<&str>) -> fmt::Result {
+        let location = match (self.line(), self.inner.column) {
+            (Some(line), Some(column)) => {
+                format!("{}:{}:{}: ", origin.unwrap_or("<input>"), line, column)
+            }
+            _ => origin
+                .map(|origin| format!("{}: ", origin))
+                .unwrap_or_default(),
+        };
+
+        writeln!(out, "{}error: {}", location, self.inner.kind)?;
+
+        if let Some(help) = self.inner.help {
+            writeln!(out, "{}help: {}", location, help)?;
+        }
+
+        Ok(())
+    }
+
+    fn render_json(&self, out: &mut String, origin: Option<&str>) -> fmt::Result {
+        out.push_str("{\"severity\":\"error\",\"message\":");
+        write_json_string(out, &self.inner.kind.to_string())?;
+
+        out.push_str(",\"file\":");
+        write_json_option(out, origin)?;
+
+        match (self.inner.span, self.inner.column) {
+            (Some(span), Some(column)) => write!(
+                out,
+                ",\"line\":{},\"column\":{},\"start\":{},\"end\":{}",
+                span.line, column, span.start, span.end
+            )?,
+            _ => out.push_str(",\"line\":null,\"column\":null,\"start\":null,\"end\":null"),
+        }
+
+        out.push_str(",\"line_text\":");
+        write_json_option(out, self.inner.line_text.as_deref())?;
+        out.push_str(",\"help\":");
+        write_json_option(out, self.inner.help)?;
+        out.push('}');
+
+        Ok(())
+    }
+}
+
+fn write_json_option(out: &mut String, value: Option<&str>) -> fmt::Result {
+    match value {
+        Some(value) => write_json_string(out, value),
+        None => {
+            out.push_str("null");
+            Ok(())
+        }
+    }
+}
+
+fn write_json_string(out: &mut String, value: &str) -> fmt::Result {
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+
+    Ok(())
+}
+
+fn help_for(kind: &ErrorKind) -> Option<&'static str> {
+    match kind {
+        ErrorKind::Parser(parser::Error::InvalidIp(_)) => {
+            Some("every entry must start with an IPv4 or IPv6 address followed by its hostnames")
+        }
+        ErrorKind::Parser(parser::Error::UnexpectedToken(CstNode::Comment(_))) => {
+            Some("a comment runs until the end of the line")
+        }
+        ErrorKind::Parser(parser::Error::UnexpectedToken(_)) => {
+            Some("a carriage return must be followed by a line feed")
+        }
+        ErrorKind::Parser(parser::Error::ExpectingToken(CstNode::NewLine)) => {
+            Some("end the last line with a line feed after the carriage return")
+        }
+        ErrorKind::Parser(parser::Error::Utf8Error(_))
+        | ErrorKind::Tokenizer(tokenizer::Error::Utf8Error(_)) => {
+            Some("hosts files must be encoded as UTF-8")
+        }
+        ErrorKind::Parser(parser::Error::IpNotInFirstColumn(_)) => {
+            Some("remove the whitespace before the IP address")
+        }
+        ErrorKind::Parser(parser::Error::TooManyHosts(_))
+        | ErrorKind::Parser(parser::Error::LineTooLong { .. }) => {
+            Some("split the hostnames over several lines with the same IP address")
+        }
+        ErrorKind::Parser(parser::Error::Ipv6NotAllowed(_)) => {
+            Some("only IPv4 entries are accepted here")
+        }
+        ErrorKind::Hostname(_) | ErrorKind::Parser(parser::Error::InvalidHostname(_)) => {
+            Some("hostnames are dot separated labels of letters, digits and hyphens")
+        }
+        ErrorKind::Cache(_) => Some("delete the cache file to rebuild it from its source"),
+        ErrorKind::Metadata(_) => {
+            Some("metadata is written as key=value, with keys of letters, digits, `_`, `-` and `.`")
+        }
+        ErrorKind::Aliases(aliases::Error::Utf8Error(_)) => {
+            Some("alias files must be encoded as UTF-8")
+        }
+        ErrorKind::Aliases(aliases::Error::InvalidAlias(_)) => {
+            Some("resolvers only look up names without dots in the alias file")
+        }
+        ErrorKind::Aliases(_) => Some("every line maps one alias to one hostname"),
+        ErrorKind::Arena(_) => Some("split the hosts files over several arenas"),
+        _ => None,
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line() {
+            Some(line) => write!(f, "line {}: {}", line, self.inner.kind),
+            None => self.inner.kind.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.inner.kind.source()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<tokenizer::Error> for Error {
+    fn from(error: tokenizer::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<parser::Error> for Error {
+    fn from(error: parser::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<hostname::Error> for Error {
+    fn from(error: hostname::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<metadata::Error> for Error {
+    fn from(error: metadata::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<aliases::Error> for Error {
+    fn from(error: aliases::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<arena::Error> for Error {
+    fn from(error: arena::Error) -> Self {
+        Self::new(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_ip() -> Error {
+        let source = b"127.0.0.1 localhost\n  bad-ip\texample.com\n";
+
+        Error::located(
+            parser::Error::InvalidIp("bad-ip".to_string()),
+            source,
+            22,
+            28,
+        )
+    }
+
+    #[test]
+    fn test_located() {
+        let error = invalid_ip();
+
+        assert_eq!(Some(Span::new(22, 28, 2)), error.span());
+        assert_eq!(Some(3), error.column());
+        assert_eq!(Some("  bad-ip\texample.com"), error.line_text());
+        assert_eq!("line 2: invalid IP address `bad-ip`", error.to_string());
+    }
+
+    #[test]
+    fn test_render_rich() {
+        assert_eq!(
+            "\
+error: invalid IP address `bad-ip`
+ --> hosts:2:3
+  |
+2 |   bad-ip\texample.com
+  |   ^^^^^^
+  |
+  = help: every entry must start with an IPv4 or IPv6 address followed by its hostnames
+",
+            invalid_ip().render(Format::Rich, Some("hosts"))
+        );
+    }
+
+    #[test]
+    fn test_render_plain() {
+        assert_eq!(
+            "\
+hosts:2:3: error: invalid IP address `bad-ip`
+hosts:2:3: help: every entry must start with an IPv4 or IPv6 address followed by its hostnames
+",
+            invalid_ip().render(Format::Plain, Some("hosts"))
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        assert_eq!(
+            concat!(
+                r#"{"severity":"error","message":"invalid IP address `bad-ip`","file":null,"#,
+                r#""line":2,"column":3,"start":22,"end":28,"line_text":"  bad-ip\texample.com","#,
+                r#""help":"every entry must start with an IPv4 or IPv6 address followed by its hostnames"}"#
+            ),
+            invalid_ip().render(Format::Json, None)
+        );
+    }
+
+    #[test]
+    fn test_render_without_location() {
+        let error = Error::new(parser::Error::ExpectingToken(CstNode::NewLine));
+
+        assert_eq!(None, error.line());
+        assert_eq!(
+            "error: expected line feed\n  = help: end the last line with a line feed after the carriage return\n",
+            error.render(Format::Rich, None)
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Random hosts files, for property tests and fuzzing of code built on the
+//! CST.
+//!
+//! A [`HostsFile`] is a description of a file that renders to its text with
+//! [`Display`]. Files come from the `arbitrary` crate with the `arbitrary`
+//! feature, and from the strategies of [`strategy`] with the `proptest`
+//! feature.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::net::IpAddr;
+
+/// Line feeds of a generated file.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// An `ip hostname...` line.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Entry {
+    /// Spaces and tabs before the address, possibly none.
+    pub indent: String,
+    pub ip: IpAddr,
+    /// Hostname and its aliases.
+    pub hosts: Vec<String>,
+    /// Spaces and tabs between words, never empty.
+    pub separator: String,
+    /// Text of a trailing comment, after its `#`.
+    pub comment: Option<String>,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}", self.indent, self.ip)?;
+
+        for host in &self.hosts {
+            write!(f, "{}{}", self.separator, host)?;
+        }
+
+        match &self.comment {
+            Some(comment) => write!(f, "{}#{}", self.separator, comment),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Line {
+    Entry(Entry),
+    /// A comment line, holding the text after the `#`.
+    Comment(String),
+    /// Spaces and tabs only, possibly none.
+    Blank(String),
+    /// A line the parser rejects, such as one with an invalid address or a
+    /// stray carriage return.
+    Malformed(String),
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Line::Entry(entry) => entry.fmt(f),
+            Line::Comment(comment) => write!(f, "#{}", comment),
+            Line::Blank(text) | Line::Malformed(text) => f.write_str(text),
+        }
+    }
+}
+
+/// A generated hosts file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HostsFile {
+    /// Whether the file starts with a UTF-8 byte order mark.
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    pub lines: Vec<Line>,
+    /// Whether the last line is terminated.
+    pub final_newline: bool,
+}
+
+impl HostsFile {
+    /// Whether the parser accepts the file with its default options.
+    pub fn is_valid(&self) -> bool {
+        !self
+            .lines
+            .iter()
+            .any(|line| matches!(line, Line::Malformed(_)))
+    }
+
+    /// Entries of the file, in order.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// Text of the file without its byte order mark, which is what
+    /// [`Cst`](crate::cst::Cst) renders back.
+    pub fn body(&self) -> String {
+        let mut body = alloc::format!("{}", self);
+
+        if self.bom {
+            body.drain(..BOM.len());
+        }
+
+        body
+    }
+}
+
+const BOM: &str = "\u{feff}";
+
+impl Display for HostsFile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.bom {
+            f.write_str(BOM)?;
+        }
+
+        for (i, line) in self.lines.iter().enumerate() {
+            line.fmt(f)?;
+
+            if i + 1 < self.lines.len() || self.final_newline {
+                f.write_str(self.line_ending.as_str())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Words that are not addresses, or not in the right place, each paired
+// with the rest of its line.
+const MALFORMED: &[(&str, &str)] = &[
+    ("300.1.2.3", " example.com"),
+    ("127.0.0", " localhost"),
+    ("not-an-ip", ""),
+    ("localhost", " 127.0.0.1"),
+    ("::1::", " ip6-localhost"),
+    ("127.0.0.1\rlocalhost", ""),
+    ("fe80::1%", " router"),
+];
+
+const SEPARATORS: &[&str] = &[" ", "\t", "  ", " \t", "\t\t"];
+
+const LABEL_CHARACTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-";
+
+// Characters of comments, including some that separate words elsewhere.
+const COMMENT_CHARACTERS: &[char] = &[
+    'a', 'z', 'A', '0', ' ', '\t', '#', '.', '-', ':', 'ü', '€', '🦀',
+];
+
+/// A hostname label: letters, digits and inner hyphens.
+fn label(characters: impl IntoIterator<Item = u8>) -> String {
+    let mut label: String = characters
+        .into_iter()
+        .map(|c| LABEL_CHARACTERS[c as usize % LABEL_CHARACTERS.len()] as char)
+        .collect();
+
+    if label.is_empty() {
+        label.push('a');
+    }
+
+    if label.starts_with('-') {
+        label.replace_range(..1, "a");
+    }
+
+    if label.ends_with('-') {
+        label.pop();
+        label.push('z');
+    }
+
+    label
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+    use super::*;
+
+    fn pick<'a>(u: &mut Unstructured<'_>, choices: &[&'a str]) -> Result<&'a str> {
+        u.choose(choices).copied()
+    }
+
+    fn hostname(u: &mut Unstructured<'_>) -> Result<String> {
+        let labels = u.int_in_range(1..=4)?;
+        let mut host = String::new();
+
+        for i in 0..labels {
+            if i > 0 {
+                host.push('.');
+            }
+
+            let length = u.int_in_range(1..=12)?;
+            let characters = (0..length)
+                .map(|_| u.arbitrary::<u8>())
+                .collect::<Result<Vec<_>>>()?;
+
+            host.push_str(&label(characters));
+        }
+
+        Ok(host)
+    }
+
+    fn comment(u: &mut Unstructured<'_>) -> Result<String> {
+        let length = u.int_in_range(0..=20)?;
+
+        (0..length)
+            .map(|_| u.choose(COMMENT_CHARACTERS).copied())
+            .collect()
+    }
+
+    impl<'a> Arbitrary<'a> for LineEnding {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(match u.arbitrary()? {
+                true => LineEnding::CrLf,
+                false => LineEnding::Lf,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Entry {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let indent = match u.ratio(1, 4)? {
+                true => pick(u, SEPARATORS)?,
+                false => "",
+            };
+            let hosts = u.int_in_range(0..=4)?;
+
+            Ok(Entry {
+                indent: indent.into(),
+                ip: u.arbitrary()?,
+                hosts: (0..hosts).map(|_| hostname(u)).collect::<Result<_>>()?,
+                separator: pick(u, SEPARATORS)?.into(),
+                comment: match u.ratio(1, 4)? {
+                    true => Some(comment(u)?),
+                    false => None,
+                },
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Line {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(match u.int_in_range(0..=9)? {
+                0..=5 => Line::Entry(u.arbitrary()?),
+                6 | 7 => Line::Comment(comment(u)?),
+                8 => Line::Blank(match u.arbitrary()? {
+                    true => pick(u, SEPARATORS)?.into(),
+                    false => String::new(),
+                }),
+                _ => {
+                    let (word, rest) = *u.choose(MALFORMED)?;
+                    Line::Malformed(alloc::format!("{}{}", word, rest))
+                }
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for HostsFile {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(HostsFile {
+                bom: u.ratio(1, 8)?,
+                line_ending: u.arbitrary()?,
+                lines: u.arbitrary()?,
+                final_newline: u.ratio(3, 4)?,
+            })
+        }
+    }
+}
+
+/// Proptest strategies for hosts files and their parts.
+#[cfg(feature = "proptest")]
+pub mod strategy {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    use super::*;
+
+    /// Addresses of either family, including IPv4-mapped IPv6 ones.
+    pub fn ip() -> impl Strategy<Value = IpAddr> {
+        prop_oneof![
+            any::<[u8; 4]>().prop_map(IpAddr::from),
+            any::<[u8; 16]>().prop_map(IpAddr::from),
+            any::<[u8; 4]>()
+                .prop_map(|octets| IpAddr::V6(core::net::Ipv4Addr::from(octets).to_ipv6_mapped())),
+        ]
+    }
+
+    /// Valid hostnames of one to four labels.
+    pub fn hostname() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop::collection::vec(any::<u8>(), 1..12).prop_map(label),
+            1..=4,
+        )
+        .prop_map(|labels| labels.join("."))
+    }
+
+    pub fn comment() -> impl Strategy<Value = String> {
+        prop::collection::vec(select(COMMENT_CHARACTERS), 0..20)
+            .prop_map(|characters| characters.into_iter().collect())
+    }
+
+    fn whitespace() -> impl Strategy<Value = String> {
+        select(SEPARATORS).prop_map(String::from)
+    }
+
+    pub fn entry() -> impl Strategy<Value = Entry> {
+        (
+            prop_oneof![3 => Just(String::new()), 1 => whitespace()],
+            ip(),
+            prop::collection::vec(hostname(), 0..=4),
+            whitespace(),
+            prop::option::weighted(0.25, comment()),
+        )
+            .prop_map(|(indent, ip, hosts, separator, comment)| Entry {
+                indent,
+                ip,
+                hosts,
+                separator,
+                comment,
+            })
+    }
+
+    /// Lines the parser rejects.
+    pub fn malformed_line() -> impl Strategy<Value = Line> {
+        select(MALFORMED)
+            .prop_map(|(word, rest)| Line::Malformed(alloc::format!("{}{}", word, rest)))
+    }
+
+    /// Lines the parser accepts.
+    pub fn valid_line() -> impl Strategy<Value = Line> {
+        prop_oneof![
+            6 => entry().prop_map(Line::Entry),
+            2 => comment().prop_map(Line::Comment),
+            1 => prop_oneof![Just(String::new()), whitespace()].prop_map(Line::Blank),
+        ]
+    }
+
+    pub fn line_ending() -> impl Strategy<Value = LineEnding> {
+        prop_oneof![Just(LineEnding::Lf), Just(LineEnding::CrLf)]
+    }
+
+    fn file(lines: impl Strategy<Value = Vec<Line>>) -> impl Strategy<Value = HostsFile> {
+        (
+            prop::bool::weighted(0.125),
+            line_ending(),
+            lines,
+            prop::bool::weighted(0.75),
+        )
+            .prop_map(|(bom, line_ending, lines, final_newline)| HostsFile {
+                bom,
+                line_ending,
+                lines,
+                final_newline,
+            })
+    }
+
+    /// Files the parser accepts.
+    pub fn valid_hosts_file() -> impl Strategy<Value = HostsFile> {
+        file(prop::collection::vec(valid_line(), 0..32))
+    }
+
+    /// Files with at least one line the parser rejects.
+    pub fn malformed_hosts_file() -> impl Strategy<Value = HostsFile> {
+        (
+            prop::collection::vec(valid_line(), 0..16),
+            prop::collection::vec(malformed_line(), 1..4),
+            prop::collection::vec(valid_line(), 0..16),
+        )
+            .prop_flat_map(|(before, malformed, after)| {
+                let lines = before
+                    .into_iter()
+                    .chain(malformed)
+                    .chain(after)
+                    .collect::<Vec<_>>();
+
+                file(Just(lines).prop_shuffle())
+            })
+    }
+
+    impl Arbitrary for HostsFile {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        /// Valid files three times out of four, malformed ones otherwise.
+        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+            prop_oneof![3 => valid_hosts_file(), 1 => malformed_hosts_file()].boxed()
+        }
+    }
+}
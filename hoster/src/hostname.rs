@@ -0,0 +1,146 @@
+use alloc::string::{String, ToString};
+
+use idna::AsciiDenyList;
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("invalid hostname {0}")]
+    InvalidHostname(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizeOptions {
+    pub strip_trailing_dot: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            strip_trailing_dot: true,
+        }
+    }
+}
+
+/// Converts a hostname into its canonical ASCII (ACE) form.
+///
+/// The name is mapped with UTS-46, so `Example.COM` becomes `example.com`
+/// and `bücher.de` becomes `xn--bcher-kva.de`.
+pub fn normalize(host: &str) -> Result<String, Error> {
+    normalize_with(host, NormalizeOptions::default())
+}
+
+pub fn normalize_with(host: &str, options: NormalizeOptions) -> Result<String, Error> {
+    let mut ascii = idna::domain_to_ascii_cow(host.as_bytes(), AsciiDenyList::URL)
+        .map_err(|_| Error::InvalidHostname(host.to_string()))?
+        .into_owned();
+
+    if ascii.is_empty() {
+        return Err(Error::InvalidHostname(host.to_string()));
+    }
+
+    if options.strip_trailing_dot && ascii.len() > 1 && ascii.ends_with('.') {
+        ascii.pop();
+    }
+
+    Ok(ascii)
+}
+
+/// Checks that `host` is a hostname by RFC 1123: after normalization, at
+/// most 253 bytes of dot separated labels made of letters, digits and inner
+/// hyphens, each up to 63 bytes long.
+pub fn is_valid(host: &str) -> bool {
+    let ascii = match normalize(host) {
+        Ok(ascii) => ascii,
+        Err(_) => return false,
+    };
+
+    ascii.len() <= 253
+        && ascii.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+/// Converts a hostname into its Unicode form, decoding any punycode labels.
+pub fn to_unicode(host: &str) -> String {
+    let (unicode, _) = idna::domain_to_unicode(host);
+
+    unicode
+}
+
+/// Compares two hostnames by their canonical form.
+///
+/// Names that cannot be normalized are compared with ASCII case folding only.
+pub fn eq(a: &str, b: &str) -> bool {
+    match (normalize(a), normalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_lowercases_ascii() {
+        assert_eq!("example.com", normalize("Example.COM").unwrap());
+    }
+
+    #[test]
+    fn test_normalize_trailing_dot() {
+        assert_eq!("example.com", normalize("example.com.").unwrap());
+        assert_eq!(
+            "example.com.",
+            normalize_with(
+                "example.com.",
+                NormalizeOptions {
+                    strip_trailing_dot: false
+                }
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_normalize_punycode() {
+        assert_eq!("xn--bcher-kva.de", normalize("bücher.de").unwrap());
+        assert_eq!("xn--bcher-kva.de", normalize("BÜCHER.de").unwrap());
+        assert_eq!("xn--bcher-kva.de", normalize("xn--bcher-kva.de").unwrap());
+    }
+
+    #[test]
+    fn test_normalize_invalid() {
+        assert!(normalize("").is_err());
+        assert!(normalize("exa mple.com").is_err());
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("localhost"));
+        assert!(is_valid("Bücher.de."));
+        assert!(is_valid("ads-1.example.com"));
+        assert!(!is_valid("exa_mple.com"));
+        assert!(!is_valid("-example.com"));
+        assert!(!is_valid("example..com"));
+        assert!(!is_valid(&"a".repeat(64)));
+    }
+
+    #[test]
+    fn test_to_unicode() {
+        assert_eq!("bücher.de", to_unicode("xn--bcher-kva.de"));
+        assert_eq!("localhost", to_unicode("localhost"));
+    }
+
+    #[test]
+    fn test_eq() {
+        assert!(eq("bücher.de", "xn--bcher-kva.de"));
+        assert!(eq("Example.COM", "example.com."));
+        assert!(!eq("example.com", "example.org"));
+    }
+}
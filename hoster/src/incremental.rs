@@ -0,0 +1,172 @@
+use alloc::string::String;
+use core::ops::Range;
+
+use crate::cst::CstNode;
+use crate::document::Document;
+use crate::error::Error;
+use crate::parser::Parser;
+use crate::span::Span;
+
+/// Replacement of a byte range of a text, as sent by editors.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TextEdit<'a> {
+    pub range: Range<usize>,
+    pub text: &'a str,
+}
+
+impl<'a> TextEdit<'a> {
+    pub fn new(range: Range<usize>, text: &'a str) -> Self {
+        Self { range, text }
+    }
+
+    /// Applies the edit to `input`.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds or does not fall on character
+    /// boundaries.
+    pub fn apply(&self, input: &mut String) {
+        input.replace_range(self.range.clone(), self.text);
+    }
+}
+
+impl<V> Parser<V> {
+    /// Updates `document`, the parse of a text, to the parse of `input`, the
+    /// same text once `edit` was applied to it.
+    ///
+    /// Only the lines the edit touches are parsed again; the nodes of the
+    /// other lines are kept and their spans moved. Returns the indices of the
+    /// new nodes in `document`, for re-checking only what changed.
+    ///
+    /// On error the document is left as it was, and the error location
+    /// refers to `input`.
+    pub fn reparse<const LENGTH: usize>(
+        &self,
+        document: &mut Document<LENGTH>,
+        input: &str,
+        edit: &TextEdit<'_>,
+    ) -> Result<Range<usize>, Error> {
+        let bytes = input.as_bytes();
+        let edit_start = edit.range.start.min(bytes.len());
+        let edit_end = (edit_start + edit.text.len()).min(bytes.len());
+
+        // The text before the edit is the same in both versions, and so is
+        // the text after it, moved by the length difference.
+        let mut start = line_start(bytes, edit_start);
+        let end =
+            memchr::memchr(b'\n', &bytes[edit_end..]).map_or(bytes.len(), |i| edit_end + i + 1);
+        let old_end = end + edit.range.len() - edit.text.len();
+
+        // A byte order mark is only one at the start of the file.
+        while start > 0 && input[start..].starts_with('\u{feff}') {
+            start = line_start(bytes, start - 1);
+        }
+
+        let first = document.spans.partition_point(|span| span.start < start);
+        let last = document.spans.partition_point(|span| span.start < old_end);
+        let line = match first {
+            0 => 1,
+            _ => document.spans[first - 1].line + 1,
+        };
+
+        let region = self
+            .parse_document::<LENGTH>(&input[start..end])
+            .map_err(|error| error.shift(start, line - 1))?;
+
+        let new_lines = memchr::memchr_iter(b'\n', &bytes[start..end]).count();
+        let old_lines = document.cst.nodes[first..last]
+            .iter()
+            .filter(|node| **node == CstNode::NewLine)
+            .count();
+
+        for span in &mut document.spans[last..] {
+            *span = Span::new(
+                span.start - old_end + end,
+                span.end - old_end + end,
+                span.line - old_lines + new_lines,
+            );
+        }
+
+        let inserted = region.spans.len();
+
+        document.spans.splice(
+            first..last,
+            region
+                .spans
+                .into_iter()
+                .map(|span| span.shift(start, line - 1)),
+        );
+        document.cst.nodes.drain(first..last);
+        document.cst.nodes.insert_many(first, region.cst.nodes);
+
+        Ok(first..first + inserted)
+    }
+}
+
+fn line_start(input: &[u8], offset: usize) -> usize {
+    memchr::memrchr(b'\n', &input[..offset]).map_or(0, |i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::ErrorKind;
+
+    fn reparse(input: &str, edit: TextEdit<'_>) -> (Document<1>, Range<usize>, String) {
+        let parser = Parser::default();
+        let mut document = parser.parse_document::<1>(input).unwrap();
+        let mut input = input.to_string();
+
+        edit.apply(&mut input);
+        let changed = parser.reparse(&mut document, &input, &edit).unwrap();
+        let full = parser.parse_document::<1>(&input).unwrap();
+
+        assert_eq!(full.cst.nodes, document.cst.nodes);
+        assert_eq!(full.spans, document.spans);
+
+        (document, changed, input)
+    }
+
+    #[test]
+    fn test_reparse_line() {
+        let input = "# hosts\n127.0.0.1 localhost\n::1 localhost\n";
+        let (document, changed, _) = reparse(input, TextEdit::new(18..27, "example.test"));
+
+        assert_eq!(2..6, changed);
+        assert_eq!(
+            CstNode::Host("example.test".to_string()),
+            document.cst.nodes[4]
+        );
+        assert_eq!(Span::new(44, 45, 3), document.spans[9]);
+    }
+
+    #[test]
+    fn test_reparse_lines() {
+        let input = "127.0.0.1 a\n127.0.0.1 b\n127.0.0.1 c";
+
+        // Joining lines, splitting them and editing the unterminated last one.
+        reparse(input, TextEdit::new(11..12, " "));
+        reparse(input, TextEdit::new(11..11, "\n# new\n"));
+        reparse(input, TextEdit::new(34..35, "d\n"));
+        reparse(input, TextEdit::new(0..input.len(), ""));
+        reparse("", TextEdit::new(0..0, "\u{feff}0.0.0.0 ads\n"));
+    }
+
+    #[test]
+    fn test_reparse_error() {
+        let parser = Parser::default();
+        let input = "127.0.0.1 a\n127.0.0.1 b\n";
+        let mut document = parser.parse_document::<1>(input).unwrap();
+        let edit = TextEdit::new(12..21, "nope");
+        let mut edited = input.to_string();
+
+        edit.apply(&mut edited);
+        let error = parser.reparse(&mut document, &edited, &edit).unwrap_err();
+
+        assert!(matches!(error.kind(), ErrorKind::Parser(_)));
+        assert_eq!(Some(Span::new(12, 16, 2)), error.span());
+        assert_eq!(input, document.to_string());
+    }
+}
@@ -1,7 +1,48 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod aliases;
+pub mod arena;
+
+#[cfg(feature = "tokio")]
+pub mod async_io;
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod compression;
+pub mod cursor;
+#[cfg(feature = "std")]
+pub mod db;
+pub mod dialect;
+pub mod document;
+pub mod error;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+pub mod generate;
+pub mod hostname;
+pub mod incremental;
+pub mod lint;
+pub mod metadata;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod net;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod parser;
+pub mod resolver;
+pub mod span;
 pub mod tokenizer;
 pub mod tokens;
+pub mod trie;
 pub mod visitor;
 
 pub mod cst;
-mod lookahead;
+
+#[cfg(feature = "std")]
+mod temp;
+
+pub use error::{Error, ErrorKind};
+
+#[cfg(feature = "std")]
+pub use cache::{load_cache, read_source, save_cache};
+#[cfg(feature = "mmap")]
+pub use mmap::parse_path;
@@ -0,0 +1,354 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::net::IpAddr;
+
+use crate::cst::CstNode;
+use crate::dialect::Dialect;
+use crate::document::Document;
+use crate::hostname;
+use crate::span::Span;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LintKind {
+    /// The line declares more hostnames than the resolver reads.
+    TooManyHosts { count: usize, max: usize },
+    /// The line, including its line feed, is longer than the resolver reads.
+    LineTooLong { length: usize, max: usize },
+    /// A `#` inside a word cuts the hostname short.
+    CommentInsideWord { host: String },
+    /// A name of this machine points at an address that is unreachable.
+    UnreachableLocalhost { ip: IpAddr, host: String },
+    /// An entry the platform expects is missing.
+    MissingDefault {
+        ip: &'static str,
+        host: &'static str,
+    },
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LintKind::TooManyHosts { count, max } => write!(
+                f,
+                "line declares {} hostnames, only the first {} are read",
+                count, max
+            ),
+            LintKind::LineTooLong { length, max } => write!(
+                f,
+                "line is {} bytes long, the rest after {} bytes is read as a new line",
+                length, max
+            ),
+            LintKind::CommentInsideWord { host } => {
+                let declared = host.split('#').next().unwrap_or_default();
+
+                write!(
+                    f,
+                    "`#` starts a comment inside `{}`, only `{}` is declared",
+                    host, declared
+                )
+            }
+            LintKind::UnreachableLocalhost { ip, host } => write!(
+                f,
+                "`{}` points at `{}`, which is unreachable instead of this machine",
+                host, ip
+            ),
+            LintKind::MissingDefault { ip, host } => {
+                write!(f, "missing default entry `{} {}`", ip, host)
+            }
+        }
+    }
+}
+
+/// A problem found by the [`Linter`]. `span` is `None` for problems with the
+/// file as a whole.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub span: Option<Span>,
+}
+
+impl Lint {
+    pub fn line(&self) -> Option<usize> {
+        self.span.map(|span| span.line)
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.line() {
+            Some(line) => write!(f, "line {}: {}", line, self.kind),
+            None => self.kind.fmt(f),
+        }
+    }
+}
+
+/// Checks a parsed document against the rules of a [`Dialect`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Linter {
+    dialect: Dialect,
+}
+
+#[derive(Default)]
+struct Line {
+    start: Option<Span>,
+    ip: Option<IpAddr>,
+    hosts: usize,
+    extra_hosts: Option<Span>,
+}
+
+impl Linter {
+    pub fn new(dialect: Dialect) -> Self {
+        Self { dialect }
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn check<const LENGTH: usize>(&self, document: &Document<LENGTH>) -> Vec<Lint> {
+        let dialect = self.dialect;
+        let defaults = dialect.defaults();
+        let mut found = vec![false; defaults.len()];
+        let mut lints = Vec::new();
+        let mut line = Line::default();
+        let mut previous: Option<(&CstNode, &Span)> = None;
+
+        for (node, span) in document.iter() {
+            line.start.get_or_insert(*span);
+
+            match node {
+                CstNode::IP(ip) => line.ip = Some(*ip),
+                CstNode::Host(host) => {
+                    line.hosts += 1;
+
+                    if dialect
+                        .max_hosts_per_line()
+                        .is_some_and(|max| line.hosts > max)
+                    {
+                        let start = line.extra_hosts.map_or(span.start, |extra| extra.start);
+                        line.extra_hosts = Some(Span::new(start, span.end, span.line));
+                    }
+
+                    if dialect.comment_inside_word() && host.contains('#') {
+                        lints.push(Lint {
+                            kind: LintKind::CommentInsideWord { host: host.clone() },
+                            span: Some(*span),
+                        });
+                    }
+
+                    if let Some(ip) = line.ip {
+                        self.check_host(ip, host, span, &mut found, &mut lints);
+                    }
+                }
+                CstNode::Comment(comment) if dialect.comment_inside_word() => {
+                    // Parsed with a dialect that already split the word.
+                    if let Some((CstNode::Host(host), host_span)) = previous {
+                        if host_span.end == span.start {
+                            lints.push(Lint {
+                                kind: LintKind::CommentInsideWord {
+                                    host: alloc::format!("{}#{}", host, comment),
+                                },
+                                span: Some(Span::new(host_span.start, span.end, span.line)),
+                            });
+                        }
+                    }
+                }
+                CstNode::NewLine => {
+                    self.finish_line(&line, span.end, &mut lints);
+                    line = Line::default();
+                }
+                _ => {}
+            }
+
+            previous = Some((node, span));
+        }
+
+        if let Some((_, last)) = previous {
+            self.finish_line(&line, last.end, &mut lints);
+        }
+
+        for ((ip, host), found) in defaults.iter().zip(found) {
+            if !found {
+                lints.push(Lint {
+                    kind: LintKind::MissingDefault { ip, host },
+                    span: None,
+                });
+            }
+        }
+
+        lints
+    }
+
+    fn check_host(
+        &self,
+        ip: IpAddr,
+        host: &str,
+        span: &Span,
+        found: &mut [bool],
+        lints: &mut Vec<Lint>,
+    ) {
+        let is_localhost = hostname::eq(host, "localhost")
+            || host
+                .to_ascii_lowercase()
+                .trim_end_matches('.')
+                .ends_with(".localhost");
+
+        if self.dialect.unspecified_is_unreachable() && ip.is_unspecified() && is_localhost {
+            lints.push(Lint {
+                kind: LintKind::UnreachableLocalhost {
+                    ip,
+                    host: host.into(),
+                },
+                span: Some(*span),
+            });
+        }
+
+        for ((default_ip, default_host), found) in self.dialect.defaults().iter().zip(found) {
+            if default_ip.parse() == Ok(ip) && hostname::eq(host, default_host) {
+                *found = true;
+            }
+        }
+    }
+
+    fn finish_line(&self, line: &Line, end: usize, lints: &mut Vec<Lint>) {
+        let start = match line.start {
+            Some(start) => start,
+            None => return,
+        };
+
+        if let Some(extra) = line.extra_hosts {
+            lints.push(Lint {
+                kind: LintKind::TooManyHosts {
+                    count: line.hosts,
+                    max: self.dialect.max_hosts_per_line().unwrap_or(line.hosts),
+                },
+                span: Some(extra),
+            });
+        }
+
+        let length = end - start.start;
+
+        if let Some(max) = self.dialect.max_line_length().filter(|max| length > *max) {
+            lints.push(Lint {
+                kind: LintKind::LineTooLong { length, max },
+                span: Some(Span::new(start.start + max, end, start.line)),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::parser::Parser;
+
+    fn check(dialect: Dialect, data: &str) -> Vec<Lint> {
+        let document = Parser::default().parse_document::<1>(data).unwrap();
+
+        Linter::new(dialect).check(&document)
+    }
+
+    #[test]
+    fn test_windows_host_limit() {
+        let mut data = "0.0.0.0".to_string();
+        for i in 0..11 {
+            data.push_str(&alloc::format!(" ads{}.example.com", i));
+        }
+        data.push('\n');
+
+        let lints = check(Dialect::Windows, &data);
+
+        assert_eq!(1, lints.len());
+        assert_eq!(LintKind::TooManyHosts { count: 11, max: 9 }, lints[0].kind);
+        assert_eq!(
+            "ads9.example.com ads10.example.com",
+            &data[lints[0].span.unwrap().range()]
+        );
+        assert!(check(Dialect::Glibc, &data).is_empty());
+    }
+
+    #[test]
+    fn test_windows_unspecified_localhost() {
+        let lints = check(
+            Dialect::Windows,
+            "0.0.0.0 localhost app.localhost\n0.0.0.0 ads.example.com\n",
+        );
+
+        assert_eq!(
+            vec![Some(1), Some(1)],
+            lints.iter().map(Lint::line).collect::<Vec<_>>()
+        );
+        assert!(check(Dialect::Glibc, "0.0.0.0 localhost\n").is_empty());
+    }
+
+    #[test]
+    fn test_musl_line_length() {
+        let data = alloc::format!("127.0.0.1 {}\n127.0.0.1 short\n", "a".repeat(501));
+
+        let lints = check(Dialect::Musl, &data);
+
+        assert_eq!(1, lints.len());
+        assert_eq!(
+            LintKind::LineTooLong {
+                length: 512,
+                max: 511
+            },
+            lints[0].kind
+        );
+        assert_eq!(Some(Span::new(511, 512, 1)), lints[0].span);
+        assert!(check(Dialect::Glibc, &data).is_empty());
+    }
+
+    #[test]
+    fn test_comment_inside_word() {
+        let data = "127.0.0.1 localhost#loopback\n";
+
+        let lints = check(Dialect::Glibc, data);
+
+        assert_eq!(
+            "line 1: `#` starts a comment inside `localhost#loopback`, only `localhost` is declared",
+            lints[0].to_string()
+        );
+        assert!(check(Dialect::MacOs, data)
+            .iter()
+            .any(|lint| matches!(lint.kind, LintKind::CommentInsideWord { .. })));
+        assert!(check(Dialect::Windows, data)
+            .iter()
+            .all(|lint| !matches!(lint.kind, LintKind::CommentInsideWord { .. })));
+
+        let document = Parser::<()>::builder()
+            .dialect(Dialect::Musl)
+            .build()
+            .parse_document::<1>(data)
+            .unwrap();
+        let lints = Linter::new(Dialect::Musl).check(&document);
+
+        assert_eq!(lints.len(), 1);
+        assert_eq!(Some(Span::new(10, 28, 1)), lints[0].span);
+    }
+
+    #[test]
+    fn test_macos_defaults() {
+        let lints = check(Dialect::MacOs, "127.0.0.1\tlocalhost\n::1 LOCALHOST\n");
+
+        assert_eq!(
+            vec![Lint {
+                kind: LintKind::MissingDefault {
+                    ip: "255.255.255.255",
+                    host: "broadcasthost"
+                },
+                span: None,
+            }],
+            lints
+        );
+        assert!(check(
+            Dialect::MacOs,
+            "127.0.0.1 localhost\n255.255.255.255 broadcasthost\n::1 localhost"
+        )
+        .is_empty());
+    }
+}
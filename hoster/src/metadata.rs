@@ -0,0 +1,421 @@
+//! `key=value` metadata inside comments, as in
+//! `10.1.2.3 db.dev  # owner=payments expires=2026-12-01 ticket=OPS-42`.
+//!
+//! A comment is split on whitespace. Words of the form `key=value` are
+//! metadata, every other word is free text. Keys are made of ASCII letters,
+//! digits, `_`, `-` and `.`, and start with a letter, digit or `_`. Values
+//! holding whitespace, `"` or `\`, or empty ones, are written in double
+//! quotes, where `\"`, `\\`, `\n`, `\r` and `\t` are escapes. A word that
+//! looks like a pair but is not well formed, such as one with an unclosed
+//! quote, is free text.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Write};
+use core::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+/// Metadata of an entry, by key.
+pub type Metadata = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Eq, PartialEq, ThisError)]
+pub enum Error {
+    #[error("invalid metadata key `{0}`")]
+    InvalidKey(String),
+    #[error("invalid metadata `{0}`, expected key=value")]
+    InvalidPair(String),
+}
+
+/// A single `key=value` pair.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Pair {
+    pub key: String,
+    pub value: String,
+}
+
+impl Pair {
+    pub fn new(key: &str, value: &str) -> Result<Self, Error> {
+        if !is_key(key) {
+            return Err(Error::InvalidKey(key.to_string()));
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Parses `key=value`, with the value quoted or not.
+impl FromStr for Pair {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_pair(s) {
+            Some((pair, length)) if length == s.len() => Ok(pair),
+            _ => Err(Error::InvalidPair(s.to_string())),
+        }
+    }
+}
+
+impl Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key)?;
+        f.write_char('=')?;
+
+        if !needs_quotes(&self.value) {
+            return f.write_str(&self.value);
+        }
+
+        f.write_char('"')?;
+
+        for c in self.value.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c => f.write_char(c)?,
+            }
+        }
+
+        f.write_char('"')
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Part {
+    Text(String),
+    Pair {
+        pair: Pair,
+        // Text the pair was parsed from, `None` once it was changed.
+        raw: Option<String>,
+    },
+}
+
+/// The text of a comment, split into free text and metadata.
+///
+/// Writing it back with [`Display`] gives the text it was parsed from,
+/// byte for byte, until it is changed. Changed pairs are written in their
+/// canonical form; the rest of the comment is kept as it was.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Comment {
+    parts: Vec<Part>,
+}
+
+impl Comment {
+    /// Splits the text of a comment, without its `#`.
+    pub fn parse(text: &str) -> Self {
+        let mut comment = Self::default();
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let blank = rest.len() - rest.trim_start().len();
+
+            if blank > 0 {
+                comment.push_text(&rest[..blank]);
+                rest = &rest[blank..];
+                continue;
+            }
+
+            match parse_pair(rest) {
+                Some((pair, length))
+                    if rest[length..].starts_with(char::is_whitespace) || length == rest.len() =>
+                {
+                    comment.parts.push(Part::Pair {
+                        pair,
+                        raw: Some(rest[..length].to_string()),
+                    });
+                    rest = &rest[length..];
+                }
+                _ => {
+                    let word = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    comment.push_text(&rest[..word]);
+                    rest = &rest[word..];
+                }
+            }
+        }
+
+        comment
+    }
+
+    fn push_text(&mut self, text: &str) {
+        match self.parts.last_mut() {
+            Some(Part::Text(last)) => last.push_str(text),
+            _ => self.parts.push(Part::Text(text.to_string())),
+        }
+    }
+
+    /// The pairs, in the order they appear. A key may appear more than once.
+    pub fn pairs(&self) -> impl Iterator<Item = &Pair> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Pair { pair, .. } => Some(pair),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// The value of `key`. The last one wins when it appears more than once.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs()
+            .filter(|pair| pair.key == key)
+            .last()
+            .map(|pair| pair.value.as_str())
+    }
+
+    /// The pairs by key, the last one winning.
+    pub fn metadata(&self) -> Metadata {
+        self.pairs()
+            .map(|pair| (pair.key.clone(), pair.value.clone()))
+            .collect()
+    }
+
+    /// Sets the value of every occurrence of `key`, or appends the pair
+    /// after a space when there is none.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let new = Pair::new(key, value)?;
+        let mut found = false;
+
+        for part in &mut self.parts {
+            if let Part::Pair { pair, raw } = part {
+                if pair.key == key {
+                    *pair = new.clone();
+                    *raw = None;
+                    found = true;
+                }
+            }
+        }
+
+        if !found {
+            if !self.to_string().ends_with(char::is_whitespace) {
+                self.push_text(" ");
+            }
+
+            self.parts.push(Part::Pair {
+                pair: new,
+                raw: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Removes every occurrence of `key` and the whitespace before it.
+    /// Returns the value that was in effect.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let value = self.get(key).map(str::to_string);
+        let mut parts = Vec::with_capacity(self.parts.len());
+
+        for part in self.parts.drain(..) {
+            match part {
+                Part::Pair { ref pair, .. } if pair.key == key => {
+                    if let Some(Part::Text(text)) = parts.last_mut() {
+                        text.truncate(text.trim_end().len());
+
+                        if text.is_empty() {
+                            parts.pop();
+                        }
+                    }
+                }
+                Part::Text(text) => match parts.last_mut() {
+                    Some(Part::Text(last)) => last.push_str(&text),
+                    _ => parts.push(Part::Text(text)),
+                },
+                part => parts.push(part),
+            }
+        }
+
+        self.parts = parts;
+        value
+    }
+}
+
+impl Display for Comment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => f.write_str(text)?,
+                Part::Pair { raw: Some(raw), .. } => f.write_str(raw)?,
+                Part::Pair { pair, raw: None } => pair.fmt(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The metadata of a comment. Shorthand for `Comment::parse(text).metadata()`.
+pub fn parse(text: &str) -> Metadata {
+    Comment::parse(text).metadata()
+}
+
+fn is_key(key: &str) -> bool {
+    let mut chars = key.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value.starts_with('"')
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\\')
+}
+
+/// Parses a pair at the start of `input`, returning it with the number of
+/// bytes it takes.
+fn parse_pair(input: &str) -> Option<(Pair, usize)> {
+    let equals = input.find('=')?;
+    let key = &input[..equals];
+
+    if !is_key(key) {
+        return None;
+    }
+
+    let rest = &input[equals + 1..];
+    let (value, length) = match rest.strip_prefix('"') {
+        Some(quoted) => {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+
+            loop {
+                match chars.next()? {
+                    (end, '"') => break (value, end + 2),
+                    (_, '\\') => match chars.next()?.1 {
+                        '"' => value.push('"'),
+                        '\\' => value.push('\\'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        _ => return None,
+                    },
+                    (_, c) => value.push(c),
+                }
+            }
+        }
+        None => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+
+            if value.contains(['"', '\\']) {
+                return None;
+            }
+
+            (value.to_string(), end)
+        }
+    };
+
+    Some((
+        Pair {
+            key: key.to_string(),
+            value,
+        },
+        equals + 1 + length,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text =
+            r#"  db server owner=payments  expires=2026-12-01 note="on call \"ops\"" url=a=b"#;
+        let comment = Comment::parse(text);
+
+        assert_eq!(text, comment.to_string());
+        assert_eq!(Some("payments"), comment.get("owner"));
+        assert_eq!(Some("on call \"ops\""), comment.get("note"));
+        assert_eq!(Some("a=b"), comment.get("url"));
+        assert_eq!(
+            vec!["owner", "expires", "note", "url"],
+            comment
+                .pairs()
+                .map(|pair| pair.key.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_malformed_pairs_are_text() {
+        for text in [
+            " =x -a=1 a=\"open b=\"x\"y c=\\ d=\"\\q\"",
+            " http://example.com/?a=1",
+        ] {
+            let comment = Comment::parse(text);
+
+            assert_eq!(text, comment.to_string());
+            assert_eq!(None, comment.pairs().next(), "{}", text);
+        }
+
+        assert_eq!(Some(""), Comment::parse("a=\"\"").get("a"));
+        assert_eq!(Some(""), Comment::parse("a=").get("a"));
+    }
+
+    #[test]
+    fn test_last_value_wins() {
+        let metadata = parse(" owner=a owner=b");
+
+        assert_eq!(1, metadata.len());
+        assert_eq!("b", metadata["owner"]);
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut comment = Comment::parse(" db  owner=payments\tticket=OPS-1 owner=x");
+
+        comment.set("owner", "core team").unwrap();
+        assert_eq!(
+            " db  owner=\"core team\"\tticket=OPS-1 owner=\"core team\"",
+            comment.to_string()
+        );
+
+        assert_eq!(Some("OPS-1".to_string()), comment.remove("ticket"));
+        assert_eq!(Some("core team".to_string()), comment.remove("owner"));
+        assert_eq!(None, comment.remove("owner"));
+        assert_eq!(" db", comment.to_string());
+
+        comment.set("expires", "2026-12-01").unwrap();
+        assert_eq!(" db expires=2026-12-01", comment.to_string());
+
+        let mut comment = Comment::parse("");
+        comment.set("a", "").unwrap();
+        assert_eq!(" a=\"\"", comment.to_string());
+
+        assert_eq!(
+            Err(Error::InvalidKey("no key".to_string())),
+            comment.set("no key", "x")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for value in [
+            "",
+            "plain",
+            "two words",
+            "\"quoted\"",
+            "back\\slash",
+            "a\nb\tc\r",
+            "é=ü",
+        ] {
+            let pair = Pair::new("key", value).unwrap();
+            let text = pair.to_string();
+
+            assert_eq!(Ok(pair.clone()), text.parse::<Pair>(), "{}", text);
+            assert_eq!(Some(value), Comment::parse(&text).get("key"));
+        }
+
+        assert!("key".parse::<Pair>().is_err());
+        assert!("key=a b".parse::<Pair>().is_err());
+    }
+}
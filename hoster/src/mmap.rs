@@ -0,0 +1,131 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::cst::Cst;
+use crate::error::Error;
+use crate::parser::Parser;
+
+/// Parses the hosts file at `path` straight from a memory map of it, with
+/// the default parser.
+pub fn parse_path<P, const LENGTH: usize>(path: P) -> Result<Cst<LENGTH>, Error>
+where
+    P: AsRef<Path>,
+{
+    Parser::default().parse_path(path)
+}
+
+impl<V> Parser<V> {
+    /// Parses the hosts file at `path` straight from a memory map of it, with
+    /// the options of this parser.
+    ///
+    /// Pipes, character devices and files reporting a zero length (such as
+    /// the ones in `/proc`) cannot be mapped and are read into a buffer
+    /// instead.
+    pub fn parse_path<P, const LENGTH: usize>(&self, path: P) -> Result<Cst<LENGTH>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+
+        if metadata.is_file() && metadata.len() > 0 {
+            // SAFETY: the map is only read while parsing and dropped before
+            // returning. Truncating the file from another process in the
+            // meantime is undefined behaviour, as with any memory map.
+            if let Ok(map) = unsafe { Mmap::map(&file) } {
+                return self.parse_bytes(&map);
+            }
+        }
+
+        let mut buffer = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut buffer)?;
+
+        self.parse_bytes(&buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::cst::CstNode;
+    use crate::{parser, ErrorKind};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all("\u{feff}127.0.0.1 localhost\n# end\n".as_bytes())
+            .unwrap();
+
+        let cst = parse_path::<_, 1>(file.path()).unwrap();
+
+        assert_eq!(
+            vec![
+                CstNode::IP("127.0.0.1".parse().unwrap()),
+                CstNode::Space,
+                CstNode::Host("localhost".to_string()),
+                CstNode::NewLine,
+                CstNode::Comment(" end".to_string()),
+                CstNode::NewLine,
+            ],
+            cst.nodes.into_vec()
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        assert!(parse_path::<_, 1>(file.path()).unwrap().nodes.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_character_device() {
+        assert!(parse_path::<_, 1>("/dev/null").unwrap().nodes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_path_with_options() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"127.0.0.1 localhost\n::1 localhost\n")
+            .unwrap();
+
+        assert_eq!(8, parse_path::<_, 1>(file.path()).unwrap().nodes.len());
+
+        let error = Parser::<()>::builder()
+            .allow_ipv6(false)
+            .build()
+            .parse_path::<_, 1>(file.path())
+            .unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(parser::Error::Ipv6NotAllowed(_))
+        ));
+        assert_eq!(Some(2), error.line());
+    }
+
+    #[test]
+    fn test_parse_path_errors() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"localhost 127.0.0.1\n").unwrap();
+
+        let error = parse_path::<_, 1>(file.path()).unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(parser::Error::InvalidIp(_))
+        ));
+        assert_eq!(Some(1), error.line());
+        assert!(matches!(
+            parse_path::<_, 1>(file.path().with_extension("missing")).map_err(Error::into_kind),
+            Err(ErrorKind::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,331 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::str::FromStr;
+
+use thiserror::Error as ThisError;
+
+#[derive(Debug, Clone, Eq, PartialEq, ThisError)]
+pub enum Error {
+    #[error("invalid network address `{0}`")]
+    InvalidAddress(String),
+    #[error("invalid prefix length `{0}`, expected at most {1}")]
+    InvalidPrefix(String, u8),
+    #[error("unknown address class `{0}`, expected one of loopback, private, link-local, sinkhole or public")]
+    UnknownClass(String),
+}
+
+/// A subnet such as `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Creates the subnet of the first `prefix` bits of `address`. Any bits
+    /// of the address past the prefix are cleared.
+    pub fn new(address: IpAddr, prefix: u8) -> Result<Self, Error> {
+        let network = match address {
+            IpAddr::V4(ip) if prefix <= 32 => {
+                IpAddr::V4(Ipv4Addr::from_bits(ip.to_bits() & v4_mask(prefix)))
+            }
+            IpAddr::V6(ip) if prefix <= 128 => {
+                IpAddr::V6(Ipv6Addr::from_bits(ip.to_bits() & v6_mask(prefix)))
+            }
+            _ => {
+                return Err(Error::InvalidPrefix(
+                    prefix.to_string(),
+                    max_prefix(address),
+                ))
+            }
+        };
+
+        Ok(Self { network, prefix })
+    }
+
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Whether `ip` is in the subnet. In an IPv4 subnet, IPv4 addresses
+    /// mapped into IPv6, such as `::ffff:10.0.0.1`, count as the IPv4
+    /// address they map; an IPv6 subnet such as `::ffff:0:0/96` matches them
+    /// as they are.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match self.network {
+            IpAddr::V4(_) => ip.to_canonical(),
+            IpAddr::V6(_) => ip,
+        };
+
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                ip.to_bits() & v4_mask(self.prefix) == network.to_bits()
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                ip.to_bits() & v6_mask(self.prefix) == network.to_bits()
+            }
+            _ => false,
+        }
+    }
+}
+
+fn v4_mask(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0)
+}
+
+fn v6_mask(prefix: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0)
+}
+
+fn max_prefix(ip: IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = Error;
+
+    /// Parses `address/prefix`. A bare address is a subnet of that address
+    /// alone.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| Error::InvalidAddress(address.to_string()))?;
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .map_err(|_| Error::InvalidPrefix(prefix.to_string(), max_prefix(address)))?,
+            None => max_prefix(address),
+        };
+
+        Self::new(address, prefix)
+    }
+}
+
+/// What kind of host an address reaches.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AddressClass {
+    /// This machine: `127.0.0.0/8` and `::1`.
+    Loopback,
+    /// Private networks: `10.0.0.0/8`, `172.16.0.0/12`, `192.168.0.0/16`
+    /// and unique local `fc00::/7`.
+    Private,
+    /// `169.254.0.0/16` and `fe80::/10`.
+    LinkLocal,
+    /// `0.0.0.0` and `::`, used to block names.
+    Sinkhole,
+    /// Any other address.
+    Public,
+}
+
+impl AddressClass {
+    pub const ALL: [AddressClass; 5] = [
+        AddressClass::Loopback,
+        AddressClass::Private,
+        AddressClass::LinkLocal,
+        AddressClass::Sinkhole,
+        AddressClass::Public,
+    ];
+
+    /// Class of `ip`. IPv4 addresses mapped into IPv6 get the class of the
+    /// IPv4 address they map.
+    pub fn of(ip: IpAddr) -> Self {
+        match ip.to_canonical() {
+            ip if ip.is_unspecified() => AddressClass::Sinkhole,
+            ip if ip.is_loopback() => AddressClass::Loopback,
+            IpAddr::V4(ip) if ip.is_private() => AddressClass::Private,
+            IpAddr::V4(ip) if ip.is_link_local() => AddressClass::LinkLocal,
+            IpAddr::V6(ip) if ip.segments()[0] & 0xfe00 == 0xfc00 => AddressClass::Private,
+            IpAddr::V6(ip) if ip.is_unicast_link_local() => AddressClass::LinkLocal,
+            _ => AddressClass::Public,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            AddressClass::Loopback => "loopback",
+            AddressClass::Private => "private",
+            AddressClass::LinkLocal => "link-local",
+            AddressClass::Sinkhole => "sinkhole",
+            AddressClass::Public => "public",
+        }
+    }
+}
+
+impl Display for AddressClass {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AddressClass {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AddressClass::ALL
+            .into_iter()
+            .find(|class| class.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::UnknownClass(s.to_string()))
+    }
+}
+
+/// Selects entries by the address they point at.
+///
+/// An address matches when it is in any of the subnets and of any of the
+/// classes. An empty list of either does not restrict anything, so the
+/// default filter matches every address.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AddressFilter {
+    pub cidrs: Vec<Cidr>,
+    pub classes: Vec<AddressClass>,
+}
+
+impl AddressFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cidr(mut self, cidr: Cidr) -> Self {
+        self.cidrs.push(cidr);
+        self
+    }
+
+    pub fn class(mut self, class: AddressClass) -> Self {
+        self.classes.push(class);
+        self
+    }
+
+    pub fn matches(&self, ip: IpAddr) -> bool {
+        (self.cidrs.is_empty() || self.cidrs.iter().any(|cidr| cidr.contains(ip)))
+            && (self.classes.is_empty() || self.classes.contains(&AddressClass::of(ip)))
+    }
+
+    /// Keeps the `(ip, hostname)` pairs whose address matches, for use with
+    /// [`Cst::entries`](crate::cst::Cst::entries).
+    pub fn filter<'a, I>(&'a self, entries: I) -> impl Iterator<Item = (IpAddr, &'a str)> + 'a
+    where
+        I: IntoIterator<Item = (IpAddr, &'a str)>,
+        I::IntoIter: 'a,
+    {
+        entries.into_iter().filter(|(ip, _)| self.matches(*ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr() {
+        let office: Cidr = "10.20.0.0/16".parse().unwrap();
+
+        assert!(office.contains(ip("10.20.3.4")));
+        assert!(office.contains(ip("::ffff:10.20.0.1")));
+        assert!(!office.contains(ip("10.21.0.1")));
+        assert!(!office.contains(ip("::1")));
+
+        let ula: Cidr = "fd00::/8".parse().unwrap();
+
+        assert!(ula.contains(ip("fd12:3456::1")));
+        assert!(!ula.contains(ip("fe80::1")));
+
+        let mapped: Cidr = "::ffff:0:0/96".parse().unwrap();
+
+        assert!(mapped.contains(ip("::ffff:1.2.3.4")));
+        assert!(!mapped.contains(ip("1.2.3.4")));
+        assert!(!mapped.contains(ip("::1")));
+
+        assert_eq!(
+            "10.0.0.0/8",
+            "10.1.2.3/8".parse::<Cidr>().unwrap().to_string()
+        );
+        assert_eq!("::1/128", "::1".parse::<Cidr>().unwrap().to_string());
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(ip("8.8.8.8")));
+        assert_eq!(
+            Err(Error::InvalidPrefix("33".to_string(), 32)),
+            "10.0.0.0/33".parse::<Cidr>()
+        );
+        assert_eq!(
+            Err(Error::InvalidAddress("office".to_string())),
+            "office/8".parse::<Cidr>()
+        );
+    }
+
+    #[test]
+    fn test_address_class() {
+        for (address, class) in [
+            ("127.0.1.1", AddressClass::Loopback),
+            ("::1", AddressClass::Loopback),
+            ("192.168.1.10", AddressClass::Private),
+            ("172.31.0.1", AddressClass::Private),
+            ("fd00::5", AddressClass::Private),
+            ("169.254.10.1", AddressClass::LinkLocal),
+            ("fe80::1", AddressClass::LinkLocal),
+            ("0.0.0.0", AddressClass::Sinkhole),
+            ("::", AddressClass::Sinkhole),
+            ("::ffff:0.0.0.0", AddressClass::Sinkhole),
+            ("93.184.216.34", AddressClass::Public),
+            ("2606:4700::1111", AddressClass::Public),
+        ] {
+            assert_eq!(class, AddressClass::of(ip(address)), "{}", address);
+        }
+
+        for class in AddressClass::ALL {
+            assert_eq!(Ok(class), class.name().parse());
+        }
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let cst = Parser::default()
+            .parse_str::<1>(
+                "10.20.0.5 db.dev wiki.dev\n192.168.1.2 printer\n0.0.0.0 ads.example.com\n",
+            )
+            .unwrap();
+
+        let office = AddressFilter::new().cidr("10.20.0.0/16".parse().unwrap());
+        assert_eq!(
+            vec!["db.dev", "wiki.dev"],
+            office
+                .filter(cst.entries())
+                .map(|(_, host)| host)
+                .collect::<Vec<_>>()
+        );
+
+        let private = AddressFilter::new()
+            .class(AddressClass::Private)
+            .cidr("192.168.0.0/16".parse().unwrap());
+        assert_eq!(
+            vec!["printer"],
+            private
+                .filter(cst.entries())
+                .map(|(_, host)| host)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(4, AddressFilter::new().filter(cst.entries()).count());
+    }
+}
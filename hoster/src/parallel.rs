@@ -0,0 +1,231 @@
+use core::str::from_utf8;
+
+use rayon::prelude::*;
+use smallvec::SmallVec;
+
+use crate::cst::Cst;
+use crate::document::Document;
+use crate::error::Error;
+use crate::parser::{self, Parser};
+use crate::tokenizer::skip_bom_bytes;
+
+// Below this size a chunk is not worth handing to another thread.
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Splits `input` into chunks that each end right after a newline.
+///
+/// Returns the start offset of every chunk; the last chunk runs to the end.
+fn chunk_starts(input: &[u8], chunk_size: usize) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut position = chunk_size;
+
+    while position < input.len() {
+        match memchr::memchr(b'\n', &input[position..]) {
+            Some(newline) if position + newline + 1 < input.len() => {
+                starts.push(position + newline + 1);
+                position += newline + 1 + chunk_size;
+            }
+            _ => break,
+        }
+    }
+
+    starts
+}
+
+/// Parses `input` on the rayon thread pool with the default [`Parser`].
+///
+/// See [`Parser::parse_parallel`].
+pub fn parse_parallel<const LENGTH: usize>(input: &[u8]) -> Result<Document<LENGTH>, Error> {
+    Parser::default().parse_parallel(input)
+}
+
+impl<V: Sync> Parser<V> {
+    /// Parses `input` on the rayon thread pool, with the options of this
+    /// parser.
+    ///
+    /// The buffer is split into line-aligned chunks which are validated,
+    /// tokenized and parsed independently, then stitched back into a single
+    /// [`Document`]. Spans and line numbers refer to positions in `input`,
+    /// and when several chunks fail, the error closest to the start is
+    /// returned.
+    pub fn parse_parallel<const LENGTH: usize>(
+        &self,
+        input: &[u8],
+    ) -> Result<Document<LENGTH>, Error> {
+        let bom = input.len() - skip_bom_bytes(input).len();
+        let body = &input[bom..];
+
+        let chunk_size = (body.len() / (rayon::current_num_threads() * 4)).max(MIN_CHUNK_SIZE);
+        let starts = chunk_starts(body, chunk_size);
+
+        let chunks = starts
+            .par_iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = starts.get(i + 1).copied().unwrap_or(body.len());
+                let chunk = &body[start..end];
+                let newlines = memchr::memchr_iter(b'\n', chunk).count();
+
+                let text = from_utf8(chunk).map_err(|error| {
+                    let offset = error.valid_up_to();
+                    Error::located(parser::Error::Utf8Error(error), chunk, offset, offset)
+                })?;
+
+                let mut spans = Vec::new();
+
+                self.parse_body::<LENGTH>(text, 0, Some(&mut spans))
+                    .map(|cst| (Document { cst, spans }, newlines))
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = 0;
+        for chunk in &chunks {
+            match chunk {
+                Ok((document, _)) => nodes += document.spans.len(),
+                Err(_) => break,
+            }
+        }
+
+        let mut cst = Cst::<LENGTH> {
+            nodes: SmallVec::with_capacity(nodes),
+        };
+        let mut spans = Vec::with_capacity(nodes);
+        let mut lines = 0;
+
+        for (chunk, start) in chunks.into_iter().zip(starts) {
+            match chunk {
+                Ok((document, newlines)) => {
+                    cst.nodes.extend(document.cst.nodes);
+                    spans.extend(
+                        document
+                            .spans
+                            .into_iter()
+                            .map(|span| span.shift(bom + start, lines)),
+                    );
+                    lines += newlines;
+                }
+                Err(error) => return Err(error.shift(bom + start, lines)),
+            }
+        }
+
+        Ok(Document { cst, spans })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+    use crate::ErrorKind;
+
+    fn blocklist(lines: usize) -> String {
+        let mut data = String::from("\u{feff}# blocklist\r\n");
+
+        for i in 0..lines {
+            writeln!(
+                data,
+                "0.0.0.0 ads-{}.example.com\ttracker-{}.example.com",
+                i, i
+            )
+            .unwrap();
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_chunk_starts_are_line_aligned() {
+        let data = b"a b\nc d\ne f\ng h\n";
+
+        assert_eq!(vec![0, 4, 8, 12], chunk_starts(data, 1));
+        assert_eq!(vec![0, 8], chunk_starts(data, 5));
+        assert_eq!(vec![0], chunk_starts(data, 64));
+    }
+
+    #[test]
+    fn test_parse_parallel_matches_single_threaded() {
+        let data = blocklist(20_000);
+
+        let expected = Parser::default().parse_document::<1>(&data).unwrap();
+        let document = parse_parallel::<1>(data.as_bytes()).unwrap();
+
+        assert!(chunk_starts(data.as_bytes(), MIN_CHUNK_SIZE).len() > 1);
+        assert_eq!(expected.cst.nodes, document.cst.nodes);
+        assert_eq!(expected.spans, document.spans);
+        assert_eq!(20_001, document.lines());
+    }
+
+    #[test]
+    fn test_parse_parallel_reports_global_position() {
+        let mut data = blocklist(20_000);
+        let offset = data.len();
+        data.push_str("0.0.0.0 ok.example.com\nnot-an-ip example.com\n0.0.0.0 ads.example.com\n");
+
+        let error = parse_parallel::<1>(data.as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(parser::Error::InvalidIp(ip)) if ip == "not-an-ip"
+        ));
+        assert_eq!(Some(20_003), error.line());
+        assert_eq!(offset + 23..offset + 32, error.span().unwrap().range());
+        assert_eq!(Some("not-an-ip example.com"), error.line_text());
+    }
+
+    #[test]
+    fn test_parse_parallel_invalid_utf8() {
+        let mut data = blocklist(20_000).into_bytes();
+        let offset = data.len();
+        data.extend_from_slice(b"0.0.0.0 \xC3\x28\n");
+
+        let error = parse_parallel::<1>(&data).unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(parser::Error::Utf8Error(_))
+        ));
+        assert_eq!(Some(20_002), error.line());
+        assert_eq!(offset + 8, error.span().unwrap().start);
+    }
+
+    #[test]
+    fn test_parse_parallel_with_options() {
+        let mut data = blocklist(20_000);
+        data.push_str("not-an-ip example.com\n");
+
+        let parser = Parser::<()>::builder()
+            .invalid_lines(parser::InvalidLines::Keep)
+            .build();
+        let expected = parser.parse_document::<1>(&data).unwrap();
+        let document = parser.parse_parallel::<1>(data.as_bytes()).unwrap();
+
+        assert_eq!(expected.cst.nodes, document.cst.nodes);
+        assert_eq!(expected.spans, document.spans);
+        assert!(parse_parallel::<1>(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_parse_parallel_rejects_bom_inside_input() {
+        let mut data = blocklist(20_000).into_bytes();
+        let bom = '\u{feff}'.len_utf8();
+        let chunk_size =
+            ((data.len() - bom) / (rayon::current_num_threads() * 4)).max(MIN_CHUNK_SIZE);
+
+        // Turn the first line of the second chunk into one starting with
+        // U+FEFF, keeping the length so the chunks stay the same.
+        let start = bom + chunk_starts(&data[bom..], chunk_size)[1];
+        let end = start + memchr::memchr(b'\n', &data[start..]).unwrap();
+        let mut line = "\u{feff}0.0.0.0 ".as_bytes().to_vec();
+        line.resize(end - start, b'a');
+        data[start..end].copy_from_slice(&line);
+
+        let error = parse_parallel::<1>(&data).unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(parser::Error::InvalidIp(_))
+        ));
+        assert_eq!(start, error.span().unwrap().start);
+    }
+}
@@ -1,37 +1,117 @@
-use std::net::IpAddr;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::net::{IpAddr, Ipv6Addr};
+use core::str::from_utf8;
 
+use smallvec::SmallVec;
 use thiserror::Error as ThisError;
 
 use crate::cst::{Cst, CstNode};
-use crate::lookahead::LookaheadIter;
-use crate::tokens::Tokens;
+use crate::dialect::Dialect;
+use crate::document::Document;
+use crate::hostname;
+use crate::span::Span;
+use crate::tokenizer::{skip_bom_bytes, TokenIter};
+use crate::tokens::{TokenRef, Tokens};
 use crate::visitor::CstVisitor;
 
 #[derive(Debug, ThisError)]
 pub enum Error {
-    #[error("unexpected token {0}")]
+    #[error("unexpected {}", describe(.0))]
     UnexpectedToken(CstNode),
 
-    #[error("Invalid ip {0}")]
+    #[error("invalid IP address `{0}`")]
     InvalidIp(String),
 
-    #[error("Expecting token: {0}")]
+    #[error("expected {}", describe(.0))]
     ExpectingToken(CstNode),
+
+    #[error(transparent)]
+    Utf8Error(#[from] core::str::Utf8Error),
+
+    #[error("IP address `{0}` does not start in the first column")]
+    IpNotInFirstColumn(String),
+
+    #[error("more than {0} hostnames on one line")]
+    TooManyHosts(usize),
+
+    #[error("IPv6 address `{0}` is not allowed")]
+    Ipv6NotAllowed(Ipv6Addr),
+
+    #[error("invalid hostname `{0}`")]
+    InvalidHostname(String),
+
+    #[error("line is {length} bytes long, the limit is {max}")]
+    LineTooLong { length: usize, max: usize },
+}
+
+/// How strictly hostnames are checked.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum HostnameValidation {
+    /// Any word after the IP address is a hostname.
+    #[default]
+    Lenient,
+    /// Hostnames must pass [`hostname::is_valid`].
+    Strict,
+}
+
+/// What happens to a line that breaks one of the parser's rules.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum InvalidLines {
+    /// Parsing stops with an error.
+    #[default]
+    Reject,
+    /// The line is kept verbatim as a [`CstNode::Invalid`] and parsing
+    /// carries on with the next one.
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Options {
+    pub(crate) ip_in_first_column: bool,
+    pub(crate) max_hosts_per_line: Option<usize>,
+    pub(crate) allow_ipv6: bool,
+    pub(crate) hostnames: HostnameValidation,
+    pub(crate) invalid_lines: InvalidLines,
+    pub(crate) max_line_length: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ip_in_first_column: false,
+            max_hosts_per_line: None,
+            allow_ipv6: true,
+            hostnames: HostnameValidation::Lenient,
+            invalid_lines: InvalidLines::Reject,
+            max_line_length: None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Parser<V> {
     pub(crate) visitor: Option<V>,
+    pub(crate) dialect: Option<Dialect>,
+    pub(crate) options: Options,
 }
 
 #[derive(Debug)]
 pub struct ParserBuilder<V> {
     pub(crate) visitor: Option<V>,
+    pub(crate) dialect: Option<Dialect>,
+    pub(crate) options: Options,
 }
 
 impl<V> Default for ParserBuilder<V> {
     fn default() -> Self {
-        Self { visitor: None }
+        Self {
+            visitor: None,
+            dialect: None,
+            options: Options::default(),
+        }
     }
 }
 
@@ -41,19 +121,64 @@ impl<V> ParserBuilder<V> {
         self
     }
 
+    /// Reads the input the way `dialect` does. Without a dialect, a `#`
+    /// only starts a comment at the beginning of a word.
+    ///
+    /// The dialect only changes where comments start. Its other limits, such
+    /// as the Windows alias limit and the musl line length, are reported by
+    /// [`Linter`](crate::lint::Linter); to reject them while parsing, set
+    /// [`max_hosts_per_line`](Self::max_hosts_per_line) and
+    /// [`max_line_length`](Self::max_line_length) as well.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = Some(dialect);
+        self
+    }
+
+    /// Rejects entries whose IP address is preceded by whitespace. Off by
+    /// default.
+    pub fn ip_in_first_column(mut self, required: bool) -> Self {
+        self.options.ip_in_first_column = required;
+        self
+    }
+
+    /// Rejects lines with more than `max` hostnames. Unlimited by default.
+    pub fn max_hosts_per_line(mut self, max: usize) -> Self {
+        self.options.max_hosts_per_line = Some(max);
+        self
+    }
+
+    /// Allows entries with an IPv6 address. On by default.
+    pub fn allow_ipv6(mut self, allowed: bool) -> Self {
+        self.options.allow_ipv6 = allowed;
+        self
+    }
+
+    pub fn hostnames(mut self, validation: HostnameValidation) -> Self {
+        self.options.hostnames = validation;
+        self
+    }
+
+    pub fn invalid_lines(mut self, invalid_lines: InvalidLines) -> Self {
+        self.options.invalid_lines = invalid_lines;
+        self
+    }
+
+    /// Rejects lines longer than `max` bytes, counting the line feed.
+    /// Unlimited by default.
+    pub fn max_line_length(mut self, max: usize) -> Self {
+        self.options.max_line_length = Some(max);
+        self
+    }
+
     pub fn build(self) -> Parser<V> {
         Parser {
             visitor: self.visitor,
+            dialect: self.dialect,
+            options: self.options,
         }
     }
 }
 
-fn parse_ip(ip: String) -> Result<IpAddr, Error> {
-    let addr = ip.parse::<IpAddr>().map_err(|_| Error::InvalidIp(ip))?;
-
-    Ok(addr)
-}
-
 impl<V: CstVisitor> Parser<V> {
     pub fn visit<const LENGTH: usize>(&mut self, cst: &Cst<LENGTH>) {
         let visitor = match self.visitor.as_mut() {
@@ -75,7 +200,269 @@ impl<V: CstVisitor> Parser<V> {
 
 impl Default for Parser<()> {
     fn default() -> Self {
-        Self { visitor: None }
+        Parser::builder().build()
+    }
+}
+
+// Most lines of a hosts file are `ip<space>host<newline>`.
+const NODES_PER_LINE: usize = 4;
+
+/// What a token did to the tree.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Pushed {
+    /// One node was added.
+    Node,
+    /// The token belongs to an invalid line that is being kept.
+    Skipped,
+    /// The current line was replaced by a [`CstNode::Invalid`], followed by
+    /// a line feed node when `newline` is set.
+    Invalid { newline: bool },
+}
+
+struct CstBuilder<const LENGTH: usize> {
+    cst: Cst<LENGTH>,
+    options: Options,
+    is_ip_parsed: bool,
+    after_carriage_return: bool,
+    after_comment: bool,
+    leading_whitespace: bool,
+    line_start: usize,
+    line_length: usize,
+    hosts: usize,
+    invalid: Option<String>,
+}
+
+impl<const LENGTH: usize> CstBuilder<LENGTH> {
+    fn with_capacity(capacity: usize, options: Options) -> Self {
+        Self {
+            cst: Cst {
+                nodes: SmallVec::with_capacity(capacity),
+            },
+            options,
+            is_ip_parsed: false,
+            after_carriage_return: false,
+            after_comment: false,
+            leading_whitespace: false,
+            line_start: 0,
+            line_length: 0,
+            hosts: 0,
+            invalid: None,
+        }
+    }
+
+    #[inline]
+    fn node(&mut self, node: CstNode) -> Result<(), Error> {
+        if self.after_carriage_return && !matches!(node, CstNode::NewLine) {
+            return Err(Error::UnexpectedToken(node));
+        }
+
+        if self.after_comment && !matches!(node, CstNode::NewLine | CstNode::CarriageReturn) {
+            return Err(Error::UnexpectedToken(
+                self.cst.nodes.last().cloned().unwrap_or(node),
+            ));
+        }
+
+        if self.cst.nodes.len() == self.line_start {
+            self.leading_whitespace = matches!(node, CstNode::Space | CstNode::Tab);
+        }
+
+        self.after_carriage_return = matches!(node, CstNode::CarriageReturn);
+        self.after_comment = matches!(node, CstNode::Comment(_));
+
+        let is_new_line = matches!(node, CstNode::NewLine);
+
+        self.cst.add_node(node);
+
+        if is_new_line {
+            self.start_line();
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn start_line(&mut self) {
+        self.is_ip_parsed = false;
+        self.after_carriage_return = false;
+        self.after_comment = false;
+        self.leading_whitespace = false;
+        self.line_start = self.cst.nodes.len();
+        self.line_length = 0;
+        self.hosts = 0;
+    }
+
+    #[inline]
+    fn word<S>(&mut self, word: S) -> Result<(), Error>
+    where
+        S: AsRef<str> + Into<String>,
+    {
+        let node = if self.is_ip_parsed {
+            self.hosts += 1;
+
+            if let Some(max) = self.options.max_hosts_per_line {
+                if self.hosts > max {
+                    return Err(Error::TooManyHosts(max));
+                }
+            }
+
+            if self.options.hostnames == HostnameValidation::Strict
+                && !hostname::is_valid(word.as_ref())
+            {
+                return Err(Error::InvalidHostname(word.into()));
+            }
+
+            CstNode::Host(word.into())
+        } else {
+            let ip = word
+                .as_ref()
+                .parse::<IpAddr>()
+                .map_err(|_| Error::InvalidIp(word.as_ref().into()))?;
+
+            if self.options.ip_in_first_column && self.leading_whitespace {
+                return Err(Error::IpNotInFirstColumn(word.into()));
+            }
+
+            if let (IpAddr::V6(ip), false) = (ip, self.options.allow_ipv6) {
+                return Err(Error::Ipv6NotAllowed(ip));
+            }
+
+            self.is_ip_parsed = true;
+            CstNode::IP(ip)
+        };
+
+        self.node(node)
+    }
+
+    #[inline]
+    fn check_line_length(&self, length: usize) -> Result<(), Error> {
+        match self.options.max_line_length {
+            Some(max) if length > max => Err(Error::LineTooLong { length, max }),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, token: Tokens) -> Result<Pushed, Error> {
+        if self.options.invalid_lines == InvalidLines::Keep {
+            return self.push_ref(token.as_token_ref());
+        }
+
+        self.line_length += token.as_token_ref().source_len();
+
+        match token {
+            Tokens::HostOrIp(word) => self.word(word),
+            Tokens::Comment(comment) => self.node(CstNode::Comment(comment)),
+            Tokens::Space => self.node(CstNode::Space),
+            Tokens::Tab => self.node(CstNode::Tab),
+            Tokens::CarriageReturn => self.node(CstNode::CarriageReturn),
+            Tokens::NewLine => self
+                .check_line_length(self.line_length)
+                .and_then(|_| self.node(CstNode::NewLine)),
+        }
+        .map(|_| Pushed::Node)
+    }
+
+    #[inline]
+    fn push_ref(&mut self, token: TokenRef<'_>) -> Result<Pushed, Error> {
+        if let Some(line) = self.invalid.as_mut() {
+            if let TokenRef::NewLine = token {
+                self.keep_invalid_line(None);
+                return Ok(Pushed::Invalid { newline: true });
+            }
+
+            let _ = write!(line, "{}", token);
+            return Ok(Pushed::Skipped);
+        }
+
+        self.line_length += token.source_len();
+
+        let result = match token {
+            TokenRef::HostOrIp(word) => self.word(word),
+            TokenRef::Comment(comment) => self.node(CstNode::Comment(comment.into())),
+            TokenRef::Space => self.node(CstNode::Space),
+            TokenRef::Tab => self.node(CstNode::Tab),
+            TokenRef::CarriageReturn => self.node(CstNode::CarriageReturn),
+            TokenRef::NewLine => self
+                .check_line_length(self.line_length)
+                .and_then(|_| self.node(CstNode::NewLine)),
+        };
+
+        match result {
+            Ok(()) => Ok(Pushed::Node),
+            Err(_) if self.options.invalid_lines == InvalidLines::Keep => {
+                if let TokenRef::NewLine = token {
+                    self.keep_invalid_line(None);
+                    return Ok(Pushed::Invalid { newline: true });
+                }
+
+                self.keep_invalid_line(Some(token));
+                Ok(Pushed::Skipped)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Moves the nodes of the current line into a [`CstNode::Invalid`].
+    ///
+    /// With a token, the line stays open and the following tokens are
+    /// appended to it until the next line feed.
+    fn keep_invalid_line(&mut self, token: Option<TokenRef<'_>>) {
+        let continued = self.invalid.is_some();
+        let mut line = self.invalid.take().unwrap_or_default();
+
+        if !continued {
+            for node in self.cst.nodes.drain(self.line_start..) {
+                let _ = write!(line, "{}", node);
+            }
+        }
+
+        match token {
+            Some(token) => {
+                let _ = write!(line, "{}", token);
+                self.invalid = Some(line);
+            }
+            None => {
+                self.cst.add_node(CstNode::Invalid(line));
+                self.cst.add_node(CstNode::NewLine);
+                self.start_line();
+            }
+        }
+    }
+
+    /// Checks the last line once the input is exhausted.
+    fn end(&mut self) -> Result<Pushed, Error> {
+        if let Some(line) = self.invalid.take() {
+            self.cst.add_node(CstNode::Invalid(line));
+            return Ok(Pushed::Invalid { newline: false });
+        }
+
+        let result = if self.after_carriage_return {
+            Err(Error::ExpectingToken(CstNode::NewLine))
+        } else {
+            self.check_line_length(self.line_length)
+        };
+
+        match result {
+            Ok(()) => Ok(Pushed::Skipped),
+            Err(_)
+                if self.options.invalid_lines == InvalidLines::Keep
+                    && self.cst.nodes.len() > self.line_start =>
+            {
+                let mut line = String::new();
+
+                for node in self.cst.nodes.drain(self.line_start..) {
+                    let _ = write!(line, "{}", node);
+                }
+
+                self.cst.add_node(CstNode::Invalid(line));
+                Ok(Pushed::Invalid { newline: false })
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn finish(self) -> Cst<LENGTH> {
+        self.cst
     }
 }
 
@@ -84,98 +471,168 @@ impl<V> Parser<V> {
         ParserBuilder::<V>::default()
     }
 
+    pub fn dialect(&self) -> Option<Dialect> {
+        self.dialect
+    }
+
     pub fn parse<const LENGTH: usize>(&self, tokens: Vec<Tokens>) -> Result<Cst<LENGTH>, Error> {
-        let mut cst = Cst::<LENGTH> {
-            nodes: smallvec::smallvec![],
-        };
-        let lookahead = LookaheadIter {
-            iter: tokens.into_iter(),
+        let mut builder = CstBuilder::with_capacity(tokens.len(), self.options);
+
+        for token in tokens {
+            builder.push(token)?;
+        }
+
+        builder.end()?;
+
+        Ok(builder.finish())
+    }
+
+    /// Tokenizes and parses `input` in a single pass, without building an
+    /// intermediate token vector.
+    pub fn parse_str<const LENGTH: usize>(&self, input: &str) -> Result<Cst<LENGTH>, crate::Error> {
+        self.parse_with(input, None)
+    }
+
+    /// Parses `input` like [`Parser::parse_str`], also recording the span of
+    /// every node. Spans are byte offsets into `input`.
+    pub fn parse_document<const LENGTH: usize>(
+        &self,
+        input: &str,
+    ) -> Result<Document<LENGTH>, crate::Error> {
+        let mut spans = Vec::with_capacity(estimate_nodes(input));
+        let cst = self.parse_with(input, Some(&mut spans))?;
+
+        Ok(Document { cst, spans })
+    }
+
+    pub fn parse_bytes<const LENGTH: usize>(
+        &self,
+        input: &[u8],
+    ) -> Result<Cst<LENGTH>, crate::Error> {
+        let body = skip_bom_bytes(input);
+        let bom = input.len() - body.len();
+
+        match from_utf8(body) {
+            Ok(text) => self
+                .parse_with(text, None)
+                .map_err(|error| error.shift(bom, 0)),
+            Err(error) => {
+                let offset = error.valid_up_to();
+
+                Err(
+                    crate::Error::located(Error::Utf8Error(error), body, offset, offset)
+                        .shift(bom, 0),
+                )
+            }
+        }
+    }
+
+    fn parse_with<const LENGTH: usize>(
+        &self,
+        input: &str,
+        spans: Option<&mut Vec<Span>>,
+    ) -> Result<Cst<LENGTH>, crate::Error> {
+        let body = input.strip_prefix('\u{feff}').unwrap_or(input);
+
+        self.parse_body(body, input.len() - body.len(), spans)
+    }
+
+    /// Parses a slice of a larger input that starts `bom` bytes after its
+    /// byte order mark. A U+FEFF at the start of `body` is not skipped, as it
+    /// is not at the start of the input.
+    pub(crate) fn parse_body<const LENGTH: usize>(
+        &self,
+        body: &str,
+        bom: usize,
+        mut spans: Option<&mut Vec<Span>>,
+    ) -> Result<Cst<LENGTH>, crate::Error> {
+        let mut builder = CstBuilder::with_capacity(estimate_nodes(body), self.options);
+        let mut tokens = TokenIter::new(body)
+            .comment_inside_word(self.dialect.is_some_and(Dialect::comment_inside_word));
+        let mut line = 1;
+        let mut line_start = 0;
+
+        let locate = |error: Error, start: usize, end: usize, line_start: usize| {
+            let start = match error {
+                Error::LineTooLong { max, .. } => line_start + max,
+                _ => start,
+            };
+
+            crate::Error::located(error, body.as_bytes(), start, end).shift(bom, 0)
         };
 
-        let mut is_ip_parsed = false;
-
-        for (token, next) in lookahead {
-            match token {
-                Tokens::HostOrIp(ip) => {
-                    if !is_ip_parsed {
-                        let ip = parse_ip(ip)?;
-                        cst.add_node(CstNode::IP(ip));
-                        is_ip_parsed = true;
-                    } else {
-                        cst.add_node(CstNode::Host(ip));
-                    }
+        loop {
+            let start = tokens.position();
+            let token = match tokens.next() {
+                Some(token) => token,
+                None => break,
+            };
+            let end = tokens.position();
 
-                    if let Some(next) = next {
-                        if next == Tokens::NewLine {
-                            is_ip_parsed = false;
-                        }
+            let line_node = builder.line_start;
+            let pushed = builder
+                .push_ref(token)
+                .map_err(|error| locate(error, start, end, line_start))?;
 
-                        cst.add_node(CstNode::from(next));
-                    }
-                }
-                Tokens::Comment(comment) => {
-                    if let Some(Tokens::NewLine | Tokens::CarriageReturn) | None = next {
-                        cst.add_node(CstNode::Comment(comment));
-                    } else {
-                        return Err(Error::UnexpectedToken(CstNode::Comment(comment)));
-                    }
+            if let Some(spans) = spans.as_deref_mut() {
+                let span = Span::new(bom + start, bom + end, line);
 
-                    if let Some(next) = next {
-                        cst.add_node(CstNode::from(next));
-                    }
-                }
-                Tokens::Space | Tokens::Tab => {
-                    cst.add_node(CstNode::from(token));
-
-                    if let Some(Tokens::HostOrIp(host)) = next {
-                        if !is_ip_parsed {
-                            let ip = host.parse::<IpAddr>().map_err(|_| Error::InvalidIp(host))?;
-                            cst.add_node(CstNode::IP(ip));
-                            is_ip_parsed = true;
-                        } else {
-                            cst.add_node(CstNode::Host(host));
-                        }
-                    }
-                }
-                Tokens::CarriageReturn => {
-                    cst.add_node(CstNode::CarriageReturn);
-
-                    match next {
-                        Some(Tokens::NewLine) => {
-                            cst.add_node(CstNode::NewLine);
-                            is_ip_parsed = false;
-                        }
-                        Some(t) => return Err(Error::UnexpectedToken(CstNode::from(t))),
-                        None => return Err(Error::UnexpectedToken(CstNode::NewLine)),
-                    };
-                }
-                Tokens::NewLine => {
-                    cst.add_node(CstNode::from(token));
-
-                    match next {
-                        Some(Tokens::HostOrIp(ip)) => {
-                            let ip = ip.parse::<IpAddr>().map_err(|_| Error::InvalidIp(ip))?;
-                            cst.add_node(CstNode::IP(ip));
-                            is_ip_parsed = true;
-                        }
-                        Some(next) => cst.add_node(CstNode::from(next)),
-                        None => {}
+                match pushed {
+                    Pushed::Node => spans.push(span),
+                    Pushed::Skipped => {}
+                    Pushed::Invalid { .. } => {
+                        spans.truncate(line_node);
+                        spans.push(Span::new(bom + line_start, bom + start, line));
+                        spans.push(span);
                     }
                 }
             }
+
+            if let TokenRef::NewLine = token {
+                line += 1;
+                line_start = end;
+            }
+        }
+
+        let line_node = builder.line_start;
+        let pushed = builder
+            .end()
+            .map_err(|error| locate(error, body.len(), body.len(), line_start))?;
+
+        if let (Some(spans), Pushed::Invalid { .. }) = (spans, pushed) {
+            spans.truncate(line_node);
+            spans.push(Span::new(bom + line_start, bom + body.len(), line));
         }
 
-        Ok(cst)
+        Ok(builder.finish())
+    }
+}
+
+fn estimate_nodes(input: &str) -> usize {
+    (memchr::memchr_iter(b'\n', input.as_bytes()).count() + 1) * NODES_PER_LINE
+}
+
+fn describe(node: &CstNode) -> String {
+    match node {
+        CstNode::Host(host) => format!("host `{}`", host),
+        CstNode::IP(ip) => format!("address `{}`", ip),
+        CstNode::Comment(_) => "comment".to_string(),
+        CstNode::Space => "space".to_string(),
+        CstNode::Tab => "tab".to_string(),
+        CstNode::CarriageReturn => "carriage return".to_string(),
+        CstNode::NewLine => "line feed".to_string(),
+        CstNode::Invalid(_) => "invalid line".to_string(),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::net::Ipv4Addr;
+    use core::net::Ipv4Addr;
 
     use smallvec::smallvec_inline;
 
     use super::*;
+    use crate::ErrorKind;
 
     #[test]
     fn test_parse_tokens() {
@@ -435,4 +892,271 @@ mod tests {
             cst.nodes
         );
     }
+
+    #[test]
+    fn test_parse_keeps_runs_of_whitespace() {
+        let tokens = vec![
+            Tokens::HostOrIp("127.0.0.1".to_string()),
+            Tokens::Space,
+            Tokens::Space,
+            Tokens::Space,
+            Tokens::HostOrIp("localhost".to_string()),
+            Tokens::Tab,
+            Tokens::Tab,
+            Tokens::Comment(" loopback".to_string()),
+            Tokens::NewLine,
+        ];
+
+        let parser = Parser::default();
+
+        let cst = parser.parse::<1>(tokens).unwrap();
+
+        assert_eq!("127.0.0.1   localhost\t\t# loopback\n", cst.to_string());
+    }
+
+    #[test]
+    fn test_parse_str_matches_token_parser() {
+        let data = "\
+# localhost name resolution is handled within DNS itself.\r
+192.168.0.17\thost.docker.internal  gateway.docker.internal # docker\r
+\t127.0.0.1\tkubernetes.docker.internal\r
+\r
+::1 ip6-localhost\r
+";
+        let parser = Parser::default();
+        let tokens = crate::tokenizer::Tokenizer::new_with_bytes(data)
+            .parse_bytes()
+            .unwrap()
+            .get_tokens();
+
+        let expected = parser.parse::<1>(tokens).unwrap();
+        let cst = parser.parse_str::<1>(data).unwrap();
+
+        assert_eq!(expected.nodes, cst.nodes);
+        assert_eq!(data, cst.to_string());
+    }
+
+    #[test]
+    fn test_parse_bytes_skips_bom() {
+        let parser = Parser::default();
+
+        let cst = parser
+            .parse_bytes::<1>(b"\xEF\xBB\xBF127.0.0.1 localhost\n")
+            .unwrap();
+
+        assert_eq!("127.0.0.1 localhost\n", cst.to_string());
+        let error = parser
+            .parse_bytes::<1>(b"\xEF\xBB\xBF127.0.0.1 \xC3\x28\n")
+            .unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(Error::Utf8Error(_))
+        ));
+        assert_eq!(Some(Span::new(13, 13, 1)), error.span());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parser = Parser::default();
+
+        assert!(matches!(
+            parser.parse_str::<1>("localhost 127.0.0.1\n").map_err(crate::Error::into_kind),
+            Err(ErrorKind::Parser(Error::InvalidIp(ip))) if ip == "localhost"
+        ));
+        assert!(matches!(
+            parser
+                .parse_str::<1>("127.0.0.1 localhost\r")
+                .map_err(crate::Error::into_kind),
+            Err(ErrorKind::Parser(Error::ExpectingToken(CstNode::NewLine)))
+        ));
+        assert!(matches!(
+            parser
+                .parse_str::<1>("127.0.0.1 localhost\r \n")
+                .map_err(crate::Error::into_kind),
+            Err(ErrorKind::Parser(Error::UnexpectedToken(CstNode::Space)))
+        ));
+        assert!(matches!(
+            parser.parse::<1>(vec![Tokens::Comment(" comment".to_string()), Tokens::Space,]),
+            Err(Error::UnexpectedToken(CstNode::Comment(_)))
+        ));
+    }
+
+    #[test]
+    fn test_parse_document_spans() {
+        let data = "\u{feff}# hosts\r\n::1\tlocalhost\n";
+
+        let parser = Parser::default();
+        let document = parser.parse_document::<1>(data).unwrap();
+
+        assert_eq!(
+            vec![
+                Span::new(3, 10, 1),
+                Span::new(10, 11, 1),
+                Span::new(11, 12, 1),
+                Span::new(12, 15, 2),
+                Span::new(15, 16, 2),
+                Span::new(16, 25, 2),
+                Span::new(25, 26, 2),
+            ],
+            document.spans
+        );
+        assert_eq!(2, document.lines());
+        assert_eq!(&data[3..], document.to_string());
+
+        let error = parser
+            .parse_document::<1>("127.0.0.1 localhost\n\tlocalhost 127.0.0.1\n")
+            .unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(Error::InvalidIp(_))
+        ));
+        assert_eq!(Some(Span::new(21, 30, 2)), error.span());
+        assert_eq!(Some(2), error.column());
+        assert_eq!(Some("\tlocalhost 127.0.0.1"), error.line_text());
+    }
+
+    #[test]
+    fn test_parse_with_dialect() {
+        let data = "127.0.0.1 localhost#loopback\n";
+
+        for dialect in [Dialect::Glibc, Dialect::Musl, Dialect::MacOs] {
+            let cst = Parser::<()>::builder()
+                .dialect(dialect)
+                .build()
+                .parse_str::<1>(data)
+                .unwrap();
+
+            assert_eq!(
+                vec![
+                    CstNode::IP(Ipv4Addr::LOCALHOST.into()),
+                    CstNode::Space,
+                    CstNode::Host("localhost".to_string()),
+                    CstNode::Comment("loopback".to_string()),
+                    CstNode::NewLine,
+                ],
+                cst.nodes.into_vec(),
+                "{}",
+                dialect
+            );
+        }
+
+        let cst = Parser::<()>::builder()
+            .dialect(Dialect::Windows)
+            .build()
+            .parse_str::<1>(data)
+            .unwrap();
+
+        assert_eq!(
+            Some(&CstNode::Host("localhost#loopback".to_string())),
+            cst.nodes.get(2)
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        fn kind(parser: &Parser<()>, data: &str) -> Option<ErrorKind> {
+            parser
+                .parse_str::<1>(data)
+                .err()
+                .map(crate::Error::into_kind)
+        }
+
+        let parser = Parser::<()>::builder().ip_in_first_column(true).build();
+        assert!(matches!(
+            kind(&parser, "\t127.0.0.1 localhost\n"),
+            Some(ErrorKind::Parser(Error::IpNotInFirstColumn(ip))) if ip == "127.0.0.1"
+        ));
+        assert!(kind(&parser, "127.0.0.1 localhost\n  # indented comment\n").is_none());
+
+        let parser = Parser::<()>::builder().max_hosts_per_line(2).build();
+        assert!(kind(&parser, "127.0.0.1 a b\n").is_none());
+        assert!(matches!(
+            kind(&parser, "127.0.0.1 a b c\n"),
+            Some(ErrorKind::Parser(Error::TooManyHosts(2)))
+        ));
+
+        let parser = Parser::<()>::builder().allow_ipv6(false).build();
+        assert!(matches!(
+            kind(&parser, "::1 localhost\n"),
+            Some(ErrorKind::Parser(Error::Ipv6NotAllowed(ip))) if ip.is_loopback()
+        ));
+
+        let parser = Parser::<()>::builder()
+            .hostnames(HostnameValidation::Strict)
+            .build();
+        assert!(kind(&parser, "127.0.0.1 bücher.de\n").is_none());
+        assert!(matches!(
+            kind(&parser, "127.0.0.1 exa_mple!.com\n"),
+            Some(ErrorKind::Parser(Error::InvalidHostname(host))) if host == "exa_mple!.com"
+        ));
+        assert!(kind(&Parser::default(), "127.0.0.1 exa_mple!.com\n").is_none());
+
+        let parser = Parser::<()>::builder().max_line_length(20).build();
+        assert!(kind(&parser, "127.0.0.1 localhost\n").is_none());
+
+        let error = parser
+            .parse_str::<1>("127.0.0.1 localhost\n127.0.0.1 localhost2\n")
+            .unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Parser(Error::LineTooLong {
+                length: 21,
+                max: 20
+            })
+        ));
+        assert_eq!(Some(Span::new(40, 41, 2)), error.span());
+    }
+
+    #[test]
+    fn test_parse_keeps_invalid_lines() {
+        let data = "\
+127.0.0.1 localhost\r
+not-an-ip host # comment\r
+::1 a b c\r
+10.0.0.1 db\r";
+        let parser = Parser::<()>::builder()
+            .invalid_lines(InvalidLines::Keep)
+            .max_hosts_per_line(2)
+            .build();
+
+        let document = parser.parse_document::<1>(data).unwrap();
+
+        assert_eq!(
+            vec![
+                CstNode::IP(Ipv4Addr::LOCALHOST.into()),
+                CstNode::Space,
+                CstNode::Host("localhost".to_string()),
+                CstNode::CarriageReturn,
+                CstNode::NewLine,
+                CstNode::Invalid("not-an-ip host # comment\r".to_string()),
+                CstNode::NewLine,
+                CstNode::Invalid("::1 a b c\r".to_string()),
+                CstNode::NewLine,
+                CstNode::Invalid("10.0.0.1 db\r".to_string()),
+            ],
+            document.cst.nodes.to_vec()
+        );
+        assert_eq!(data, document.to_string());
+        assert_eq!(document.cst.nodes.len(), document.spans.len());
+        assert_eq!(
+            vec![
+                Span::new(21, 46, 2),
+                Span::new(46, 47, 2),
+                Span::new(47, 57, 3),
+                Span::new(57, 58, 3),
+                Span::new(58, 70, 4),
+            ],
+            document.spans[5..].to_vec()
+        );
+
+        let tokens = crate::tokenizer::Tokenizer::new_with_bytes(data)
+            .parse_bytes()
+            .unwrap()
+            .get_tokens();
+
+        assert_eq!(document.cst.nodes, parser.parse::<1>(tokens).unwrap().nodes);
+    }
 }
@@ -0,0 +1,472 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::net::IpAddr;
+
+use hashbrown::HashMap;
+
+use crate::cst::Cst;
+use crate::error::Error;
+use crate::parser::Parser;
+use crate::trie;
+
+/// Addresses of every hostname of a hosts file, for answering lookups.
+///
+/// Unlike a resolver reading the file, every address a name is declared
+/// with is kept, in file order, so `localhost` can resolve to both
+/// `127.0.0.1` and `::1`.
+#[derive(Debug, Clone, Default)]
+pub struct HostsTable {
+    names: HashMap<String, Vec<IpAddr>>,
+}
+
+impl HostsTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_cst<const LENGTH: usize>(cst: &Cst<LENGTH>) -> Self {
+        let mut table = Self::new();
+
+        for (ip, host) in cst.entries() {
+            table.insert(ip, host);
+        }
+
+        table
+    }
+
+    /// Parses an in-memory hosts file.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        Ok(Self::from_cst(&Parser::default().parse_str::<1>(input)?))
+    }
+
+    /// Reads and parses the hosts file at `path`.
+    #[cfg(feature = "std")]
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let data = std::fs::read(path)?;
+
+        Ok(Self::from_cst(&Parser::default().parse_bytes::<1>(&data)?))
+    }
+
+    /// Adds `ip` to the addresses of `host`, unless it is already there.
+    pub fn insert(&mut self, ip: IpAddr, host: &str) {
+        let addresses = self.names.entry(trie::key(host)).or_default();
+
+        if !addresses.contains(&ip) {
+            addresses.push(ip);
+        }
+    }
+
+    /// Addresses of `host`, empty if the file does not declare it.
+    pub fn lookup(&self, host: &str) -> &[IpAddr] {
+        self.names
+            .get(&trie::key(host))
+            .map_or(&[], |addresses| addresses.as_slice())
+    }
+
+    /// Number of distinct hostnames.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Where resolvers find the addresses of a name: a fixed table, or the
+/// current snapshot of a [`HostsDb`](crate::db::HostsDb), which follows
+/// reloads of the file.
+#[cfg(any(feature = "reqwest", feature = "hyper", feature = "hickory"))]
+#[derive(Debug, Clone)]
+enum Source {
+    Table(std::sync::Arc<HostsTable>),
+    Db(crate::db::HostsDb),
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper", feature = "hickory"))]
+impl Source {
+    fn lookup(&self, host: &str) -> Vec<IpAddr> {
+        match self {
+            Source::Table(table) => table.lookup(host).to_vec(),
+            Source::Db(db) => db.lookup(host),
+        }
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+pub use self::system::HostsResolver;
+
+/// Resolvers for HTTP clients, falling through to the system resolver.
+#[cfg(any(feature = "reqwest", feature = "hyper"))]
+mod system {
+    use std::io;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    use super::{HostsTable, Source};
+    use crate::db::HostsDb;
+
+    /// Resolves names from a [`HostsTable`] or a [`HostsDb`] for `reqwest`
+    /// and `hyper` clients, optionally asking the system resolver for the
+    /// rest.
+    ///
+    /// Addresses are returned with port 0; the clients fill in the port of
+    /// the URL.
+    #[derive(Debug, Clone)]
+    pub struct HostsResolver {
+        source: Source,
+        fallback: bool,
+    }
+
+    impl HostsResolver {
+        /// Creates a resolver answering from `table` alone. Names it does
+        /// not declare fail to resolve.
+        pub fn new(table: HostsTable) -> Self {
+            Self {
+                source: Source::Table(Arc::new(table)),
+                fallback: false,
+            }
+        }
+
+        /// Creates a resolver answering from the current snapshot of `db`,
+        /// so that reloads of the file apply to the next lookup.
+        pub fn from_db(db: HostsDb) -> Self {
+            Self {
+                source: Source::Db(db),
+                fallback: false,
+            }
+        }
+
+        /// Whether names missing from the table are looked up with the
+        /// system resolver.
+        pub fn fallback(mut self, fallback: bool) -> Self {
+            self.fallback = fallback;
+            self
+        }
+
+        pub async fn resolve(&self, host: &str) -> io::Result<Vec<SocketAddr>> {
+            let addresses = self.source.lookup(host);
+
+            if !addresses.is_empty() {
+                return Ok(addresses.iter().map(|ip| SocketAddr::new(*ip, 0)).collect());
+            }
+
+            if !self.fallback {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no hosts entry for `{}`", host),
+                ));
+            }
+
+            Ok(tokio::net::lookup_host((host, 0)).await?.collect())
+        }
+    }
+
+    #[cfg(feature = "reqwest")]
+    impl reqwest::dns::Resolve for HostsResolver {
+        fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+            let resolver = self.clone();
+
+            Box::pin(async move {
+                let addresses = resolver.resolve(name.as_str()).await?;
+                let addresses: reqwest::dns::Addrs = Box::new(addresses.into_iter());
+
+                Ok(addresses)
+            })
+        }
+    }
+
+    #[cfg(feature = "hyper")]
+    impl tower_service::Service<hyper_util::client::legacy::connect::dns::Name> for HostsResolver {
+        type Response = std::vec::IntoIter<SocketAddr>;
+        type Error = io::Error;
+        type Future =
+            std::pin::Pin<Box<dyn std::future::Future<Output = io::Result<Self::Response>> + Send>>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, name: hyper_util::client::legacy::connect::dns::Name) -> Self::Future {
+            let resolver = self.clone();
+
+            Box::pin(async move { Ok(resolver.resolve(name.as_str()).await?.into_iter()) })
+        }
+    }
+}
+
+#[cfg(feature = "hickory")]
+pub use self::hickory::HostsLookup;
+
+#[cfg(feature = "hickory")]
+mod hickory {
+    use std::net::IpAddr;
+    use std::sync::Arc;
+
+    use hickory_resolver::{ResolveError, TokioResolver};
+
+    use super::{HostsTable, Source};
+    use crate::db::HostsDb;
+
+    /// Looks names up in a [`HostsTable`] or a [`HostsDb`], passing the rest
+    /// to a hickory resolver, if any.
+    ///
+    /// This wraps the resolver rather than plugging into it: only lookups
+    /// made through [`HostsLookup::lookup_ip`] see the hosts file, while
+    /// queries sent to the [`TokioResolver`] directly, or made by code
+    /// holding it, bypass it. The resolver keeps reading the system hosts
+    /// file on its own, as configured.
+    #[derive(Clone)]
+    pub struct HostsLookup {
+        source: Source,
+        fallback: Option<TokioResolver>,
+    }
+
+    impl core::fmt::Debug for HostsLookup {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("HostsLookup")
+                .field("source", &self.source)
+                .field("fallback", &self.fallback.is_some())
+                .finish()
+        }
+    }
+
+    impl HostsLookup {
+        pub fn new(table: HostsTable) -> Self {
+            Self {
+                source: Source::Table(Arc::new(table)),
+                fallback: None,
+            }
+        }
+
+        /// Looks names up in the current snapshot of `db`, so that reloads
+        /// of the file apply to the next lookup.
+        pub fn from_db(db: HostsDb) -> Self {
+            Self {
+                source: Source::Db(db),
+                fallback: None,
+            }
+        }
+
+        /// Passes names missing from the table on to `resolver`.
+        pub fn fallback(mut self, resolver: TokioResolver) -> Self {
+            self.fallback = Some(resolver);
+            self
+        }
+
+        pub async fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>, ResolveError> {
+            let addresses = self.source.lookup(host);
+
+            if !addresses.is_empty() {
+                return Ok(addresses);
+            }
+
+            match &self.fallback {
+                Some(resolver) => Ok(resolver.lookup_ip(host).await?.iter().collect()),
+                None => Err(format!("no hosts entry for `{}`", host).into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "\
+127.0.0.1 localhost api.test
+::1 localhost
+0.0.0.0 ads.example.com
+127.0.0.1 LOCALHOST
+";
+
+    #[test]
+    fn test_lookup() {
+        let table = HostsTable::parse(HOSTS).unwrap();
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+        assert_eq!(&[ip("127.0.0.1"), ip("::1")], table.lookup("localhost"));
+        assert_eq!(&[ip("127.0.0.1")], table.lookup("API.test."));
+        assert!(table.lookup("example.com").is_empty());
+        assert_eq!(3, table.len());
+    }
+
+    /// Serves `ok` to one request on 127.0.0.1 and returns the port and the
+    /// `Host` header it received.
+    #[cfg(any(feature = "reqwest", feature = "hyper"))]
+    fn serve_once() -> (u16, std::thread::JoinHandle<String>) {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut host = String::new();
+
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("host:") {
+                    host = value.trim().into();
+                }
+
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+            }
+
+            reader
+                .into_inner()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .unwrap();
+
+            host
+        });
+
+        (port, server)
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn test_reqwest() {
+        let (port, server) = serve_once();
+        let resolver = HostsResolver::new(HostsTable::parse(HOSTS).unwrap());
+        let client = reqwest::Client::builder()
+            .dns_resolver(std::sync::Arc::new(resolver))
+            .no_proxy()
+            .build()
+            .unwrap();
+
+        let body = client
+            .get(format!("http://api.test:{}/", port))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert_eq!("ok", body);
+        assert_eq!(format!("api.test:{}", port), server.join().unwrap());
+
+        let error = client
+            .get(format!("http://missing.test:{}/", port))
+            .send()
+            .await
+            .unwrap_err();
+
+        assert!(error.is_connect());
+    }
+
+    #[cfg(any(feature = "reqwest", feature = "hyper"))]
+    #[tokio::test]
+    async fn test_fallback() {
+        let resolver = HostsResolver::new(HostsTable::parse("0.0.0.0 ads.test\n").unwrap());
+
+        assert!(resolver.resolve("localhost").await.is_err());
+
+        let addresses = resolver.fallback(true).resolve("localhost").await.unwrap();
+
+        assert!(addresses.iter().all(|address| address.ip().is_loopback()));
+    }
+
+    #[cfg(feature = "hyper")]
+    #[tokio::test]
+    async fn test_hyper() {
+        use http_body_util::{BodyExt, Empty};
+        use hyper::body::Bytes;
+        use hyper_util::client::legacy::connect::HttpConnector;
+        use hyper_util::client::legacy::Client;
+        use hyper_util::rt::TokioExecutor;
+
+        let (port, server) = serve_once();
+        let resolver = HostsResolver::new(HostsTable::parse(HOSTS).unwrap());
+        let client = Client::builder(TokioExecutor::new())
+            .build::<_, Empty<Bytes>>(HttpConnector::new_with_resolver(resolver));
+
+        let response = client
+            .get(format!("http://api.test:{}/", port).parse().unwrap())
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        assert_eq!(&b"ok"[..], &body[..]);
+        assert_eq!(format!("api.test:{}", port), server.join().unwrap());
+    }
+
+    #[cfg(feature = "hickory")]
+    #[tokio::test]
+    async fn test_hickory() {
+        let lookup = HostsLookup::new(HostsTable::parse(HOSTS).unwrap());
+
+        assert_eq!(
+            vec![
+                "127.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse().unwrap()
+            ],
+            lookup.lookup_ip("localhost").await.unwrap()
+        );
+        assert!(lookup.lookup_ip("missing.test").await.is_err());
+    }
+
+    #[cfg(any(feature = "reqwest", feature = "hyper"))]
+    #[tokio::test]
+    async fn test_resolver_follows_db_reloads() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        std::fs::write(&path, "10.0.0.5 db.test\n").unwrap();
+
+        let db = crate::db::HostsDb::open(&path).unwrap();
+        let resolver = HostsResolver::from_db(db.clone());
+        let ips = |addresses: Vec<std::net::SocketAddr>| {
+            addresses.iter().map(|a| a.ip()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec!["10.0.0.5".parse::<IpAddr>().unwrap()],
+            ips(resolver.resolve("db.test").await.unwrap())
+        );
+
+        std::fs::write(&path, "10.0.0.6 db.test\n::1 db.test\n").unwrap();
+        assert!(db.reload().unwrap());
+
+        assert_eq!(
+            vec![
+                "10.0.0.6".parse::<IpAddr>().unwrap(),
+                "::1".parse().unwrap()
+            ],
+            ips(resolver.resolve("db.test").await.unwrap())
+        );
+    }
+
+    #[cfg(feature = "hickory")]
+    #[tokio::test]
+    async fn test_hickory_follows_db_reloads() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        std::fs::write(&path, "10.0.0.5 db.test\n").unwrap();
+
+        let db = crate::db::HostsDb::open(&path).unwrap();
+        let lookup = HostsLookup::from_db(db.clone());
+
+        assert!(lookup.lookup_ip("new.test").await.is_err());
+
+        std::fs::write(&path, "10.0.0.5 db.test\n10.0.0.7 new.test\n").unwrap();
+        assert!(db.reload().unwrap());
+
+        assert_eq!(
+            vec!["10.0.0.7".parse::<IpAddr>().unwrap()],
+            lookup.lookup_ip("new.test").await.unwrap()
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use core::ops::Range;
+
+/// Location of a node in the parsed input.
+///
+/// `start` and `end` are byte offsets and `line` is 1-based. A newline node
+/// belongs to the line it terminates.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize) -> Self {
+        Self { start, end, line }
+    }
+
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Moves the span by `offset` bytes and `lines` lines.
+    #[inline]
+    pub fn shift(self, offset: usize, lines: usize) -> Self {
+        Self {
+            start: self.start + offset,
+            end: self.end + offset,
+            line: self.line + lines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift() {
+        let span = Span::new(4, 9, 1).shift(10, 2);
+
+        assert_eq!(Span::new(14, 19, 3), span);
+        assert_eq!(14..19, span.range());
+        assert_eq!(5, span.len());
+    }
+}
@@ -0,0 +1,43 @@
+//! Temporary files written next to a file and then renamed over it, so
+//! readers never see it partly written.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A hidden path in the directory of `path`, unique to this process and
+/// call, so concurrent saves never write into each other's file.
+pub(crate) fn path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "hosts".to_string());
+
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_for() {
+        let path = Path::new("/var/cache/hosts.cache");
+        let first = path_for(path);
+        let second = path_for(path);
+
+        assert_ne!(first, second);
+        assert_eq!(path.parent(), first.parent());
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with(".hosts.cache."));
+    }
+}
@@ -1,21 +1,32 @@
-use std::{
-    io::{ErrorKind, Read},
-    str::from_utf8,
-};
+use alloc::{string::String, vec::Vec};
+use core::str::from_utf8;
+#[cfg(feature = "std")]
+use std::io::{ErrorKind, Read};
 
 use thiserror::Error as ThisError;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
 
-use crate::tokens::Tokens;
+use crate::tokens::{TokenRef, Tokens};
 
+#[cfg(feature = "std")]
 const READ_BUFFER_SIZE: usize = 32 * 1024;
 
+// Length of the UTF-8 byte order mark, the longest one that is skipped.
+#[cfg(feature = "std")]
+const BOM_LEN: usize = 3;
+
+// Rough number of tokens per input byte, used to pre-size the token buffer.
+const BYTES_PER_TOKEN: usize = 6;
+
 #[derive(Debug, ThisError)]
 pub enum Error {
+    #[cfg(feature = "std")]
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
     #[error(transparent)]
-    Utf8Error(#[from] std::str::Utf8Error),
+    Utf8Error(#[from] core::str::Utf8Error),
 }
 
 #[derive(Debug)]
@@ -34,6 +45,11 @@ const fn end_of_comment(c: &char) -> bool {
     *c != '\n' && *c != '\r'
 }
 
+#[inline]
+const fn is_separator(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
 type CheckEnding = fn(&char) -> bool;
 
 fn get_data_and_separator_fn(token: &mut Tokens) -> Option<(&mut String, CheckEnding)> {
@@ -73,65 +89,120 @@ fn check_last_token(tokens: &mut [Tokens], slice: &str, start: usize) -> usize {
     }
 }
 
+/// Iterator over the tokens of a string, borrowing their text from it.
+///
+/// Scanning works on bytes: every separator is ASCII, so it can never split
+/// a multi-byte character.
+#[derive(Debug, Clone)]
+pub struct TokenIter<'a> {
+    input: &'a str,
+    position: usize,
+    comment_inside_word: bool,
+}
+
+impl<'a> TokenIter<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            position: 0,
+            comment_inside_word: false,
+        }
+    }
+
+    /// Ends a word at a `#`, which then starts a comment, as glibc, musl and
+    /// the BSDs do. By default a `#` only starts a comment at the beginning
+    /// of a word.
+    pub fn comment_inside_word(mut self, enabled: bool) -> Self {
+        self.comment_inside_word = enabled;
+        self
+    }
+
+    /// Byte offset of the next token in the input.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl<'a> Iterator for TokenIter<'a> {
+    type Item = TokenRef<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.input.as_bytes();
+        let start = self.position;
+
+        let token = match *bytes.get(start)? {
+            b'#' => {
+                let end = memchr::memchr2(b'\n', b'\r', &bytes[start + 1..])
+                    .map_or(bytes.len(), |end| start + 1 + end);
+
+                self.position = end;
+                return Some(TokenRef::Comment(&self.input[start + 1..end]));
+            }
+            b' ' => TokenRef::Space,
+            b'\t' => TokenRef::Tab,
+            b'\n' => TokenRef::NewLine,
+            b'\r' => TokenRef::CarriageReturn,
+            _ => {
+                let end = bytes[start..]
+                    .iter()
+                    .position(|b| is_separator(*b) || (self.comment_inside_word && *b == b'#'))
+                    .map_or(bytes.len(), |end| start + end);
+
+                self.position = end;
+                return Some(TokenRef::HostOrIp(&self.input[start..end]));
+            }
+        };
+
+        self.position += 1;
+        Some(token)
+    }
+}
+
 impl<T> Tokenizer<T> {
     #[inline]
     pub fn get_tokens(self) -> Vec<Tokens> {
         self.tokens
     }
+}
 
-    #[inline]
-    pub(crate) fn parse_slice(&mut self, slice: &[u8]) -> Result<(), Error> {
-        let code_points = from_utf8(slice)?;
+#[inline]
+fn tokenize_str(tokens: &mut Vec<Tokens>, code_points: &str) {
+    let advance = check_last_token(tokens, code_points, 0);
 
-        let mut advance = check_last_token(&mut self.tokens, code_points, 0);
+    tokens.extend(TokenIter::new(&code_points[advance..]).map(Tokens::from));
+}
 
-        loop {
-            if advance >= code_points.len() {
-                break;
-            }
+/// Tokenizes the valid UTF-8 prefix of `buffer[..filled]` and moves any
+/// incomplete trailing character to the front of the buffer.
+///
+/// Returns the number of bytes carried over to the next read.
+#[cfg(feature = "std")]
+fn tokenize_chunk(
+    tokens: &mut Vec<Tokens>,
+    buffer: &mut [u8],
+    filled: usize,
+    first: bool,
+) -> Result<usize, Error> {
+    let start = if first {
+        filled - skip_bom_bytes(&buffer[..filled]).len()
+    } else {
+        0
+    };
 
-            match code_points[advance..].chars().next() {
-                Some('#') => {
-                    // +1 to skip the '#' character
-                    let comment = code_points[advance + 1..]
-                        .chars()
-                        .take_while(end_of_comment)
-                        .collect::<String>();
+    let code_points = match from_utf8(&buffer[start..filled]) {
+        Ok(code_points) => code_points,
+        Err(e) if e.error_len().is_none() => from_utf8(&buffer[start..start + e.valid_up_to()])?,
+        Err(e) => return Err(e.into()),
+    };
 
-                    advance += comment.len() + 1;
-                    self.tokens.push(Tokens::Comment(comment));
-                }
-                Some('\t') => {
-                    advance += 1;
-                    self.tokens.push(Tokens::Tab);
-                }
-                Some(' ') => {
-                    self.tokens.push(Tokens::Space);
-                    advance += 1;
-                }
-                Some('\n') => {
-                    self.tokens.push(Tokens::NewLine);
-                    advance += 1;
-                }
-                Some('\r') => {
-                    self.tokens.push(Tokens::CarriageReturn);
-                    advance += 1;
-                }
-                Some(_) => {
-                    let host_or_ip = code_points[advance..]
-                        .chars()
-                        .take_while(end_of_host_or_ip)
-                        .collect::<String>();
-
-                    advance += host_or_ip.len();
-                    self.tokens.push(Tokens::HostOrIp(host_or_ip));
-                }
-                None => break,
-            }
-        }
+    let consumed = start + code_points.len();
+    tokenize_str(tokens, code_points);
 
-        Ok(())
-    }
+    buffer.copy_within(consumed..filled, 0);
+
+    Ok(filled - consumed)
 }
 
 fn check_bom_bytes_utf8(buffer: &[u8]) -> bool {
@@ -142,32 +213,104 @@ fn check_bom_bytes_utf16(buffer: &[u8]) -> bool {
     buffer.len() >= 2 && buffer[0] == 0xFE && buffer[1] == 0xFF
 }
 
+#[inline]
+pub(crate) fn skip_bom_bytes(buffer: &[u8]) -> &[u8] {
+    if check_bom_bytes_utf8(buffer) {
+        &buffer[3..]
+    } else if check_bom_bytes_utf16(buffer) {
+        &buffer[2..]
+    } else {
+        buffer
+    }
+}
+
+impl<T: AsRef<[u8]>> Tokenizer<T> {
+    pub fn new_with_bytes(input: T) -> Tokenizer<T> {
+        let capacity = input.as_ref().len() / BYTES_PER_TOKEN;
+
+        Tokenizer {
+            input,
+            tokens: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn parse_bytes(mut self) -> Result<Self, Error> {
+        let code_points = from_utf8(skip_bom_bytes(self.input.as_ref()))?;
+        tokenize_str(&mut self.tokens, code_points);
+
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "std")]
 impl<T: Read> Tokenizer<T> {
     pub fn new_with_reader(file: T) -> Tokenizer<T> {
         Tokenizer {
             input: file,
-            tokens: Vec::new(),
+            tokens: Vec::with_capacity(READ_BUFFER_SIZE / BYTES_PER_TOKEN),
         }
     }
 
     pub fn parse(mut self) -> Result<Self, Error> {
         let mut read_buffer = [0u8; READ_BUFFER_SIZE];
-        let mut buff: &[u8];
+        let mut pending = 0;
+        let mut first = true;
+
+        loop {
+            let result = self.input.read(&mut read_buffer[pending..]);
+
+            match result {
+                Ok(0) => break,
+                // Short reads may split the byte order mark, wait for all of it.
+                Ok(n) if first && pending + n < BOM_LEN => pending += n,
+                Ok(n) => {
+                    pending =
+                        tokenize_chunk(&mut self.tokens, &mut read_buffer, pending + n, first)?;
+                    first = false;
+                }
+                Err(e) => match e.kind() {
+                    ErrorKind::Interrupted => continue,
+                    _ => return Err(e.into()),
+                },
+            }
+        }
+
+        if first {
+            pending = tokenize_chunk(&mut self.tokens, &mut read_buffer, pending, true)?;
+        }
+
+        from_utf8(&read_buffer[..pending])?;
+
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: AsyncRead + Unpin> Tokenizer<T> {
+    pub fn new_with_async_reader(reader: T) -> Tokenizer<T> {
+        Tokenizer {
+            input: reader,
+            tokens: Vec::with_capacity(READ_BUFFER_SIZE / BYTES_PER_TOKEN),
+        }
+    }
+
+    pub async fn parse_async(mut self) -> Result<Self, Error> {
+        // Kept on the heap so the returned future stays small.
+        let mut read_buffer = vec![0u8; READ_BUFFER_SIZE];
+        let mut pending = 0;
+        let mut first = true;
+
         loop {
-            let result = self.input.read(&mut read_buffer[..]);
+            let result = self.input.read(&mut read_buffer[pending..]).await;
 
             match result {
                 Ok(0) => break,
+                // Short reads may split the byte order mark, wait for all of it.
+                Ok(n) if first && pending + n < BOM_LEN => pending += n,
                 Ok(n) => {
-                    buff = if check_bom_bytes_utf8(&read_buffer) {
-                        &read_buffer[3..n]
-                    } else if check_bom_bytes_utf16(&read_buffer) {
-                        &read_buffer[2..n]
-                    } else {
-                        &read_buffer[..n]
-                    };
-
-                    self.parse_slice(buff)?;
+                    pending =
+                        tokenize_chunk(&mut self.tokens, &mut read_buffer, pending + n, first)?;
+                    first = false;
                 }
                 Err(e) => match e.kind() {
                     ErrorKind::Interrupted => continue,
@@ -176,6 +319,12 @@ impl<T: Read> Tokenizer<T> {
             }
         }
 
+        if first {
+            pending = tokenize_chunk(&mut self.tokens, &mut read_buffer, pending, true)?;
+        }
+
+        from_utf8(&read_buffer[..pending])?;
+
         Ok(self)
     }
 }
@@ -248,6 +397,7 @@ mod tests {
         assert_eq!(find_separator(separator, "world", 0), "world");
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_tokenizes_multiple_hosts_on_the_same_line() {
         let data = "\
@@ -294,6 +444,7 @@ fe00::0 ip6-localnet
         );
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_parses_the_buffer() {
         let str = "\
@@ -347,6 +498,7 @@ fe00::0 ip6-localnet
         );
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn it_parses_comment_on_the_same_line_as_ip_and_host() {
         let str = "192.168.0.17\thost.docker.internal # Comment\n";
@@ -368,4 +520,147 @@ fe00::0 ip6-localnet
             tokens.unwrap().get_tokens()
         );
     }
+
+    #[test]
+    fn it_tokenizes_bytes_without_reader() {
+        let data = "\u{feff}127.0.0.1 localhost # loopback\n";
+
+        let tokens = Tokenizer::new_with_bytes(data).parse_bytes();
+
+        assert!(tokens.is_ok());
+
+        assert_eq!(
+            vec![
+                Tokens::HostOrIp("127.0.0.1".to_string()),
+                Tokens::Space,
+                Tokens::HostOrIp("localhost".to_string()),
+                Tokens::Space,
+                Tokens::Comment(" loopback".to_string()),
+                Tokens::NewLine,
+            ],
+            tokens.unwrap().get_tokens()
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_utf8_bytes() {
+        let data: &[u8] = &[b'1', b' ', 0xC3, 0x28, b'\n'];
+
+        assert!(matches!(
+            Tokenizer::new_with_bytes(data).parse_bytes(),
+            Err(Error::Utf8Error(_))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    struct ByteByByte<'a>(&'a [u8]);
+
+    #[cfg(feature = "std")]
+    impl<'a> Read for ByteByByte<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((byte, rest)) if !buf.is_empty() => {
+                    buf[0] = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_tokenizes_characters_split_across_reads() {
+        let data = "127.0.0.1 bücher.de # größe\n";
+
+        let tokens = Tokenizer::new_with_reader(ByteByByte(data.as_bytes())).parse();
+
+        assert!(tokens.is_ok());
+
+        assert_eq!(
+            vec![
+                Tokens::HostOrIp("127.0.0.1".to_string()),
+                Tokens::Space,
+                Tokens::HostOrIp("bücher.de".to_string()),
+                Tokens::Space,
+                Tokens::Comment(" größe".to_string()),
+                Tokens::NewLine,
+            ],
+            tokens.unwrap().get_tokens()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_skips_bom_split_across_reads() {
+        let data = "\u{feff}127.0.0.1 localhost\n";
+
+        let tokens = Tokenizer::new_with_reader(ByteByByte(data.as_bytes())).parse();
+
+        assert_eq!(
+            vec![
+                Tokens::HostOrIp("127.0.0.1".to_string()),
+                Tokens::Space,
+                Tokens::HostOrIp("localhost".to_string()),
+                Tokens::NewLine,
+            ],
+            tokens.unwrap().get_tokens()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_tokenizes_input_shorter_than_a_bom() {
+        let tokens = Tokenizer::new_with_reader(ByteByByte(b"a\n")).parse();
+
+        assert_eq!(
+            vec![Tokens::HostOrIp("a".to_string()), Tokens::NewLine],
+            tokens.unwrap().get_tokens()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_rejects_truncated_utf8_at_end_of_input() {
+        let data: &[u8] = &[b'a', b' ', 0xC3];
+
+        assert!(matches!(
+            Tokenizer::new_with_reader(ByteByByte(data)).parse(),
+            Err(Error::Utf8Error(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_iter() {
+        let mut iter = TokenIter::new("::1\tlocalhost#x # c\r\n");
+
+        assert_eq!(Some(TokenRef::HostOrIp("::1")), iter.next());
+        assert_eq!(Some(TokenRef::Tab), iter.next());
+        assert_eq!(Some(TokenRef::HostOrIp("localhost#x")), iter.next());
+        assert_eq!(Some(TokenRef::Space), iter.next());
+        assert_eq!(16, iter.position());
+        assert_eq!(Some(TokenRef::Comment(" c")), iter.next());
+        assert_eq!(Some(TokenRef::CarriageReturn), iter.next());
+        assert_eq!(Some(TokenRef::NewLine), iter.next());
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn test_token_iter_comment_inside_word() {
+        let tokens: Vec<_> = TokenIter::new("::1 localhost#x y\n")
+            .comment_inside_word(true)
+            .collect();
+
+        assert_eq!(
+            vec![
+                TokenRef::HostOrIp("::1"),
+                TokenRef::Space,
+                TokenRef::HostOrIp("localhost"),
+                TokenRef::Comment("x y"),
+                TokenRef::NewLine,
+            ],
+            tokens
+        );
+    }
 }
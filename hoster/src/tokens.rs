@@ -1,4 +1,5 @@
-use std::fmt::Debug;
+use alloc::string::{String, ToString};
+use core::fmt::{Debug, Display};
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Tokens {
@@ -9,3 +10,66 @@ pub enum Tokens {
     CarriageReturn,
     NewLine,
 }
+
+/// A token borrowing its text from the input it was scanned from.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenRef<'a> {
+    HostOrIp(&'a str),
+    Comment(&'a str),
+    Space,
+    Tab,
+    CarriageReturn,
+    NewLine,
+}
+
+impl Tokens {
+    pub fn as_token_ref(&self) -> TokenRef<'_> {
+        match self {
+            Tokens::HostOrIp(data) => TokenRef::HostOrIp(data),
+            Tokens::Comment(data) => TokenRef::Comment(data),
+            Tokens::Space => TokenRef::Space,
+            Tokens::Tab => TokenRef::Tab,
+            Tokens::CarriageReturn => TokenRef::CarriageReturn,
+            Tokens::NewLine => TokenRef::NewLine,
+        }
+    }
+}
+
+impl<'a> TokenRef<'a> {
+    /// Length of the token in the input, in bytes.
+    #[inline]
+    pub fn source_len(&self) -> usize {
+        match self {
+            TokenRef::HostOrIp(data) => data.len(),
+            TokenRef::Comment(data) => data.len() + 1,
+            _ => 1,
+        }
+    }
+}
+
+/// Writes the token back as it appeared in the input.
+impl<'a> Display for TokenRef<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TokenRef::HostOrIp(data) => f.write_str(data),
+            TokenRef::Comment(data) => write!(f, "#{}", data),
+            TokenRef::Space => f.write_str(" "),
+            TokenRef::Tab => f.write_str("\t"),
+            TokenRef::CarriageReturn => f.write_str("\r"),
+            TokenRef::NewLine => f.write_str("\n"),
+        }
+    }
+}
+
+impl<'a> From<TokenRef<'a>> for Tokens {
+    fn from(token: TokenRef<'a>) -> Self {
+        match token {
+            TokenRef::HostOrIp(data) => Tokens::HostOrIp(data.to_string()),
+            TokenRef::Comment(data) => Tokens::Comment(data.to_string()),
+            TokenRef::Space => Tokens::Space,
+            TokenRef::Tab => Tokens::Tab,
+            TokenRef::CarriageReturn => Tokens::CarriageReturn,
+            TokenRef::NewLine => Tokens::NewLine,
+        }
+    }
+}
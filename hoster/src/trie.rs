@@ -0,0 +1,322 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::net::IpAddr;
+
+use hashbrown::HashMap;
+
+use crate::cst::CstNode;
+use crate::document::Document;
+use crate::hostname;
+
+const ROOT: u32 = 0;
+
+/// A hostname listed in a [`SuffixTrie`], as it was written in the file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Entry {
+    pub ip: IpAddr,
+    pub host: String,
+    /// Line of the hostname, when built from a [`Document`].
+    pub line: Option<usize>,
+}
+
+/// An entry that a listed parent domain already covers.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Redundant<'a> {
+    pub entry: &'a Entry,
+    pub parent: &'a Entry,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    parent: u32,
+    children: HashMap<Box<str>, u32>,
+    entry: Option<u32>,
+}
+
+/// Index of hostnames by their labels from the top level domain down, so
+/// that `ads.example.com` sits below `example.com`, which sits below `com`.
+///
+/// Names are compared in their normalized form, so `Example.COM.` and
+/// `example.com` are the same name. When a name is listed more than once
+/// the first entry wins, as it does for resolvers.
+#[derive(Debug, Clone)]
+pub struct SuffixTrie {
+    nodes: Vec<Node>,
+    entries: Vec<(u32, Entry)>,
+}
+
+impl Default for SuffixTrie {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl SuffixTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes every hostname of `document`, remembering its line.
+    pub fn from_document<const LENGTH: usize>(document: &Document<LENGTH>) -> Self {
+        let mut trie = Self::new();
+        let mut ip = None;
+
+        for (node, span) in document.iter() {
+            match node {
+                CstNode::IP(address) => ip = Some(*address),
+                CstNode::Host(host) => {
+                    if let Some(ip) = ip {
+                        trie.push(Entry {
+                            ip,
+                            host: host.clone(),
+                            line: Some(span.line),
+                        });
+                    }
+                }
+                CstNode::NewLine => ip = None,
+                _ => {}
+            }
+        }
+
+        trie
+    }
+
+    /// Lists `host` under `ip`. Returns `false` if the name was already
+    /// listed, in which case the first entry is kept.
+    pub fn insert(&mut self, ip: IpAddr, host: &str) -> bool {
+        self.push(Entry {
+            ip,
+            host: host.into(),
+            line: None,
+        })
+    }
+
+    /// Number of listed names.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Listed entries in the order they were inserted.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().map(|(_, entry)| entry)
+    }
+
+    /// The entry of `host` itself.
+    pub fn get(&self, host: &str) -> Option<&Entry> {
+        let key = key(host);
+        let mut node = ROOT;
+
+        for label in key.rsplit('.') {
+            node = *self.nodes[node as usize].children.get(label)?;
+        }
+
+        let index = self.nodes[node as usize].entry?;
+
+        Some(&self.entries[index as usize].1)
+    }
+
+    /// Whether `host` itself is listed.
+    pub fn matches_exact(&self, host: &str) -> bool {
+        self.get(host).is_some()
+    }
+
+    /// Whether `host` or any of its parent domains is listed.
+    pub fn matches_suffix(&self, host: &str) -> bool {
+        self.longest_match(host).is_some()
+    }
+
+    /// The most specific listed entry that is `host` or one of its parent
+    /// domains.
+    pub fn longest_match(&self, host: &str) -> Option<&Entry> {
+        let key = key(host);
+        let mut node = ROOT;
+        let mut found = None;
+
+        for label in key.rsplit('.') {
+            match self.nodes[node as usize].children.get(label) {
+                Some(&child) => node = child,
+                None => break,
+            }
+
+            found = self.nodes[node as usize].entry.or(found);
+        }
+
+        found.map(|index| &self.entries[index as usize].1)
+    }
+
+    /// Entries whose closest listed parent domain points at the same IP
+    /// address, so removing them changes nothing for suffix matching.
+    ///
+    /// Entries below a parent listed with a different address override it
+    /// and are not reported.
+    pub fn redundant(&self) -> impl Iterator<Item = Redundant<'_>> {
+        self.entries.iter().filter_map(move |(node, entry)| {
+            let mut ancestor = self.nodes[*node as usize].parent;
+
+            while ancestor != ROOT {
+                let node = &self.nodes[ancestor as usize];
+
+                if let Some(index) = node.entry {
+                    let parent = &self.entries[index as usize].1;
+
+                    return (parent.ip == entry.ip).then_some(Redundant { entry, parent });
+                }
+
+                ancestor = node.parent;
+            }
+
+            None
+        })
+    }
+
+    fn push(&mut self, entry: Entry) -> bool {
+        let key = key(&entry.host);
+        let mut node = ROOT;
+
+        for label in key.rsplit('.') {
+            node = match self.nodes[node as usize].children.get(label) {
+                Some(&child) => child,
+                None => {
+                    let child = u32::try_from(self.nodes.len()).expect("trie exceeds 4G nodes");
+
+                    self.nodes.push(Node {
+                        parent: node,
+                        ..Node::default()
+                    });
+                    self.nodes[node as usize]
+                        .children
+                        .insert(label.into(), child);
+
+                    child
+                }
+            };
+        }
+
+        if self.nodes[node as usize].entry.is_some() {
+            return false;
+        }
+
+        self.nodes[node as usize].entry = Some(self.entries.len() as u32);
+        self.entries.push((node, entry));
+
+        true
+    }
+}
+
+impl<'a> Extend<(IpAddr, &'a str)> for SuffixTrie {
+    fn extend<T: IntoIterator<Item = (IpAddr, &'a str)>>(&mut self, iter: T) {
+        for (ip, host) in iter {
+            self.insert(ip, host);
+        }
+    }
+}
+
+impl<'a> FromIterator<(IpAddr, &'a str)> for SuffixTrie {
+    fn from_iter<T: IntoIterator<Item = (IpAddr, &'a str)>>(iter: T) -> Self {
+        let mut trie = Self::new();
+        trie.extend(iter);
+        trie
+    }
+}
+
+/// Names that cannot be normalized are only lowercased.
+pub(crate) fn key(host: &str) -> String {
+    hostname::normalize(host).unwrap_or_else(|_| host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    const BLOCKED: &str = "0.0.0.0";
+
+    fn trie(hosts: &[&str]) -> SuffixTrie {
+        let ip = BLOCKED.parse().unwrap();
+
+        hosts.iter().map(|host| (ip, *host)).collect()
+    }
+
+    #[test]
+    fn test_matches() {
+        let trie = trie(&["doubleclick.net", "ads.example.com", "Tracker.COM."]);
+
+        assert!(trie.matches_exact("doubleclick.net"));
+        assert!(!trie.matches_exact("ads.doubleclick.net"));
+        assert!(!trie.matches_exact("example.com"));
+        assert!(trie.matches_exact("tracker.com"));
+
+        assert!(trie.matches_suffix("ads.doubleclick.net"));
+        assert!(trie.matches_suffix("x.y.ads.example.com"));
+        assert!(!trie.matches_suffix("example.com"));
+        assert!(!trie.matches_suffix("bads.example.com"));
+        assert!(!trie.matches_suffix("net"));
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let trie = trie(&["example.com", "ads.example.com", "bücher.de"]);
+
+        let host = |name| trie.longest_match(name).map(|entry| entry.host.as_str());
+
+        assert_eq!(Some("ads.example.com"), host("eu.ads.example.com"));
+        assert_eq!(Some("example.com"), host("www.example.com"));
+        assert_eq!(Some("bücher.de"), host("shop.xn--bcher-kva.de"));
+        assert_eq!(None, host("example.org"));
+    }
+
+    #[test]
+    fn test_first_entry_wins() {
+        let mut trie = SuffixTrie::new();
+
+        assert!(trie.insert("0.0.0.0".parse().unwrap(), "example.com"));
+        assert!(!trie.insert("127.0.0.1".parse().unwrap(), "EXAMPLE.com"));
+        assert_eq!(1, trie.len());
+        assert_eq!(
+            "0.0.0.0".parse::<IpAddr>().unwrap(),
+            trie.longest_match("example.com").unwrap().ip
+        );
+    }
+
+    #[test]
+    fn test_redundant() {
+        let data = "\
+0.0.0.0 ads.example.com
+0.0.0.0 example.com cdn.example.com
+127.0.0.1 local.example.com
+0.0.0.0 eu.ads.example.com # covered by ads.example.com
+0.0.0.0 example.org
+";
+        let document = Parser::default().parse_document::<1>(data).unwrap();
+        let trie = SuffixTrie::from_document(&document);
+
+        let redundant: Vec<_> = trie
+            .redundant()
+            .map(|redundant| {
+                (
+                    redundant.entry.host.as_str(),
+                    redundant.entry.line,
+                    redundant.parent.host.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("ads.example.com", Some(1), "example.com"),
+                ("cdn.example.com", Some(2), "example.com"),
+                ("eu.ads.example.com", Some(4), "ads.example.com"),
+            ],
+            redundant
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use hoster::arena::Arena;
+use hoster::parser::Parser;
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+// Tests in this file run on several threads; measurements must not overlap.
+static MEASURING: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Peak bytes allocated while running `f`, on top of what was live before.
+fn peak_of<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+
+    let value = f();
+
+    (value, PEAK.load(Ordering::Relaxed) - base)
+}
+
+/// A blocklist of `lines` entries, sharing all but `lines / 10` of them with
+/// the list of the next `seed`.
+fn blocklist(seed: usize, lines: usize) -> String {
+    let mut data = String::from("# blocklist\n");
+    let start = seed * lines / 10;
+
+    for i in start..start + lines {
+        writeln!(
+            data,
+            "0.0.0.0 ads-{}.tracker{}.example{}.com",
+            i,
+            i % 500,
+            i % 40
+        )
+        .unwrap();
+    }
+
+    data
+}
+
+/// Peak memory of parsing `lists` into CSTs and into one arena.
+fn compare(lists: &[String]) -> (usize, usize) {
+    let (csts, cst_peak) = peak_of(|| {
+        lists
+            .iter()
+            .map(|list| Parser::default().parse_str::<1>(list).unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let ((arena, documents), arena_peak) = peak_of(|| {
+        let mut arena = Arena::new();
+        let documents = lists
+            .iter()
+            .map(|list| arena.parse(list).unwrap())
+            .collect::<Vec<_>>();
+
+        (arena, documents)
+    });
+
+    for (cst, document) in csts.iter().zip(&documents) {
+        assert_eq!(cst.nodes, document.to_cst::<1>(&arena).nodes);
+    }
+
+    (cst_peak, arena_peak)
+}
+
+/// The saving promised for several blocklists loaded into one arena.
+const SEVERAL_LISTS_SAVING: usize = 10;
+
+#[test]
+fn test_several_blocklists_take_an_order_of_magnitude_less_memory() {
+    let _guard = MEASURING.lock().unwrap();
+
+    // Every hostname is stored once however many lists contain it.
+    let lists: Vec<String> = (0..4).map(|seed| blocklist(seed, 50_000)).collect();
+    let (cst_peak, arena_peak) = compare(&lists);
+
+    assert!(
+        arena_peak * SEVERAL_LISTS_SAVING <= cst_peak,
+        "arena peaked at {} bytes, more than a tenth of the {} of the CSTs",
+        arena_peak,
+        cst_peak
+    );
+}
+
+#[test]
+fn test_single_blocklist_memory() {
+    let _guard = MEASURING.lock().unwrap();
+
+    // A single list of unique names only saves on the nodes and suffixes,
+    // which the order of magnitude above does not cover.
+    let (cst_peak, arena_peak) = compare(&[blocklist(0, 50_000)]);
+
+    assert!(
+        arena_peak * 3 <= cst_peak,
+        "arena peaked at {} bytes, the CST at {}",
+        arena_peak,
+        cst_peak
+    );
+}
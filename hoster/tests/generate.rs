@@ -0,0 +1,96 @@
+#![cfg(any(feature = "arbitrary", feature = "proptest"))]
+
+use hoster::cst::Cst;
+use hoster::generate::HostsFile;
+use hoster::parser::{InvalidLines, Parser};
+use hoster::tokenizer::Tokenizer;
+
+/// Tokenizes and parses `file` the long way round, through a token vector.
+fn parse_tokens(file: &HostsFile, parser: &Parser<()>) -> Result<Cst<1>, hoster::parser::Error> {
+    let text = file.to_string();
+    let tokens = Tokenizer::new_with_bytes(text.as_bytes())
+        .parse_bytes()
+        .unwrap()
+        .get_tokens();
+
+    parser.parse(tokens)
+}
+
+fn keep_invalid_lines() -> Parser<()> {
+    Parser::builder().invalid_lines(InvalidLines::Keep).build()
+}
+
+#[cfg(feature = "proptest")]
+mod strategies {
+    use hoster::generate::strategy::{malformed_hosts_file, valid_hosts_file};
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn valid_files_round_trip(file in valid_hosts_file()) {
+            let cst = parse_tokens(&file, &Parser::default()).unwrap();
+
+            prop_assert_eq!(file.body(), cst.to_string());
+            prop_assert_eq!(
+                &cst.nodes,
+                &Parser::default().parse_str::<1>(&file.to_string()).unwrap().nodes
+            );
+            prop_assert!(cst
+                .entries()
+                .map(|(ip, host)| (ip, host.to_string()))
+                .eq(file
+                    .entries()
+                    .flat_map(|entry| entry.hosts.iter().map(|host| (entry.ip, host.clone())))));
+        }
+
+        #[test]
+        fn malformed_files_are_rejected_or_kept(file in malformed_hosts_file()) {
+            prop_assert!(!file.is_valid());
+            prop_assert!(parse_tokens(&file, &Parser::default()).is_err());
+            prop_assert!(Parser::default().parse_str::<1>(&file.to_string()).is_err());
+            prop_assert_eq!(
+                file.body(),
+                parse_tokens(&file, &keep_invalid_lines()).unwrap().to_string()
+            );
+        }
+
+        #[test]
+        fn any_file_round_trips_keeping_invalid_lines(file in any::<HostsFile>()) {
+            let cst = parse_tokens(&file, &keep_invalid_lines()).unwrap();
+
+            prop_assert_eq!(file.body(), cst.to_string());
+            prop_assert_eq!(file.is_valid(), parse_tokens(&file, &Parser::default()).is_ok());
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary_files_round_trip() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+
+    for _ in 0..256 {
+        let bytes = (0..1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        let file = HostsFile::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        let cst = parse_tokens(&file, &keep_invalid_lines()).unwrap();
+
+        assert_eq!(file.body(), cst.to_string());
+        assert_eq!(
+            file.is_valid(),
+            parse_tokens(&file, &Parser::default()).is_ok(),
+            "{:?}",
+            file.to_string()
+        );
+    }
+}
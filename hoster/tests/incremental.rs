@@ -0,0 +1,98 @@
+use hoster::incremental::TextEdit;
+use hoster::parser::{InvalidLines, Parser};
+use proptest::prelude::*;
+
+const FRAGMENTS: &[&str] = &[
+    "127.0.0.1",
+    "::1",
+    "0.0.0.0",
+    " ",
+    "\t",
+    "localhost",
+    "ads.example.com",
+    "# comment",
+    "#",
+    "\r",
+    "\n",
+    "\n",
+    "\n",
+    "nope",
+    "\u{feff}",
+];
+
+const LINES: &[&str] = &[
+    "127.0.0.1 localhost\n",
+    "::1 localhost ip6-localhost\r\n",
+    "\t0.0.0.0\tads.example.com # ads\n",
+    "# comment\n",
+    "\n",
+    "0.0.0.0 tracker.example.com",
+];
+
+fn text() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(FRAGMENTS), 0..40).prop_map(|parts| parts.concat())
+}
+
+/// A hosts file that parses, in which the last line may be unterminated.
+fn hosts() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(LINES), 0..12).prop_map(|lines| lines.concat())
+}
+
+/// A document and an edit of it, with the range on character boundaries.
+fn edited(
+    input: impl Strategy<Value = String>,
+) -> impl Strategy<Value = (String, usize, usize, String)> {
+    (
+        input,
+        any::<prop::sample::Index>(),
+        any::<prop::sample::Index>(),
+        text(),
+    )
+        .prop_map(|(input, a, b, replacement)| {
+            let a = input.floor_char_boundary(a.index(input.len() + 1));
+            let b = input.floor_char_boundary(b.index(input.len() + 1));
+
+            (input, a.min(b), a.max(b), replacement)
+        })
+}
+
+fn check(parser: &Parser<()>, input: &str, start: usize, end: usize, replacement: &str) {
+    let Ok(mut document) = parser.parse_document::<1>(input) else {
+        return;
+    };
+    let edit = TextEdit::new(start..end, replacement);
+    let mut edited = input.to_string();
+    edit.apply(&mut edited);
+
+    let full = parser.parse_document::<1>(&edited);
+    let incremental = parser.reparse(&mut document, &edited, &edit);
+
+    match (full, incremental) {
+        (Ok(full), Ok(changed)) => {
+            assert_eq!(full.cst.nodes, document.cst.nodes);
+            assert_eq!(full.spans, document.spans);
+            assert!(changed.end <= document.spans.len());
+        }
+        (Err(full), Err(incremental)) => {
+            assert_eq!(full.to_string(), incremental.to_string());
+            assert_eq!(full.span(), incremental.span());
+        }
+        (full, incremental) => panic!("full parse {:?}, reparse {:?}", full, incremental),
+    }
+}
+
+proptest! {
+    #[test]
+    fn reparse_matches_full_parse((input, start, end, replacement) in edited(hosts())) {
+        check(&Parser::default(), &input, start, end, &replacement);
+    }
+
+    #[test]
+    fn reparse_matches_full_parse_keeping_invalid_lines(
+        (input, start, end, replacement) in edited(text())
+    ) {
+        let parser = Parser::builder().invalid_lines(InvalidLines::Keep).build();
+
+        check(&parser, &input, start, end, &replacement);
+    }
+}
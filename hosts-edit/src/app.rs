@@ -1,9 +1,18 @@
-use std::io::{Cursor, Seek, SeekFrom, Write};
-use std::{fs::File, path::PathBuf};
+use std::io::{ErrorKind, Write};
+use std::process::ExitCode;
+use std::{fs, fs::File, path::Path, path::PathBuf};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use hoster::dialect::Dialect;
+use hoster::error::Format;
+use hoster::hostname;
+use hoster::metadata::{self, Pair};
+use hoster::net::{AddressClass, AddressFilter, Cidr};
 
 use crate::commands::add::execute as add_command;
+use crate::commands::aliases;
+use crate::commands::check::execute as check_command;
+use crate::commands::import::execute as import_command;
 use crate::commands::list::execute as list_command;
 use crate::commands::remove::execute as remove_command;
 
@@ -24,57 +33,392 @@ pub(crate) enum Commands {
     List {
         #[clap(short, long, required = false, value_parser)]
         with_comments: bool,
+        /// Only list entries in this subnet, such as 10.0.0.0/8 (repeatable)
+        #[clap(long, value_parser)]
+        cidr: Vec<Cidr>,
+        /// Only list entries of this address class: loopback, private,
+        /// link-local, sinkhole or public (repeatable)
+        #[clap(long, value_parser)]
+        class: Vec<AddressClass>,
+        /// Only list entries whose comments hold this key=value pair
+        /// (repeatable)
+        #[clap(long, value_parser)]
+        meta: Vec<Pair>,
+    },
+    /// Checks the hosts file against the rules of a platform
+    Check {
+        #[clap(short, long, value_parser, default_value_t = Dialect::native())]
+        dialect: Dialect,
+        /// File to check instead of the system hosts file
+        #[clap(required = false, value_parser)]
+        file: Option<PathBuf>,
+    },
+    /// Adds the entries of a hosts file or blocklist whose hostnames are not
+    /// in the hosts file yet. gzip, xz and zstd files are decompressed
+    Import {
+        #[clap(value_parser)]
+        file: PathBuf,
+    },
+    /// Manages the user alias file instead of the hosts file: the one
+    /// HOSTALIASES names, or ~/.hosts-aliases
+    #[clap(long_flag = "user")]
+    User {
+        #[clap(subcommand)]
+        commands: UserCommands,
     },
     Version,
 }
 
+#[derive(Debug, Subcommand)]
+pub(crate) enum UserCommands {
+    /// Points an alias at a hostname
+    Add {
+        /// Name without dots
+        #[clap(value_parser)]
+        alias: String,
+        /// Hostname the alias stands for
+        #[clap(value_parser)]
+        name: String,
+    },
+    Remove {
+        #[clap(value_parser)]
+        alias: String,
+    },
+    List,
+    /// Prints the line pointing HOSTALIASES at the user alias file, for
+    /// shell init files
+    Env,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum ErrorFormat {
+    Rich,
+    Plain,
+    Json,
+}
+
+impl From<ErrorFormat> for Format {
+    fn from(format: ErrorFormat) -> Self {
+        match format {
+            ErrorFormat::Rich => Format::Rich,
+            ErrorFormat::Plain => Format::Plain,
+            ErrorFormat::Json => Format::Json,
+        }
+    }
+}
+
 #[derive(Debug, Parser)]
 #[clap(name = "hosts",about = "Parses and modified OS Hosts file", long_about = None)]
 pub struct App {
     #[clap(subcommand)]
     commands: Commands,
+    /// How parse errors are printed
+    #[clap(long, global = true, value_enum, default_value = "rich")]
+    error_format: ErrorFormat,
 }
 
-pub fn execute<P>(path: P) -> Result<(), Box<dyn std::error::Error>>
+pub fn execute<P>(path: P) -> ExitCode
 where
     P: Into<PathBuf>,
 {
     let app = App::parse();
+    let format = app.error_format.into();
+    let path = match &app.commands {
+        Commands::Check {
+            file: Some(file), ..
+        } => Ok(file.clone()),
+        Commands::User { .. } => aliases::path(),
+        _ => Ok(path.into()),
+    };
+    let path = match path {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    // The hosts file is parsed leniently on import, so errors come from the
+    // imported file.
+    let origin = match &app.commands {
+        Commands::Import { file } => file.clone(),
+        _ => path.clone(),
+    };
 
+    match run(app, path) {
+        Ok(code) => code,
+        Err(error) => {
+            let rendered = render_error(error, format, &origin);
+            eprintln!("{}", rendered.trim_end());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub(crate) fn render_error(
+    error: Box<dyn std::error::Error>,
+    format: Format,
+    path: &std::path::Path,
+) -> String {
+    into_hoster_error(error).render(format, Some(&path.display().to_string()))
+}
+
+/// Turns the errors of the hoster APIs used by the commands into the matching
+/// kind of [`hoster::Error`], so they render with their help text. Anything
+/// else is a plain message.
+fn into_hoster_error(error: Box<dyn std::error::Error>) -> hoster::Error {
+    fn convert<T>(
+        error: Box<dyn std::error::Error>,
+    ) -> Result<hoster::Error, Box<dyn std::error::Error>>
+    where
+        T: std::error::Error + Into<hoster::Error> + 'static,
+    {
+        error.downcast::<T>().map(|error| (*error).into())
+    }
+
+    convert::<hoster::Error>(error)
+        .or_else(convert::<std::io::Error>)
+        .or_else(convert::<hostname::Error>)
+        .or_else(convert::<metadata::Error>)
+        .or_else(convert::<hoster::aliases::Error>)
+        .unwrap_or_else(|error| hoster::Error::new(std::io::Error::other(error.to_string())))
+}
+
+fn run(app: App, path: PathBuf) -> Result<ExitCode, Box<dyn std::error::Error>> {
     let mut file_options = File::options();
 
     match app.commands {
         Commands::Add { host, ip, comment } => {
             add_command(
-                &mut file_options.append(true).open(path.into())?,
+                &mut file_options.append(true).open(&path)?,
                 ip,
                 host,
                 comment,
             )?;
         }
         Commands::Remove { host } => {
-            let mut hosts = file_options.open(path.into())?;
             let mut data = Vec::with_capacity(2048);
 
-            remove_command(&mut hosts, &mut Cursor::new(&mut data), host)?;
-
-            hosts.seek(SeekFrom::Start(0))?;
-            hosts.set_len(data.len() as u64)?;
-            let _n = hosts.write(&data)?;
+            if remove_command(&mut file_options.read(true).open(&path)?, &mut data, host)? {
+                replace_file(&path, &data)?;
+            }
         }
-        Commands::List { with_comments } => {
+        Commands::List {
+            with_comments,
+            cidr,
+            class,
+            meta,
+        } => {
             let stdout = std::io::stdout();
 
             list_command(
-                &mut file_options.append(false).read(true).open(path.into())?,
+                &mut file_options.append(false).read(true).open(&path)?,
                 &mut stdout.lock(),
                 with_comments,
+                &AddressFilter {
+                    cidrs: cidr,
+                    classes: class,
+                },
+                &meta,
+            )?;
+        }
+        Commands::Check { dialect, .. } => {
+            let stdout = std::io::stdout();
+
+            let found = check_command(
+                &mut file_options.read(true).open(&path)?,
+                &mut stdout.lock(),
+                &path.display().to_string(),
+                dialect,
+            )?;
+
+            if found > 0 {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Import { file } => {
+            let mut data = Vec::with_capacity(2048);
+
+            let added = import_command(
+                &mut file_options.read(true).open(&path)?,
+                File::open(&file)?,
+                &file.display().to_string(),
+                &mut data,
             )?;
+
+            replace_file(&path, &data)?;
+
+            println!("Imported {} hostnames from {}", added, file.display());
         }
+        Commands::User { commands } => return run_user(commands, path),
         Commands::Version => {
             println!("{}", env!("CARGO_PKG_VERSION"));
         }
     }
 
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Replaces the contents of the file at `path` with `data` by renaming a
+/// temporary file from the same directory over it, so that a failed write
+/// leaves the file as it was. Symbolic links are followed and the
+/// permissions of the file are kept; a missing file is created with the
+/// permissions `open` would give it.
+fn replace_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(error) if error.kind() == ErrorKind::NotFound => path.to_path_buf(),
+        Err(error) => return Err(error),
+    };
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut builder = tempfile::Builder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // The umask applies, as with `open`.
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+
+    let mut file = builder.tempfile_in(directory)?;
+    file.write_all(data)?;
+
+    if let Ok(metadata) = fs::metadata(&path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+
+    file.as_file().sync_all()?;
+    file.persist(&path)?;
+
     Ok(())
 }
+
+/// Runs `commands` against the user alias file at `path`, which is created
+/// on first use.
+fn run_user(commands: UserCommands, path: PathBuf) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(error) => return Err(error.into()),
+    };
+    let mut output = Vec::with_capacity(data.len() + 64);
+
+    match commands {
+        UserCommands::Add { alias, name } => {
+            aliases::add(&mut data.as_slice(), &mut output, &alias, &name)?;
+            replace_file(&path, &output)?;
+        }
+        UserCommands::Remove { alias } => {
+            if aliases::remove(&mut data.as_slice(), &mut output, &alias)? > 0 {
+                replace_file(&path, &output)?;
+            }
+        }
+        UserCommands::List => {
+            aliases::list(&mut data.as_slice(), &mut std::io::stdout().lock())?;
+        }
+        UserCommands::Env => {
+            aliases::env(&mut std::io::stdout().lock(), &path)?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use hoster::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_user_commands() {
+        let app = App::try_parse_from(["hosts", "--user", "add", "db", "db.example.com"]).unwrap();
+
+        assert!(matches!(
+            app.commands,
+            Commands::User {
+                commands: UserCommands::Add { alias, name },
+            } if alias == "db" && name == "db.example.com"
+        ));
+        assert!(App::try_parse_from(["hosts", "--user", "list", "--cidr", "10.0.0.0/8"]).is_err());
+        assert!(
+            App::try_parse_from(["hosts", "--user", "add", "db", "x", "--comment", "c"]).is_err()
+        );
+        assert!(App::try_parse_from(["hosts", "--user", "import", "blocklist.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.1 old.test\n").unwrap();
+
+        replace_file(&path, b"127.0.0.1 localhost\n").unwrap();
+
+        assert_eq!("127.0.0.1 localhost\n", fs::read_to_string(&path).unwrap());
+        // The temporary file was renamed over the hosts file.
+        assert_eq!(1, fs::read_dir(directory.path()).unwrap().count());
+
+        let missing = directory.path().join("missing");
+        replace_file(&missing, b"db db.example.com\n").unwrap();
+        assert_eq!("db db.example.com\n", fs::read_to_string(&missing).unwrap());
+        assert!(replace_file(&directory.path().join("no/such/file"), b"").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_file_through_symlink() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("hosts");
+        let link = directory.path().join("link");
+        fs::write(&path, "10.0.0.1 old.test\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+
+        replace_file(&link, b"10.0.0.2 new.test\n").unwrap();
+
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!("10.0.0.2 new.test\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            0o640,
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777
+        );
+    }
+
+    #[test]
+    fn test_errors_keep_their_kind() {
+        let error = into_hoster_error(hostname::normalize("exa mple.com").unwrap_err().into());
+        assert!(matches!(error.kind(), ErrorKind::Hostname(_)));
+        assert!(error.help().is_some());
+
+        let error = into_hoster_error("--bad".parse::<Pair>().unwrap_err().into());
+        assert!(matches!(error.kind(), ErrorKind::Metadata(_)));
+
+        let error = into_hoster_error(
+            hoster::aliases::Aliases::default()
+                .set("db.dev", "db.example.com")
+                .unwrap_err()
+                .into(),
+        );
+        assert!(matches!(error.kind(), ErrorKind::Aliases(_)));
+        assert_eq!(None, error.line());
+
+        let error = into_hoster_error(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        assert!(
+            matches!(error.kind(), ErrorKind::Io(error) if error.kind() == std::io::ErrorKind::NotFound)
+        );
+
+        assert_eq!(
+            "error: no home directory\n",
+            render_error(
+                "no home directory".into(),
+                Format::Rich,
+                std::path::Path::new("hosts")
+            )
+        );
+    }
+}
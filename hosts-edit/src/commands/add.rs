@@ -1,6 +1,7 @@
 use std::io::Write;
 
 use hoster::cst::{Cst, CstNode};
+use hoster::hostname;
 use smallvec::{smallvec, SmallVec};
 
 pub(crate) fn execute<W>(
@@ -12,6 +13,8 @@ pub(crate) fn execute<W>(
 where
     W: Write,
 {
+    let host = hostname::normalize(&host)?;
+
     let cst: SmallVec<[CstNode; 6]> = match comment {
         Some(comment) => smallvec![
             CstNode::Comment(comment),
@@ -31,7 +34,7 @@ where
 
     let cst = Cst { nodes: cst };
 
-    write!(writer, "{}", cst.to_string())?;
+    cst.write_to(writer)?;
 
     Ok(())
 }
@@ -86,4 +89,37 @@ mod tests {
             "# here is my comment\n127.0.0.1 localhost\n".to_string()
         );
     }
+
+    #[test]
+    fn test_add_command_writes_ace_form() {
+        let mut writer = Cursor::new(Vec::new());
+
+        let result = execute(
+            &mut writer,
+            "127.0.0.1".to_string(),
+            "Bücher.DE.".to_string(),
+            None,
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "127.0.0.1 xn--bcher-kva.de\n".to_string()
+        );
+    }
+
+    #[test]
+    fn test_add_command_invalid_host() {
+        let mut writer = Cursor::new(Vec::new());
+
+        let result = execute(
+            &mut writer,
+            "127.0.0.1".to_string(),
+            "exa mple.com".to_string(),
+            None,
+        );
+
+        assert!(result.is_err());
+        assert!(writer.into_inner().is_empty());
+    }
 }
@@ -0,0 +1,195 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use hoster::aliases::Aliases;
+
+/// Name of the per-user alias file, in the home directory.
+const FILE_NAME: &str = ".hosts-aliases";
+
+/// Path of the per-user alias file: the one `HOSTALIASES` names, as the
+/// resolver reads it, or `~/.hosts-aliases`.
+pub(crate) fn path() -> Result<PathBuf, Box<dyn Error>> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+
+    path_from(std::env::var_os("HOSTALIASES"), home)
+}
+
+fn path_from(
+    hostaliases: Option<OsString>,
+    home: Option<OsString>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(path) = hostaliases.filter(|path| !path.is_empty()) {
+        return Ok(path.into());
+    }
+
+    let home = home.ok_or("cannot find the home directory, HOME is not set")?;
+
+    Ok(Path::new(&home).join(FILE_NAME))
+}
+
+fn parse(reader: &mut impl Read) -> Result<Aliases, Box<dyn Error>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    Ok(Aliases::parse_bytes(&data)?)
+}
+
+/// Points `alias` at `name` and writes the file to `writer`. Returns the
+/// hostname the alias had.
+pub(crate) fn add(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    alias: &str,
+    name: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let mut aliases = parse(reader)?;
+    let previous = aliases.set(alias, name)?;
+
+    write!(writer, "{}", aliases)?;
+
+    Ok(previous)
+}
+
+/// Removes `alias` and writes the file to `writer`. Returns how many lines
+/// were removed.
+pub(crate) fn remove(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    alias: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let mut aliases = parse(reader)?;
+    let removed = aliases.remove(alias);
+
+    write!(writer, "{}", aliases)?;
+
+    Ok(removed)
+}
+
+pub(crate) fn list(reader: &mut impl Read, writer: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    for (alias, name) in parse(reader)?.entries() {
+        writeln!(writer, "{}\t{}", alias, name)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the line pointing `HOSTALIASES` at `path`, for shell init files.
+pub(crate) fn env(writer: &mut impl Write, path: &Path) -> Result<(), Box<dyn Error>> {
+    writeln!(
+        writer,
+        "export HOSTALIASES={}",
+        quote(&path.display().to_string())
+    )?;
+
+    Ok(())
+}
+
+/// Quotes `text` for POSIX shells, when it needs it.
+fn quote(text: &str) -> String {
+    let safe = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | '~' | ':'));
+
+    if safe {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALIASES: &str = "# dev\ndb db.internal.example.com\n";
+
+    #[test]
+    fn test_path() {
+        let home = || Some(OsString::from("/home/me"));
+
+        assert_eq!(
+            PathBuf::from("/home/me/.hosts-aliases"),
+            path_from(None, home()).unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/home/me/.hosts-aliases"),
+            path_from(Some(OsString::new()), home()).unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/etc/aliases.d/me"),
+            path_from(Some("/etc/aliases.d/me".into()), home()).unwrap()
+        );
+        assert!(path_from(None, None).is_err());
+    }
+
+    #[test]
+    fn test_add_command() {
+        let mut output = Vec::new();
+
+        assert_eq!(
+            None,
+            add(
+                &mut ALIASES.as_bytes(),
+                &mut output,
+                "web",
+                "www.example.com"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            "# dev\ndb db.internal.example.com\nweb www.example.com\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        let mut output = Vec::new();
+        assert_eq!(
+            Some("db.internal.example.com".to_string()),
+            add(&mut ALIASES.as_bytes(), &mut output, "DB", "db.example.com").unwrap()
+        );
+        assert_eq!(
+            "# dev\ndb db.example.com\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        assert!(add(&mut ALIASES.as_bytes(), &mut Vec::new(), "db.dev", "x").is_err());
+        assert!(add(&mut &b"db\n"[..], &mut Vec::new(), "web", "x").is_err());
+    }
+
+    #[test]
+    fn test_remove_command() {
+        let mut output = Vec::new();
+
+        assert_eq!(
+            1,
+            remove(&mut ALIASES.as_bytes(), &mut output, "db").unwrap()
+        );
+        assert_eq!("# dev\n", String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn test_list_command() {
+        let mut output = Vec::new();
+
+        list(&mut ALIASES.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            "db\tdb.internal.example.com\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_env_command() {
+        let mut output = Vec::new();
+
+        env(&mut output, Path::new("/home/me/.hosts-aliases")).unwrap();
+        env(&mut output, Path::new("/home/J. O'Neil/.hosts-aliases")).unwrap();
+        assert_eq!(
+            "export HOSTALIASES=/home/me/.hosts-aliases\n\
+             export HOSTALIASES='/home/J. O'\\''Neil/.hosts-aliases'\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::{Read, Write};
+
+use hoster::dialect::Dialect;
+use hoster::lint::Linter;
+use hoster::parser::Parser;
+
+/// Prints every problem `dialect` has with the hosts file and returns how
+/// many were found.
+pub(crate) fn execute<R, W>(
+    reader: &mut R,
+    output: &mut W,
+    origin: &str,
+    dialect: Dialect,
+) -> Result<usize, Box<dyn std::error::Error>>
+where
+    R: Read,
+    W: Write,
+{
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let text = std::str::from_utf8(&data).map_err(|error| {
+        let offset = error.valid_up_to();
+        hoster::Error::located(
+            hoster::parser::Error::Utf8Error(error),
+            &data,
+            offset,
+            offset,
+        )
+    })?;
+    let document = Parser::<()>::builder()
+        .dialect(dialect)
+        .build()
+        .parse_document::<1>(text)?;
+
+    let lints = Linter::new(dialect).check(&document);
+
+    for lint in &lints {
+        match lint.line() {
+            Some(line) => writeln!(
+                output,
+                "{}:{}: warning[{}]: {}",
+                origin, line, dialect, lint.kind
+            )?,
+            None => writeln!(output, "{}: warning[{}]: {}", origin, dialect, lint.kind)?,
+        }
+    }
+
+    Ok(lints.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_check_against_dialect() {
+        let mut output = Cursor::new(Vec::new());
+        let mut reader =
+            Cursor::new("127.0.0.1 localhost\n::1 localhost\n0.0.0.0 app.localhost\n".to_string());
+
+        let found = execute(&mut reader, &mut output, "hosts", Dialect::MacOs).unwrap();
+
+        assert_eq!(1, found);
+        assert_eq!(
+            "hosts: warning[macos]: missing default entry `255.255.255.255 broadcasthost`\n",
+            String::from_utf8(output.into_inner()).unwrap()
+        );
+
+        let mut output = Cursor::new(Vec::new());
+        reader.set_position(0);
+
+        let found = execute(&mut reader, &mut output, "hosts", Dialect::Windows).unwrap();
+
+        assert_eq!(1, found);
+        assert_eq!(
+            "hosts:3: warning[windows]: `app.localhost` points at `0.0.0.0`, which is unreachable instead of this machine\n",
+            String::from_utf8(output.into_inner()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{Read, Write};
+
+use hoster::compression::Decoder;
+use hoster::cst::CstNode;
+use hoster::cursor::Navigate;
+use hoster::hostname;
+use hoster::parser::{InvalidLines, Parser};
+
+/// Appends the entries of `source`, a hosts file or blocklist that may be
+/// compressed, whose hostnames are not in `hosts` yet, and writes the result
+/// to `output`. Hostnames are appended normalized. Returns how many hostnames
+/// were added.
+pub(crate) fn execute<R, S, W>(
+    hosts: &mut R,
+    source: S,
+    name: &str,
+    output: &mut W,
+) -> Result<usize, Box<dyn Error>>
+where
+    R: Read,
+    S: Read,
+    W: Write,
+{
+    let mut data = Vec::new();
+    Decoder::new(source)?.read_to_end(&mut data)?;
+    let source = Parser::default().parse_bytes::<1>(&data)?;
+
+    data.clear();
+    hosts.read_to_end(&mut data)?;
+    let mut cst = Parser::<()>::builder()
+        .invalid_lines(InvalidLines::Keep)
+        .build()
+        .parse_bytes::<1>(&data)?;
+
+    let mut known = cst
+        .entries()
+        .map(|(_, host)| canonical(host))
+        .collect::<HashSet<_>>();
+    let mut comment = Some(format!(" Imported from {}", name));
+    let mut added = 0;
+    let mut cursor = source.cursor();
+
+    if matches!(cursor.node(), Some(CstNode::IP(_))) || cursor.next_entry() {
+        loop {
+            if let Some(entry) = cursor.entry() {
+                let hosts = entry
+                    .hosts
+                    .iter()
+                    .map(|host| canonical(host))
+                    .filter(|host| known.insert(host.clone()))
+                    .collect::<Vec<_>>();
+
+                if !hosts.is_empty() {
+                    added += hosts.len();
+                    cst.append_entry(entry.ip, hosts, comment.take().as_deref());
+                }
+            }
+
+            if !cursor.next_entry() {
+                break;
+            }
+        }
+    }
+
+    cst.write_to(output)?;
+
+    Ok(added)
+}
+
+fn canonical(host: &str) -> String {
+    hostname::normalize(host).unwrap_or_else(|_| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "127.0.0.1 localhost\n";
+    const BLOCKLIST: &str = "\
+# Blocklist
+0.0.0.0 ads.example Tracker.example
+0.0.0.0 LOCALHOST
+0.0.0.0 tracker.example.
+";
+
+    fn import(source: &[u8]) -> (usize, String) {
+        let mut output = Vec::new();
+        let added = execute(&mut HOSTS.as_bytes(), source, "list", &mut output).unwrap();
+
+        (added, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_import_command() {
+        assert_eq!(
+            (
+                2,
+                "127.0.0.1 localhost\n# Imported from list\n0.0.0.0 ads.example tracker.example\n"
+                    .to_string()
+            ),
+            import(BLOCKLIST.as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_import_command_nothing_new() {
+        assert_eq!((0, HOSTS.to_string()), import(b"0.0.0.0 localhost\n"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_import_command_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(BLOCKLIST.as_bytes()).unwrap();
+
+        assert_eq!(
+            import(BLOCKLIST.as_bytes()),
+            import(&encoder.finish().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_import_command_invalid_source() {
+        let mut output = Vec::new();
+        let error = execute(
+            &mut HOSTS.as_bytes(),
+            &b"nope ads.example\n"[..],
+            "list",
+            &mut output,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            Some(1),
+            error.downcast_ref::<hoster::Error>().unwrap().line()
+        );
+        assert!(output.is_empty());
+    }
+}
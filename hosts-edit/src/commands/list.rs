@@ -1,66 +1,66 @@
 use std::error::Error;
 use std::io::{Read, Write};
 
-use hoster::cst::CstNode;
+use hoster::cursor::Entry;
+use hoster::hostname;
+use hoster::metadata::Pair;
+use hoster::net::AddressFilter;
 use hoster::parser::Parser;
-use hoster::tokenizer::Tokenizer;
-use hoster::visitor::CstVisitor;
 
 pub(crate) fn execute<R, W>(
     reader: &mut R,
     output: &mut W,
     _with_comments: bool,
+    filter: &AddressFilter,
+    meta: &[Pair],
 ) -> Result<(), Box<dyn Error>>
 where
     R: Read,
     W: Write,
 {
-    let tokens = Tokenizer::new_with_reader(reader).parse()?.get_tokens();
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
 
-    let mut parser = Parser::builder()
-        .visitor(Visitor {
-            print_new_line: false,
-            output,
-        })
-        .build();
+    let cst = Parser::default().parse_bytes::<1>(&data)?;
 
-    let cst = parser.parse::<1>(tokens)?;
+    for entry in cst.cursor().entries() {
+        if filter.matches(entry.ip) && matches_metadata(&entry, meta) {
+            write_entry(output, &entry)?;
+        }
+    }
 
-    parser.visit(&cst);
     Ok(())
 }
 
-pub(crate) struct Visitor<W> {
-    print_new_line: bool,
-    output: W,
+/// Whether the entry has every one of the `meta` pairs.
+fn matches_metadata(entry: &Entry, meta: &[Pair]) -> bool {
+    if meta.is_empty() {
+        return true;
+    }
+
+    let metadata = entry.metadata();
+
+    meta.iter()
+        .all(|pair| metadata.get(&pair.key) == Some(&pair.value))
 }
 
-impl<W> CstVisitor for Visitor<W>
-where
-    W: Write,
-{
-    fn visit(&mut self, _idx: usize, node: &CstNode) -> Option<()> {
-        match node {
-            CstNode::Host(host) => {
-                self.print_new_line = true;
-                write!(self.output, "\t{}", host).unwrap();
-            }
-            CstNode::IP(ip) => {
-                write!(self.output, "{}", ip).unwrap();
-
-                self.print_new_line = false;
-            }
-            CstNode::NewLine => {
-                if self.print_new_line {
-                    self.print_new_line = false;
-                    writeln!(self.output).unwrap();
-                }
-            }
-            _ => {}
-        }
+/// Writes the address and the canonical form of each hostname, followed by
+/// its Unicode form when it is an internationalized name.
+fn write_entry<W: Write>(output: &mut W, entry: &Entry) -> std::io::Result<()> {
+    write!(output, "{}", entry.ip)?;
+
+    for host in &entry.hosts {
+        let ascii = hostname::normalize(host).unwrap_or_else(|_| host.to_string());
+        let unicode = hostname::to_unicode(&ascii);
 
-        Some(())
+        if unicode == ascii {
+            write!(output, "\t{}", ascii)?;
+        } else {
+            write!(output, "\t{} ({})", ascii, unicode)?;
+        }
     }
+
+    writeln!(output)
 }
 
 #[cfg(test)]
@@ -88,7 +88,13 @@ mod tests {
             .to_string(),
         );
 
-        let result = execute(&mut reader, &mut output, false);
+        let result = execute(
+            &mut reader,
+            &mut output,
+            false,
+            &AddressFilter::default(),
+            &[],
+        );
 
         assert!(result.is_ok());
 
@@ -104,4 +110,128 @@ mod tests {
             output
         )
     }
+
+    #[test]
+    fn test_list_shows_ace_and_unicode_forms() {
+        let mut output = Cursor::new(Vec::new());
+
+        let mut reader = Cursor::new(
+            "127.0.0.1\tbücher.de\n127.0.0.1\txn--mnchen-3ya.de localhost\n".to_string(),
+        );
+
+        let result = execute(
+            &mut reader,
+            &mut output,
+            false,
+            &AddressFilter::default(),
+            &[],
+        );
+
+        assert!(result.is_ok());
+
+        let output = String::from_utf8(output.into_inner()).unwrap();
+
+        assert_eq!(
+            "\
+127.0.0.1\txn--bcher-kva.de (bücher.de)
+127.0.0.1\txn--mnchen-3ya.de (münchen.de)\tlocalhost
+"
+            .to_string(),
+            output
+        )
+    }
+
+    #[test]
+    fn test_list_filters_by_address() {
+        let data = "\
+10.20.0.5\tdb.dev wiki.dev
+192.168.1.2 printer
+0.0.0.0 ads.example.com
+fd00::2 nas.lan
+";
+        let list = |filter: AddressFilter| {
+            let mut output = Cursor::new(Vec::new());
+
+            execute(&mut Cursor::new(data), &mut output, false, &filter, &[]).unwrap();
+            String::from_utf8(output.into_inner()).unwrap()
+        };
+
+        assert_eq!(
+            "10.20.0.5\tdb.dev\twiki.dev\n",
+            list(AddressFilter::new().cidr("10.20.0.0/16".parse().unwrap()))
+        );
+        assert_eq!(
+            "10.20.0.5\tdb.dev\twiki.dev\n192.168.1.2\tprinter\nfd00::2\tnas.lan\n",
+            list(AddressFilter::new().class("private".parse().unwrap()))
+        );
+        assert_eq!(
+            "0.0.0.0\tads.example.com\n",
+            list(AddressFilter::new().class(hoster::net::AddressClass::Sinkhole))
+        );
+    }
+
+    #[test]
+    fn test_list_filters_by_metadata() {
+        let data = "\
+# owner=payments
+10.1.2.3 db.dev # expires=2026-12-01 ticket=OPS-42
+10.1.2.4 cache.dev # owner=payments
+10.1.2.5 queue.dev # owner=\"core team\"
+";
+        let list = |meta: &[&str]| {
+            let meta = meta
+                .iter()
+                .map(|pair| pair.parse().unwrap())
+                .collect::<Vec<Pair>>();
+            let mut output = Cursor::new(Vec::new());
+
+            execute(
+                &mut Cursor::new(data),
+                &mut output,
+                false,
+                &AddressFilter::default(),
+                &meta,
+            )
+            .unwrap();
+            String::from_utf8(output.into_inner()).unwrap()
+        };
+
+        assert_eq!(
+            "10.1.2.3\tdb.dev\n10.1.2.4\tcache.dev\n",
+            list(&["owner=payments"])
+        );
+        assert_eq!(
+            "10.1.2.3\tdb.dev\n",
+            list(&["owner=payments", "ticket=OPS-42"])
+        );
+        assert_eq!("10.1.2.5\tqueue.dev\n", list(&["owner=\"core team\""]));
+        assert_eq!("", list(&["owner=nobody"]));
+    }
+
+    #[test]
+    fn test_list_reports_parse_error_location() {
+        let mut output = Cursor::new(Vec::new());
+        let mut reader = Cursor::new("127.0.0.1 localhost\nlocalhost 127.0.0.1\n".to_string());
+
+        let error = execute(
+            &mut reader,
+            &mut output,
+            false,
+            &AddressFilter::default(),
+            &[],
+        )
+        .unwrap_err();
+        let error = error.downcast_ref::<hoster::Error>().unwrap();
+
+        assert_eq!(Some(2), error.line());
+        assert_eq!(
+            "hosts:2:1: error: invalid IP address `localhost`\n",
+            error
+                .render(hoster::error::Format::Plain, Some("hosts"))
+                .lines()
+                .next()
+                .map(|line| format!("{}\n", line))
+                .unwrap()
+        );
+    }
 }
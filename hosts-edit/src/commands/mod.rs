@@ -1,3 +1,6 @@
 pub mod add;
+pub mod aliases;
+pub mod check;
+pub mod import;
 pub mod list;
 pub mod remove;
@@ -1,29 +1,34 @@
 use std::io::{Read, Write};
 
-use hoster::{cst::CstNode, parser::Parser, tokenizer::Tokenizer, visitor::CstVisitor};
+use hoster::{cst::CstNode, hostname, parser::Parser, visitor::CstVisitor};
 
+/// Writes the file without `host` to `writer`. Returns whether it was
+/// found; nothing is written when it was not.
 pub(crate) fn execute(
     reader: &mut impl Read,
     writer: &mut impl Write,
     host: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let tokens = Tokenizer::new_with_reader(reader).parse()?.get_tokens();
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
 
     let mut parser = Parser::builder()
         .visitor(Visitor::new(host.as_str()))
         .build();
 
-    let mut cst = parser.parse::<1>(tokens)?;
+    let mut cst = parser.parse_bytes::<1>(&data)?;
     parser.visit(&cst);
 
     let visitor = parser.get_visitor().unwrap();
 
-    if visitor.has_found() {
-        cst.remove_nodes(visitor.get_start().unwrap()..=visitor.get_end().unwrap());
-        writer.write_all(cst.to_string().as_bytes())?;
+    if !visitor.has_found() {
+        return Ok(false);
     }
 
-    Ok(())
+    cst.remove_nodes(visitor.get_start().unwrap()..=visitor.get_end().unwrap());
+    cst.write_to(writer)?;
+
+    Ok(true)
 }
 
 #[derive(Debug)]
@@ -83,7 +88,7 @@ impl<'a> CstVisitor for Visitor<'a> {
         }
 
         if let CstNode::Host(host) = node {
-            if host == self.host {
+            if hostname::eq(host, self.host) {
                 self.host_to_remove_pos = Some(idx);
             }
 
@@ -108,7 +113,7 @@ mod tests {
 
         let result = execute(&mut reader, &mut writer, "localhost".to_string());
 
-        assert!(result.is_ok());
+        assert!(result.unwrap());
 
         writer.set_position(0);
 
@@ -131,7 +136,7 @@ mod tests {
 
         let result = execute(&mut reader, &mut writer, "not-found-domain.com".to_string());
 
-        assert!(result.is_ok());
+        assert!(!result.unwrap());
 
         writer.set_position(0);
 
@@ -164,4 +169,33 @@ mod tests {
             "# here is my comment\n127.0.0.1 localhost\n127.0.0.1  other-domain.com\n".to_string()
         );
     }
+
+    #[test]
+    fn test_remove_command_matches_canonical_form() {
+        let mut reader = Cursor::new(Vec::from(
+            "127.0.0.1 Example.COM.\n127.0.0.1 xn--bcher-kva.de\n127.0.0.1 localhost\n".to_string(),
+        ));
+        let mut writer = Cursor::new(Vec::new());
+
+        let result = execute(&mut reader, &mut writer, "example.com".to_string());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "127.0.0.1 xn--bcher-kva.de\n127.0.0.1 localhost\n".to_string()
+        );
+
+        let mut reader = Cursor::new(Vec::from(
+            "127.0.0.1 xn--bcher-kva.de\n127.0.0.1 localhost\n".to_string(),
+        ));
+        let mut writer = Cursor::new(Vec::new());
+
+        let result = execute(&mut reader, &mut writer, "bücher.de".to_string());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "127.0.0.1 localhost\n".to_string()
+        );
+    }
 }
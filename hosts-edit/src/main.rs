@@ -1,7 +1,7 @@
 mod app;
 mod commands;
 
-use std::error::Error;
+use std::process::ExitCode;
 
 const LINUX_HOSTS_PATH: &str = "/etc/hosts";
 const MACOS_HOSTS_PATH: &str = "/etc/hosts";
@@ -20,6 +20,6 @@ const fn get_hosts_path() -> &'static str {
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> ExitCode {
     app::execute(get_hosts_path())
 }
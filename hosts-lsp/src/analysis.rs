@@ -0,0 +1,753 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::net::IpAddr;
+
+use hoster::cst::{Cst, CstNode};
+use hoster::cursor::Navigate;
+use hoster::document::Document;
+use hoster::hostname;
+use hoster::incremental::TextEdit;
+use hoster::parser::{InvalidLines, Parser};
+use hoster::span::Span;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, DiagnosticTag,
+    DocumentSymbol, Hover, HoverContents, MarkupContent, MarkupKind, NumberOrString, Position,
+    Range, SymbolKind, TextDocumentContentChangeEvent,
+};
+
+use crate::line_index::LineIndex;
+
+const SOURCE: &str = "hosts";
+
+/// What resolvers make of a hostname declaration.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Status {
+    /// The first declaration of the name for its address family.
+    Resolved,
+    /// The name was already declared with the same address on this line.
+    Duplicate(usize),
+    /// The name was already declared with another address of the same
+    /// family on this line, which resolvers return instead.
+    Shadowed(usize),
+}
+
+#[derive(Debug)]
+struct Declaration<'a> {
+    key: String,
+    host: &'a str,
+    ip: IpAddr,
+    span: Span,
+    status: Status,
+}
+
+/// An open hosts file and its parse.
+#[derive(Debug)]
+pub(crate) struct File {
+    text: String,
+    document: Document<1>,
+    index: LineIndex,
+}
+
+impl File {
+    pub(crate) fn new(text: String) -> Self {
+        Self {
+            document: parse(&text),
+            index: LineIndex::new(&text),
+            text,
+        }
+    }
+
+    /// Applies a change sent by the editor, reparsing only the lines it
+    /// touches.
+    pub(crate) fn change(&mut self, change: TextDocumentContentChangeEvent) {
+        let Some(range) = change.range else {
+            *self = Self::new(change.text);
+            return;
+        };
+
+        let start = self.index.offset(&self.text, range.start);
+        let end = self.index.offset(&self.text, range.end).max(start);
+        let edit = TextEdit::new(start..end, &change.text);
+
+        edit.apply(&mut self.text);
+        self.index = LineIndex::new(&self.text);
+
+        if parser()
+            .reparse(&mut self.document, &self.text, &edit)
+            .is_err()
+        {
+            self.document = parse(&self.text);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub(crate) fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (node, span) in self.document.iter() {
+            match node {
+                CstNode::Invalid(line) => diagnostics.push(self.invalid_line(line, span)),
+                CstNode::Host(host) if !hostname::is_valid(host) => {
+                    diagnostics.push(self.diagnostic(
+                        span,
+                        DiagnosticSeverity::ERROR,
+                        "invalid-hostname",
+                        format!("invalid hostname `{}`", host),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        for declaration in self.declarations() {
+            let (code, message) = match declaration.status {
+                Status::Resolved => continue,
+                Status::Duplicate(line) => (
+                    "duplicate",
+                    format!(
+                        "`{}` already points at `{}` on line {}",
+                        declaration.host, declaration.ip, line
+                    ),
+                ),
+                Status::Shadowed(line) => (
+                    "shadowed",
+                    format!(
+                        "`{}` is shadowed by line {}, resolvers never return `{}` for it",
+                        declaration.host, line, declaration.ip
+                    ),
+                ),
+            };
+            let mut diagnostic = self.diagnostic(
+                &declaration.span,
+                DiagnosticSeverity::WARNING,
+                code,
+                message,
+            );
+
+            if code == "duplicate" {
+                diagnostic.tags = Some(vec![DiagnosticTag::UNNECESSARY]);
+            }
+
+            diagnostics.push(diagnostic);
+        }
+
+        diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        diagnostics
+    }
+
+    /// Every address the hostname under `position` is declared with.
+    pub(crate) fn hover(&self, position: Position) -> Option<Hover> {
+        let (host, span) = self.host_at(position)?;
+        let key = key(host);
+        let mut value = format!("**{}**\n", host);
+
+        for declaration in self.declarations() {
+            if declaration.key != key {
+                continue;
+            }
+
+            match declaration.status {
+                Status::Resolved => {
+                    let _ = writeln!(
+                        value,
+                        "- `{}` (line {})",
+                        declaration.ip, declaration.span.line
+                    );
+                }
+                Status::Shadowed(line) => {
+                    let _ = writeln!(
+                        value,
+                        "- ~~`{}`~~ (line {}, shadowed by line {})",
+                        declaration.ip, declaration.span.line, line
+                    );
+                }
+                Status::Duplicate(_) => {}
+            }
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(self.range(span)),
+        })
+    }
+
+    /// The first declaration of the hostname under `position`.
+    pub(crate) fn definition(&self, position: Position) -> Option<Range> {
+        let (host, _) = self.host_at(position)?;
+        let key = key(host);
+
+        self.declarations()
+            .into_iter()
+            .find(|declaration| declaration.key == key)
+            .map(|declaration| self.range(&declaration.span))
+    }
+
+    /// One symbol per block of lines, holding its entries. A block is named
+    /// after its first comment, if it starts with one.
+    pub(crate) fn symbols(&self) -> Vec<DocumentSymbol> {
+        let cst = &self.document.cst;
+        let mut cursor = cst.cursor();
+        let mut symbols = Vec::new();
+
+        loop {
+            let block = cursor.block_range();
+
+            if let Some(symbol) = self.block_symbol(cst, block.clone()) {
+                symbols.push(symbol);
+            }
+
+            if block.end >= cst.nodes.len() {
+                break;
+            }
+
+            cursor.set_index(block.end);
+        }
+
+        symbols
+    }
+
+    /// Hostnames used in the file, each once.
+    pub(crate) fn completions(&self) -> Vec<CompletionItem> {
+        self.declarations()
+            .into_iter()
+            .filter(|declaration| declaration.status == Status::Resolved)
+            .filter(|declaration| hostname::is_valid(declaration.host))
+            .fold(Vec::<CompletionItem>::new(), |mut items, declaration| {
+                if !items.iter().any(|item| item.label == declaration.host) {
+                    items.push(CompletionItem {
+                        label: declaration.host.to_string(),
+                        kind: Some(CompletionItemKind::VALUE),
+                        detail: Some(declaration.ip.to_string()),
+                        ..CompletionItem::default()
+                    });
+                }
+
+                items
+            })
+    }
+
+    /// Edits turning the file into its formatted form, `None` if it already
+    /// is. A leading byte order mark is kept.
+    pub(crate) fn format(&self) -> Option<Vec<lsp_types::TextEdit>> {
+        let mut formatted = format(&self.document.cst);
+
+        if self.text.starts_with('\u{feff}') {
+            formatted.insert(0, '\u{feff}');
+        }
+
+        (formatted != self.text).then(|| {
+            vec![lsp_types::TextEdit::new(
+                Range::new(
+                    Position::new(0, 0),
+                    self.index.position(&self.text, self.text.len()),
+                ),
+                formatted,
+            )]
+        })
+    }
+
+    fn range(&self, span: &Span) -> Range {
+        self.index.range(&self.text, span)
+    }
+
+    /// Range of the nodes `nodes`, without their final line feed.
+    fn nodes_range(&self, nodes: std::ops::Range<usize>) -> Range {
+        let spans = &self.document.spans;
+        let end = (nodes.start..nodes.end)
+            .rev()
+            .find(|&i| {
+                !matches!(
+                    self.document.cst.nodes[i],
+                    CstNode::NewLine | CstNode::CarriageReturn
+                )
+            })
+            .unwrap_or(nodes.start);
+
+        self.range(&Span::new(spans[nodes.start].start, spans[end].end, 0))
+    }
+
+    fn diagnostic(
+        &self,
+        span: &Span,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            range: self.range(span),
+            severity: Some(severity),
+            code: Some(NumberOrString::String(code.to_string())),
+            source: Some(SOURCE.to_string()),
+            message,
+            ..Diagnostic::default()
+        }
+    }
+
+    /// Parses a line the parser kept as invalid on its own, to find out why.
+    fn invalid_line(&self, line: &str, span: &Span) -> Diagnostic {
+        let (message, span) = match Parser::default().parse_str::<1>(line) {
+            Err(error) => (
+                error.kind().to_string(),
+                error
+                    .span()
+                    .map_or(*span, |error| error.shift(span.start, 0)),
+            ),
+            Ok(_) => ("invalid line".to_string(), *span),
+        };
+
+        self.diagnostic(&span, DiagnosticSeverity::ERROR, "invalid-line", message)
+    }
+
+    fn host_at(&self, position: Position) -> Option<(&str, &Span)> {
+        let offset = self.index.offset(&self.text, position);
+        let first = self
+            .document
+            .spans
+            .partition_point(|span| span.end < offset);
+
+        (first..first + 2).find_map(|i| {
+            match (self.document.cst.nodes.get(i)?, self.document.span(i)?) {
+                (CstNode::Host(host), span) if span.start <= offset && offset <= span.end => {
+                    Some((host.as_str(), span))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn declarations(&self) -> Vec<Declaration<'_>> {
+        let mut declarations: Vec<Declaration<'_>> = Vec::new();
+        let mut first: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut ip = None;
+
+        for (node, span) in self.document.iter() {
+            let host = match node {
+                CstNode::IP(address) => {
+                    ip = Some(*address);
+                    continue;
+                }
+                CstNode::NewLine => {
+                    ip = None;
+                    continue;
+                }
+                CstNode::Host(host) => host,
+                _ => continue,
+            };
+            let Some(ip) = ip else {
+                continue;
+            };
+            let key = key(host);
+            let earlier = first.entry(key.clone()).or_default();
+            let same = |other: &Declaration<'_>| other.ip == ip;
+            let family = |other: &Declaration<'_>| other.ip.is_ipv4() == ip.is_ipv4();
+
+            let status = if let Some(&index) = earlier.iter().find(|&&i| same(&declarations[i])) {
+                Status::Duplicate(declarations[index].span.line)
+            } else if let Some(&index) = earlier.iter().find(|&&i| family(&declarations[i])) {
+                Status::Shadowed(declarations[index].span.line)
+            } else {
+                earlier.push(declarations.len());
+                Status::Resolved
+            };
+
+            declarations.push(Declaration {
+                key,
+                host,
+                ip,
+                span: *span,
+                status,
+            });
+        }
+
+        declarations
+    }
+
+    #[allow(deprecated)]
+    fn block_symbol<const LENGTH: usize>(
+        &self,
+        cst: &Cst<LENGTH>,
+        block: std::ops::Range<usize>,
+    ) -> Option<DocumentSymbol> {
+        let mut cursor = cst.cursor();
+        let mut name = None;
+        let mut children = Vec::new();
+
+        cursor.set_index(block.start);
+
+        while cursor.index() < block.end {
+            match cursor.entry() {
+                Some(entry) => {
+                    let range = self.nodes_range(entry.range);
+
+                    children.push(DocumentSymbol {
+                        name: match entry.hosts.is_empty() {
+                            true => entry.ip.to_string(),
+                            false => entry.hosts.join(" "),
+                        },
+                        detail: Some(entry.ip.to_string()),
+                        kind: SymbolKind::KEY,
+                        tags: None,
+                        deprecated: None,
+                        range,
+                        selection_range: range,
+                        children: None,
+                    });
+                }
+                None if children.is_empty() && name.is_none() => {
+                    name = cursor.line().iter().find_map(|node| match node {
+                        CstNode::Comment(comment) => Some(comment.trim().to_string()),
+                        _ => None,
+                    });
+                }
+                None => {}
+            }
+
+            if !cursor.next_line() {
+                break;
+            }
+        }
+
+        let first = children.first()?;
+        let range = self.nodes_range(block);
+
+        Some(DocumentSymbol {
+            name: name.filter(|name| !name.is_empty()).unwrap_or_else(|| {
+                format!(
+                    "{} {}",
+                    first.detail.as_deref().unwrap_or_default(),
+                    first.name
+                )
+            }),
+            detail: None,
+            kind: SymbolKind::NAMESPACE,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: range,
+            children: Some(children),
+        })
+    }
+}
+
+fn parser() -> Parser<()> {
+    Parser::builder().invalid_lines(InvalidLines::Keep).build()
+}
+
+fn parse(text: &str) -> Document<1> {
+    // Invalid lines are kept, so only a bug in the parser ends up here.
+    parser().parse_document(text).unwrap_or_else(|_| Document {
+        cst: Cst {
+            nodes: Default::default(),
+        },
+        spans: Vec::new(),
+    })
+}
+
+/// Names that cannot be normalized are only lowercased.
+fn key(host: &str) -> String {
+    hostname::normalize(host).unwrap_or_else(|_| host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+enum Line<'a> {
+    Entry {
+        ip: IpAddr,
+        hosts: Vec<&'a str>,
+        comment: Option<&'a str>,
+    },
+    Comment(&'a str),
+    Invalid(&'a str),
+    Blank,
+}
+
+/// Formats a hosts file: in each block the hostnames line up after the
+/// widest address, words are one space apart, runs of blank lines shrink
+/// to one and trailing whitespace goes. Invalid lines are left alone.
+fn format<const LENGTH: usize>(cst: &Cst<LENGTH>) -> String {
+    let mut lines = Vec::new();
+    let mut default_ending = None;
+
+    for nodes in cst.nodes.split_inclusive(|node| *node == CstNode::NewLine) {
+        let mut line = Line::Blank;
+        let mut crlf = false;
+
+        for node in nodes {
+            match node {
+                CstNode::IP(ip) => {
+                    line = Line::Entry {
+                        ip: *ip,
+                        hosts: Vec::new(),
+                        comment: None,
+                    }
+                }
+                CstNode::Host(host) => {
+                    if let Line::Entry { hosts, .. } = &mut line {
+                        hosts.push(host);
+                    }
+                }
+                CstNode::Comment(text) => match &mut line {
+                    Line::Entry { comment, .. } => *comment = Some(text),
+                    _ => line = Line::Comment(text),
+                },
+                CstNode::Invalid(text) => {
+                    crlf |= text.ends_with('\r');
+                    line = Line::Invalid(text.strip_suffix('\r').unwrap_or(text));
+                }
+                CstNode::CarriageReturn => crlf = true,
+                CstNode::Space | CstNode::Tab | CstNode::NewLine => {}
+            }
+        }
+
+        let ending = match nodes.last() {
+            Some(CstNode::NewLine) => Some(if crlf { "\r\n" } else { "\n" }),
+            _ => None,
+        };
+
+        default_ending = default_ending.or(ending);
+        lines.push((line, ending));
+    }
+
+    let mut output = String::new();
+    let mut widths = vec![0; lines.len()];
+    let mut block_start = 0;
+
+    for i in 0..=lines.len() {
+        if i < lines.len() && !matches!(lines[i].0, Line::Blank) {
+            continue;
+        }
+
+        let width = lines[block_start..i]
+            .iter()
+            .filter_map(|(line, _)| match line {
+                Line::Entry { ip, .. } => Some(ip.to_string().len()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        widths[block_start..i].fill(width);
+        block_start = i + 1;
+    }
+
+    let mut blank = true;
+
+    for ((line, ending), width) in lines.iter().zip(widths) {
+        let ending = ending.or(default_ending).unwrap_or("\n");
+
+        match line {
+            Line::Blank if blank => continue,
+            Line::Blank => {}
+            Line::Entry { ip, hosts, comment } => {
+                let _ = write!(output, "{:<width$}", ip.to_string(), width = width);
+
+                for host in hosts {
+                    output.push(' ');
+                    output.push_str(host);
+                }
+
+                if let Some(comment) = comment {
+                    let _ = write!(output, " #{}", comment.trim_end());
+                }
+
+                if hosts.is_empty() && comment.is_none() {
+                    output.truncate(output.trim_end().len());
+                }
+            }
+            Line::Comment(comment) => {
+                let _ = write!(output, "#{}", comment.trim_end());
+            }
+            Line::Invalid(text) => output.push_str(text.trim_end()),
+        }
+
+        blank = matches!(line, Line::Blank);
+        output.push_str(ending);
+    }
+
+    if blank {
+        let trimmed = output.trim_end_matches(['\r', '\n']).len();
+
+        if trimmed < output.len() {
+            let ending = if output[trimmed..].starts_with('\r') {
+                "\r\n"
+            } else {
+                "\n"
+            };
+
+            output.truncate(trimmed);
+
+            if !output.is_empty() {
+                output.push_str(ending);
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "\
+# Development
+127.0.0.1   localhost api.test
+::1 localhost
+10.0.0.5 db.test
+10.0.0.6 db.test # moved
+127.0.0.1 api.test
+
+0.0.0.0 ads.example.com
+bad.address tracker.test
+0.0.0.0 bad_host!
+";
+
+    fn file() -> File {
+        File::new(HOSTS.to_string())
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostics = file()
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.range.start.line,
+                    diagnostic.range.start.character,
+                    diagnostic.message,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (
+                    4,
+                    9,
+                    "`db.test` is shadowed by line 4, resolvers never return `10.0.0.6` for it"
+                        .to_string()
+                ),
+                (
+                    5,
+                    10,
+                    "`api.test` already points at `127.0.0.1` on line 2".to_string()
+                ),
+                (8, 0, "invalid IP address `bad.address`".to_string()),
+                (9, 8, "invalid hostname `bad_host!`".to_string()),
+            ],
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn test_hover_and_definition() {
+        let file = file();
+        let hover = file.hover(Position::new(2, 6)).unwrap();
+
+        match hover.contents {
+            HoverContents::Markup(markup) => assert_eq!(
+                "**localhost**\n- `127.0.0.1` (line 2)\n- `::1` (line 3)\n",
+                markup.value
+            ),
+            contents => panic!("unexpected hover {:?}", contents),
+        }
+        assert_eq!(
+            Some(Range::new(Position::new(2, 4), Position::new(2, 13))),
+            hover.range
+        );
+
+        assert_eq!(
+            Some(Range::new(Position::new(1, 22), Position::new(1, 30))),
+            file.definition(Position::new(5, 12))
+        );
+        assert_eq!(None, file.definition(Position::new(5, 2)));
+    }
+
+    #[test]
+    fn test_symbols_and_completions() {
+        let symbols = file().symbols();
+
+        assert_eq!(
+            vec!["Development", "0.0.0.0 ads.example.com"],
+            symbols
+                .iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(5, symbols[0].children.as_ref().unwrap().len());
+        assert_eq!(Position::new(9, 17), symbols[1].range.end);
+
+        let completions = file().completions();
+
+        assert_eq!(
+            vec!["localhost", "api.test", "db.test", "ads.example.com"],
+            completions
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let file = File::new(
+            "\n#  Development  \n127.0.0.1\tlocalhost   api.test  \n::1 localhost #v6\r\n\n\n\
+             10.0.0.5 db.test\n not an entry \n\n"
+                .to_string(),
+        );
+        let edits = file.format().unwrap();
+
+        assert_eq!(
+            "\
+#  Development
+127.0.0.1 localhost api.test
+::1       localhost #v6\r
+
+10.0.0.5 db.test
+ not an entry
+",
+            edits[0].new_text
+        );
+        assert_eq!(Position::new(9, 0), edits[0].range.end);
+        assert!(File::new(edits[0].new_text.clone()).format().is_none());
+    }
+
+    #[test]
+    fn test_format_keeps_bom() {
+        let formatted = "\u{feff}127.0.0.1 localhost\n".to_string();
+
+        assert!(File::new(formatted).format().is_none());
+
+        let edits = File::new("\u{feff}127.0.0.1  localhost\n".to_string())
+            .format()
+            .unwrap();
+
+        assert_eq!("\u{feff}127.0.0.1 localhost\n", edits[0].new_text);
+    }
+
+    #[test]
+    fn test_change() {
+        let mut file = file();
+
+        file.change(TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(4, 0), Position::new(5, 0))),
+            range_length: None,
+            text: String::new(),
+        });
+        file.change(TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(0, 2), Position::new(0, 13))),
+            range_length: None,
+            text: "Local".to_string(),
+        });
+
+        assert!(file.text().starts_with("# Local\n"));
+        assert_eq!(3, file.diagnostics().len());
+        assert_eq!("Local", file.symbols()[0].name);
+    }
+}
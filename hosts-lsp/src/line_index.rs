@@ -0,0 +1,75 @@
+use hoster::span::Span;
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets and LSP positions, whose columns count
+/// UTF-16 code units.
+#[derive(Debug, Clone)]
+pub(crate) struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+
+        Self { starts }
+    }
+
+    pub(crate) fn position(&self, text: &str, offset: usize) -> Position {
+        let offset = offset.min(text.len());
+        let line = self.starts.partition_point(|start| *start <= offset) - 1;
+        let character = text[self.starts[line]..offset].encode_utf16().count();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    pub(crate) fn range(&self, text: &str, span: &Span) -> Range {
+        Range::new(
+            self.position(text, span.start),
+            self.position(text, span.end),
+        )
+    }
+
+    /// Byte offset of `position`. Positions past the end of a line or of
+    /// the text are clamped to it.
+    pub(crate) fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(&start) = self.starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let line = &text[start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let mut units = 0;
+
+        for (i, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+
+            units += c.len_utf16();
+        }
+
+        start + line.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_columns() {
+        let text = "# bücher 🦀\n127.0.0.1 host\n";
+        let index = LineIndex::new(text);
+
+        assert_eq!(Position::new(0, 9), index.position(text, 10));
+        assert_eq!(10, index.offset(text, Position::new(0, 9)));
+        assert_eq!(Position::new(0, 11), index.position(text, 14));
+        assert_eq!(14, index.offset(text, Position::new(0, 11)));
+        assert_eq!(Position::new(1, 5), index.position(text, 20));
+        assert_eq!(20, index.offset(text, Position::new(1, 5)));
+        assert_eq!(29, index.offset(text, Position::new(1, 99)));
+        assert_eq!(text.len(), index.offset(text, Position::new(9, 0)));
+        assert_eq!(Position::new(2, 0), index.position(text, text.len()));
+    }
+}
@@ -0,0 +1,25 @@
+mod analysis;
+mod line_index;
+mod server;
+
+use lsp_server::Connection;
+
+use crate::server::{BoxError, Server};
+
+fn main() -> Result<(), BoxError> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(server::capabilities())?;
+
+    if let Err(error) = connection.initialize(capabilities) {
+        if error.channel_is_disconnected() {
+            io_threads.join()?;
+        }
+
+        return Err(error.into());
+    }
+
+    Server::new(connection).run()?;
+    io_threads.join()?;
+
+    Ok(())
+}
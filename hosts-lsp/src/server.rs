@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest,
+    Request as RequestTrait,
+};
+use lsp_types::{
+    CompletionOptions, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentFormattingParams,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    HoverParams, HoverProviderCapability, Location, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::analysis::File;
+
+pub(crate) type BoxError = Box<dyn Error + Sync + Send>;
+
+pub(crate) fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Serves requests on `connection` until the client shuts the server down.
+pub(crate) struct Server {
+    connection: Connection,
+    files: HashMap<Uri, File>,
+}
+
+impl Server {
+    pub(crate) fn new(connection: Connection) -> Self {
+        Self {
+            connection,
+            files: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn run(mut self) -> Result<(), BoxError> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.request(request);
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => handle(request, |params: HoverParams| {
+                let position = params.text_document_position_params;
+
+                self.file(&position.text_document.uri)?
+                    .hover(position.position)
+            }),
+            GotoDefinition::METHOD => handle(request, |params: GotoDefinitionParams| {
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let range = self.file(&uri)?.definition(position.position)?;
+
+                Some(GotoDefinitionResponse::Scalar(Location::new(uri, range)))
+            }),
+            DocumentSymbolRequest::METHOD => handle(request, |params: DocumentSymbolParams| {
+                let symbols = self.file(&params.text_document.uri)?.symbols();
+
+                Some(DocumentSymbolResponse::Nested(symbols))
+            }),
+            Completion::METHOD => handle(request, |params: CompletionParams| {
+                let position = params.text_document_position;
+                let items = self.file(&position.text_document.uri)?.completions();
+
+                Some(CompletionResponse::Array(items))
+            }),
+            Formatting::METHOD => handle(request, |params: DocumentFormattingParams| {
+                self.file(&params.text_document.uri)?.format()
+            }),
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request `{}`", method),
+                )
+            }
+        };
+
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+        }
+    }
+
+    /// Handles `notification`. Malformed parameters are logged and the
+    /// notification ignored, as there is no response to report them in;
+    /// only failing to talk to the client is an error.
+    fn notification(&mut self, notification: Notification) -> Result<(), BoxError> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = params::<DidOpenTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+
+                self.files
+                    .insert(document.uri.clone(), File::new(document.text));
+                self.publish(document.uri, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) = params::<DidChangeTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+
+                if let Some(file) = self.files.get_mut(&document.uri) {
+                    for change in params.content_changes {
+                        file.change(change);
+                    }
+                }
+
+                self.publish(document.uri, Some(document.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = params::<DidCloseTextDocumentParams>(notification) else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+
+                self.files.remove(&uri);
+                self.send_diagnostics(PublishDiagnosticsParams::new(uri, Vec::new(), None))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn file(&self, uri: &Uri) -> Option<&File> {
+        self.files.get(uri)
+    }
+
+    fn publish(&self, uri: Uri, version: Option<i32>) -> Result<(), BoxError> {
+        let diagnostics = match self.file(&uri) {
+            Some(file) => file.diagnostics(),
+            None => return Ok(()),
+        };
+
+        self.send_diagnostics(PublishDiagnosticsParams::new(uri, diagnostics, version))
+    }
+
+    fn send_diagnostics(&self, params: PublishDiagnosticsParams) -> Result<(), BoxError> {
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+
+        Ok(self.connection.sender.send(notification.into())?)
+    }
+}
+
+/// The parameters of `notification`, `None` after logging why they do not
+/// parse.
+fn params<P>(notification: Notification) -> Option<P>
+where
+    P: DeserializeOwned,
+{
+    match serde_json::from_value(notification.params) {
+        Ok(params) => Some(params),
+        Err(error) => {
+            eprintln!(
+                "hosts-lsp: ignoring malformed `{}` notification: {}",
+                notification.method, error
+            );
+            None
+        }
+    }
+}
+
+/// Runs `handler` on the parameters of `request`. Requests on files that
+/// are not open get a `null` result.
+fn handle<P, R>(
+    request: Request,
+    handler: impl FnOnce(P) -> Option<R>,
+) -> Result<serde_json::Value, serde_json::Error>
+where
+    P: DeserializeOwned,
+    R: Serialize,
+{
+    let params = serde_json::from_value(request.params)?;
+
+    serde_json::to_value(handler(params))
+}
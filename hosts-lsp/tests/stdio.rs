@@ -0,0 +1,244 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///etc/hosts.d/dev";
+
+/// Talks JSON-RPC to the server binary over its standard input and output.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    notifications: Vec<Value>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hosts-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+            notifications: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+
+            match header.trim_end() {
+                "" => break,
+                header => {
+                    if let Some(value) = header.strip_prefix("Content-Length: ") {
+                        length = value.parse().unwrap();
+                    }
+                }
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Sends a request and waits for its response, keeping the
+    /// notifications that arrive meanwhile.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.receive();
+
+            if message["id"] == json!(id) {
+                return message;
+            }
+
+            self.notifications.push(message);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn notification(&mut self, method: &str) -> Value {
+        if let Some(index) = self
+            .notifications
+            .iter()
+            .position(|message| message["method"] == method)
+        {
+            return self.notifications.remove(index);
+        }
+
+        loop {
+            let message = self.receive();
+
+            if message["method"] == method {
+                return message;
+            }
+        }
+    }
+
+    fn shutdown(mut self) {
+        let response = self.request("shutdown", Value::Null);
+        assert_eq!(Value::Null, response["result"]);
+
+        self.notify("exit", Value::Null);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn position(line: u32, character: u32) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+#[test]
+fn test_session() {
+    let mut client = Client::start();
+
+    let initialize = client.request("initialize", json!({ "capabilities": {} }));
+    let capabilities = &initialize["result"]["capabilities"];
+    assert_eq!(json!(2), capabilities["textDocumentSync"]);
+    assert_eq!(json!(true), capabilities["documentFormattingProvider"]);
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": URI,
+                "languageId": "hosts",
+                "version": 1,
+                "text": "# Dev\n127.0.0.1  api.test\n10.0.0.1 api.test\nnope api.test\n",
+            }
+        }),
+    );
+
+    let diagnostics = client.notification("textDocument/publishDiagnostics");
+    let messages = diagnostics["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|diagnostic| diagnostic["message"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "`api.test` is shadowed by line 2, resolvers never return `10.0.0.1` for it",
+            "invalid IP address `nope`",
+        ],
+        messages
+    );
+    assert_eq!(json!(1), diagnostics["params"]["version"]);
+
+    let hover = client.request("textDocument/hover", position(2, 10));
+    assert_eq!(
+        "**api.test**\n- `127.0.0.1` (line 2)\n- ~~`10.0.0.1`~~ (line 3, shadowed by line 2)\n",
+        hover["result"]["contents"]["value"]
+    );
+
+    let definition = client.request("textDocument/definition", position(2, 10));
+    assert_eq!(
+        json!({
+            "uri": URI,
+            "range": {
+                "start": { "line": 1, "character": 11 },
+                "end": { "line": 1, "character": 19 },
+            }
+        }),
+        definition["result"]
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert_eq!(json!("Dev"), symbols["result"][0]["name"]);
+    assert_eq!(
+        2,
+        symbols["result"][0]["children"].as_array().unwrap().len()
+    );
+
+    let completion = client.request("textDocument/completion", position(3, 5));
+    assert_eq!(
+        json!([{ "label": "api.test", "kind": 12, "detail": "127.0.0.1" }]),
+        completion["result"]
+    );
+
+    // Fixing the broken line through an incremental change.
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{
+                "range": {
+                    "start": { "line": 2, "character": 0 },
+                    "end": { "line": 3, "character": 4 },
+                },
+                "text": "::1",
+            }],
+        }),
+    );
+
+    let diagnostics = client.notification("textDocument/publishDiagnostics");
+    assert_eq!(json!(2), diagnostics["params"]["version"]);
+    assert_eq!(json!([]), diagnostics["params"]["diagnostics"]);
+
+    let formatting = client.request(
+        "textDocument/formatting",
+        json!({
+            "textDocument": { "uri": URI },
+            "options": { "tabSize": 4, "insertSpaces": true },
+        }),
+    );
+    assert_eq!(
+        json!("# Dev\n127.0.0.1 api.test\n::1       api.test\n"),
+        formatting["result"][0]["newText"]
+    );
+
+    let unknown = client.request("textDocument/rename", json!({}));
+    assert_eq!(json!(-32601), unknown["error"]["code"]);
+
+    client.shutdown();
+}
+
+#[test]
+fn test_malformed_notifications_are_ignored() {
+    let mut client = Client::start();
+
+    client.request("initialize", json!({ "capabilities": {} }));
+    client.notify("initialized", json!({}));
+
+    client.notify("textDocument/didOpen", json!({ "textDocument": 42 }));
+    client.notify("textDocument/didChange", json!({}));
+    client.notify("textDocument/didClose", Value::Null);
+
+    // The server is still there to answer.
+    let hover = client.request("textDocument/hover", position(0, 0));
+    assert_eq!(Value::Null, hover["result"]);
+
+    client.shutdown();
+}